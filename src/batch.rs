@@ -0,0 +1,335 @@
+// Frames made without the interactive view, for scripts and other
+// programs: one printed to stdout, a recording saved to a file, or a run
+// timed to see how fast the renderer is
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+use std::time::Instant;
+
+use clap::ValueEnum;
+
+use crate::camera::Camera;
+use crate::camerapath::CameraPath;
+use crate::color::ColorSupport;
+use crate::framebuffer::FrameBuffer;
+use crate::gif::Gif;
+use crate::output::{write_ansi, write_html};
+use crate::pixels::{render_bitmap, Bitmap, DEFAULT_CELL_PIXELS};
+use crate::replay;
+use crate::render::{render_frame, Projection, RenderSettings};
+use crate::runstats::RunStats;
+use crate::scene::Scene;
+use crate::screenshot::{save_png, save_svg};
+use crate::script::Script;
+use crate::spline::CameraSpline;
+
+// The page `html` saves, around the frames as a list of strings
+const HTML_START: &str = "<!DOCTYPE html>
+<html>
+<head>
+<meta charset=\"utf-8\">
+<title>teruminator</title>
+<style>
+body { margin: 0; min-height: 100vh; display: flex; align-items: center; justify-content: center; background: #202020; }
+pre { margin: 0; padding: 16px; border-radius: 8px; background: #000; color: #ccc; font: 14px/1.15 monospace; cursor: pointer; }
+</style>
+</head>
+<body>
+<pre id=\"screen\"></pre>
+<script>
+const frames = [
+";
+const HTML_END: &str = "];
+const screen = document.getElementById(\"screen\");
+let frame = 0;
+let playing = true;
+function show() {
+    screen.innerHTML = frames[frame];
+    if (playing) frame = (frame + 1) % frames.length;
+}
+screen.addEventListener(\"click\", () => playing = !playing);
+show();
+setInterval(show, 1000 / FPS);
+</script>
+</body>
+</html>
+";
+
+// Frames `bench` renders, and the animation time between two of them
+const BENCH_FRAMES: usize = 300;
+const BENCH_STEP: f32 = 1.0 / 30.0;
+
+// What `record` saves
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum Recording {
+    // An asciicast for asciinema
+    Cast,
+    // An animated GIF of the pixel renderer's frames
+    Gif,
+    // The same frames as numbered PNGs, for putting together with ffmpeg
+    PngSeq,
+    // A web page playing the frames, needing nothing else
+    Html,
+    // The text and escape sequences of each frame with its time, for play
+    Ansi,
+}
+
+impl Recording {
+    // What the file is called when --out doesn't say
+    pub fn default_path(self) -> &'static str {
+        match self {
+            Recording::Cast => "teruminator.cast",
+            Recording::Gif => "teruminator.gif",
+            Recording::PngSeq => "teruminator-####.png",
+            Recording::Html => "teruminator.html",
+            Recording::Ansi => "teruminator.ansi",
+        }
+    }
+}
+
+pub struct Batch<'a> {
+    pub scene: Scene,
+    // Binds the scene file's animated properties
+    pub script: Script,
+    pub settings: &'a RenderSettings,
+    pub proj: Projection,
+    pub colors: ColorSupport,
+    // Where the camera is before the first cut, and the cuts a timeline
+    // makes with when they happen
+    pub camera: Camera,
+    pub cuts: Vec<(f32, Camera)>,
+    // The scene file's move along a curve, taking over from them
+    pub spline: Option<CameraSpline>,
+    // Camera moves to follow instead of either, from --camera-path
+    pub path: Option<CameraPath>,
+    // How long the frames took and how much was saved, for --stats-json
+    pub stats: RunStats,
+}
+
+impl Batch<'_> {
+    // Move everything to where it is at time `t`
+    fn pose(&mut self, t: f32) {
+        self.script.update(&mut self.scene, t);
+        self.proj.camera = match (&self.path, &self.spline, &self.scene.camera) {
+            (Some(path), _, _) => path.at(t),
+            (None, Some(spline), _) => spline.at(t),
+            (None, None, Some(aim)) => aim.camera(),
+            (None, None, None) => self.cuts.iter().rev().find(|(time, _)| *time <= t).map_or(self.camera, |(_, camera)| *camera),
+        };
+    }
+
+    fn frame(&mut self, t: f32) -> FrameBuffer {
+        let start = Instant::now();
+        self.pose(t);
+        let frame = render_frame(&self.scene, &self.proj, t, self.settings);
+        self.stats.frame_time(start.elapsed());
+        self.stats.frame_cells(self.proj.width * self.proj.height);
+        frame
+    }
+
+    // The pixel renderer's frame at time `t`
+    fn bitmap(&mut self, t: f32) -> Bitmap {
+        let start = Instant::now();
+        self.pose(t);
+        let image = render_bitmap(&self.scene, &self.proj, t, self.settings, DEFAULT_CELL_PIXELS);
+        self.stats.frame_time(start.elapsed());
+        self.stats.frame_cells(self.proj.width * self.proj.height);
+        image
+    }
+
+    // Count what went into the file at `path`
+    fn saved(&mut self, path: &Path) {
+        self.stats.bytes_written(std::fs::metadata(path).map_or(0, |m| m.len()));
+    }
+
+    // `frames` frames `step` seconds of animation apart from `start`, as
+    // lines of text with escape sequences for the colors and an empty line
+    // after each. Only the arguments decide what they look like, never the
+    // clock
+    pub fn render(&mut self, out: &mut impl Write, start: f32, step: f32, frames: usize) -> std::io::Result<()> {
+        for i in 0..frames {
+            write_ansi(out, &self.frame(start + i as f32 * step), &self.settings.ramp, self.colors)?;
+            writeln!(out)?;
+        }
+        out.flush()
+    }
+
+    // The same frames each saved to its own file, named `pattern` with its
+    // run of # replaced by the frame number, counting from 0 and padded with
+    // zeros to as many digits. Returns what was saved
+    pub fn render_files(&mut self, pattern: &str, start: f32, step: f32, frames: usize) -> Result<String, String> {
+        self.files(pattern, start, step, frames, Self::save, "saved")
+    }
+
+    // Frames saved one to a file by `save` the way `render_files` names them,
+    // told as `done` to them
+    fn files(&mut self, pattern: &str, start: f32, step: f32, frames: usize,
+        save: fn(&mut Self, &str, f32) -> Result<(), String>, done: &str) -> Result<String, String> {
+        let Some(at) = pattern.find('#') else {
+            if frames > 1 { return Err(format!("--out {pattern} needs a # standing for the frame number")); }
+            save(self, pattern, start)?;
+            self.saved(Path::new(pattern));
+            return Ok(format!("{done} {pattern}"));
+        };
+        let digits = pattern[at..].find(|c| c != '#').unwrap_or(pattern.len() - at);
+        let name = |i: usize| format!("{}{i:0digits$}{}", &pattern[..at], &pattern[at + digits..]);
+        for i in 0..frames {
+            save(self, &name(i), start + i as f32 * step)?;
+            self.saved(Path::new(&name(i)));
+        }
+        Ok(format!("{done} {frames} frames to {} up to {}", name(0), name(frames - 1)))
+    }
+
+    // The frame at time `t` as text, or as an SVG when `path` ends in .svg
+    fn save(&mut self, path: &str, t: f32) -> Result<(), String> {
+        if path.ends_with(".svg") { return save_svg(path, &self.frame(t), &self.settings.ramp, self.colors); }
+        let written = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            write_ansi(&mut out, &self.frame(t), &self.settings.ramp, self.colors)?;
+            out.flush()
+        });
+        written.map_err(|e| format!("cannot save {path}: {e}"))
+    }
+
+    // The pixel renderer's frame at time `t` as a PNG
+    fn save_png(&mut self, path: &str, t: f32) -> Result<(), String> {
+        let image = self.bitmap(t);
+        save_png(path, &image)
+    }
+
+    // `frames` frames of animation from `start` at `fps` frames per second,
+    // saved to `path` as `format`. Recordings play from 0 whatever the
+    // animation time they start at. Returns what was saved
+    pub fn record(&mut self, path: &Path, format: Recording, start: f32, fps: f32, frames: usize) -> Result<String, String> {
+        let done = match format {
+            // Counted as each is saved
+            Recording::PngSeq => return self.files(&path.to_string_lossy(), start, 1.0 / fps, frames, Self::save_png, "recorded"),
+            Recording::Cast => self.cast(path, start, fps, frames),
+            Recording::Gif => self.gif(path, start, fps, frames),
+            Recording::Html => self.html(path, start, fps, frames),
+            Recording::Ansi => self.ansi(path, start, fps, frames),
+        };
+        self.saved(path);
+        done
+    }
+
+    // `frames` frames in the file format `play` plays
+    fn ansi(&mut self, path: &Path, start: f32, fps: f32, frames: usize) -> Result<String, String> {
+        let written = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            replay::write_header(&mut out, self.proj.width, self.proj.height)?;
+            for i in 0..frames {
+                let t = i as f32 / fps;
+                let mut text = Vec::new();
+                write_ansi(&mut text, &self.frame(start + t), &self.settings.ramp, self.colors)?;
+                let text = String::from_utf8_lossy(&text).replace('\n', "\r\n");
+                replay::write_frame(&mut out, t, text.strip_suffix("\r\n").unwrap_or(&text).as_bytes())?;
+            }
+            out.flush()
+        });
+        written.map_err(|e| format!("cannot record to {}: {e}", path.display()))?;
+        Ok(format!("recorded {frames} frames to {}", path.display()))
+    }
+
+    // `frames` frames as a single HTML page that plays them over and over
+    // like a terminal would, clicking it pausing and playing them
+    fn html(&mut self, path: &Path, start: f32, fps: f32, frames: usize) -> Result<String, String> {
+        let written = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            out.write_all(HTML_START.as_bytes())?;
+            for i in 0..frames {
+                let mut html = Vec::new();
+                write_html(&mut html, &self.frame(start + i as f32 / fps), &self.settings.ramp, self.colors)?;
+                // Nothing in a frame may look like the end of the script
+                let frame = json_string(&String::from_utf8_lossy(&html)).replace("</", "<\\/");
+                writeln!(out, "{frame},")?;
+            }
+            write!(out, "{}", HTML_END.replace("FPS", &fps.to_string()))?;
+            out.flush()
+        });
+        written.map_err(|e| format!("cannot record to {}: {e}", path.display()))?;
+        Ok(format!("recorded {frames} frames to {}", path.display()))
+    }
+
+    // `frames` frames as an animated GIF, each cell `DEFAULT_CELL_PIXELS`
+    // pixels. GIF delays are whole hundredths of a second, so they are
+    // rounded to keep the frames on time overall
+    fn gif(&mut self, path: &Path, start: f32, fps: f32, frames: usize) -> Result<String, String> {
+        let cell = DEFAULT_CELL_PIXELS;
+        let (width, height) = (self.proj.width * cell.0, self.proj.height * cell.1);
+        if width > u16::MAX as usize || height > u16::MAX as usize {
+            return Err(format!("{width}x{height} pixels is too big for a GIF"));
+        }
+        let written = File::create(path).and_then(|file| {
+            let mut gif = Gif::new(BufWriter::new(file), width, height)?;
+            let hundredths = |i: usize| (i as f32 * 100.0 / fps).round() as u16;
+            for i in 0..frames {
+                let image = self.bitmap(start + i as f32 / fps);
+                gif.frame(&image, hundredths(i + 1) - hundredths(i))?;
+            }
+            gif.finish()
+        });
+        written.map_err(|e| format!("cannot record to {}: {e}", path.display()))?;
+        Ok(format!("recorded {frames} frames to {}", path.display()))
+    }
+
+    // `frames` frames as an asciicast
+    // (https://docs.asciinema.org/manual/asciicast/v2/) that asciinema plays
+    // back, each shown for 1/`fps` seconds
+    fn cast(&mut self, path: &Path, start: f32, fps: f32, frames: usize) -> Result<String, String> {
+        let written = File::create(path).and_then(|file| {
+            let mut out = BufWriter::new(file);
+            writeln!(out, "{{\"version\": 2, \"width\": {}, \"height\": {}}}", self.proj.width, self.proj.height)?;
+            for i in 0..frames {
+                let t = i as f32 / fps;
+                let mut text = Vec::new();
+                write_ansi(&mut text, &self.frame(start + t), &self.settings.ramp, self.colors)?;
+                // Each frame starts over at the top left, on a cleared screen the first time
+                let home = if i == 0 { "\x1b[2J\x1b[H" } else { "\x1b[H" };
+                let text = String::from_utf8_lossy(&text).replace('\n', "\r\n");
+                writeln!(out, "[{t:.6}, \"o\", {}]", json_string(&format!("{home}{}", text.trim_end())))?;
+            }
+            // Nothing more to show, but the last frame stays up as long as the others
+            writeln!(out, "[{:.6}, \"o\", \"\"]", frames as f32 / fps)?;
+            out.flush()
+        });
+        written.map_err(|e| format!("cannot record to {}: {e}", path.display()))?;
+        Ok(format!("recorded {frames} frames to {}", path.display()))
+    }
+
+    // Render a fixed run of frames as fast as possible, without showing
+    // them, and say how long they took
+    pub fn bench(&mut self) -> String {
+        let mut times: Vec<f32> = (0..BENCH_FRAMES)
+            .map(|i| {
+                let start = Instant::now();
+                std::hint::black_box(self.frame(i as f32 * BENCH_STEP));
+                start.elapsed().as_secs_f32() * 1000.0
+            })
+            .collect();
+        times.sort_by(f32::total_cmp);
+        let mean = times.iter().sum::<f32>() / times.len() as f32;
+        let percentile = |p: f32| times[((times.len() - 1) as f32 * p).round() as usize];
+        format!("{BENCH_FRAMES} frames at {}x{}: {mean:.2} ms mean, {:.2} ms median, {:.2} ms 95th percentile, {:.0} fps",
+            self.proj.width, self.proj.height, percentile(0.5), percentile(0.95), 1000.0 / mean)
+    }
+}
+
+// `s` as a JSON string, quotes included
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,68 @@
+use crate::camera::Camera;
+
+// Number of bookmark slots, on keys 1 to 9
+pub const SLOTS: usize = 9;
+
+// Which camera is in use and where it is
+#[derive(Clone, Copy)]
+pub struct Pose {
+    // The fly camera, `None` when orbiting
+    pub fly: Option<Camera>,
+    // Orbit camera distance, kept while flying too
+    pub distance: f32,
+}
+
+impl Pose {
+    pub fn camera(&self) -> Camera {
+        self.fly.unwrap_or(Camera::orbit(self.distance))
+    }
+}
+
+// Saved camera poses. Recalling one can glide the view there over
+// `duration` seconds instead of jumping
+pub struct Bookmarks {
+    slots: [Option<Pose>; SLOTS],
+    pub duration: f32,
+    // Where the view was when the last recall started, and how far into
+    // the glide it is
+    glide: Option<(Camera, f32)>,
+}
+
+impl Bookmarks {
+    pub fn new(duration: f32) -> Bookmarks {
+        Bookmarks { slots: [None; SLOTS], duration, glide: None }
+    }
+
+    pub fn save(&mut self, slot: usize, pose: Pose) {
+        self.slots[slot] = Some(pose);
+    }
+
+    // The pose saved in `slot`, starting a glide away from `current`
+    pub fn recall(&mut self, slot: usize, current: Camera) -> Option<Pose> {
+        let pose = self.slots[slot]?;
+        if self.duration > 0.0 { self.glide = Some((current, 0.0)); }
+        Some(pose)
+    }
+
+    // The camera to show this frame, on the way to `target`
+    pub fn view(&mut self, target: Camera, dt: f32) -> Camera {
+        let Some((from, elapsed)) = &mut self.glide else { return target; };
+        *elapsed += dt;
+        let t = (*elapsed / self.duration).min(1.0);
+        let camera = Camera::lerp(from, &target, t * t * (3.0 - 2.0 * t));
+        if t >= 1.0 { self.glide = None; }
+        camera
+    }
+}
+
+// Bookmark slot a key saves to or recalls from: digits recall, shifted
+// digits save. Terminals without modifier reporting send the symbol on a US
+// layout instead of a shifted digit
+pub fn slot_key(c: char, shift: bool) -> Option<(usize, bool)> {
+    const SHIFTED: [char; SLOTS] = ['!', '@', '#', '$', '%', '^', '&', '*', '('];
+    if let Some(i) = SHIFTED.iter().position(|s| *s == c) {
+        return Some((i, true));
+    }
+    let digit = c.to_digit(10).filter(|d| (1..=SLOTS as u32).contains(d))?;
+    Some((digit as usize - 1, shift))
+}
@@ -0,0 +1,73 @@
+use crate::math::{add, rotate_x, rotate_y, scale, sub};
+
+// Steepest the camera looks up or down, just short of straight so yaw keeps
+// meaning something
+pub const MAX_PITCH: f32 = 1.55;
+
+// Where the view is seen from. With no yaw or pitch the camera looks down
+// +z with +y up; yaw turns it right around +y, pitch tilts it down
+#[derive(Clone, Copy)]
+pub struct Camera {
+    pub position: [f32; 3],
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+// A camera given by where it is and what it looks at, as scripts and
+// keyframes set it
+#[derive(Clone, Copy)]
+pub struct Aim {
+    pub position: [f32; 3],
+    pub target: [f32; 3],
+}
+
+impl Aim {
+    pub fn camera(&self) -> Camera {
+        let [x, y, z] = sub(self.target, self.position);
+        let yaw = x.atan2(z);
+        let pitch = (-y).atan2((x * x + z * z).sqrt()).clamp(-MAX_PITCH, MAX_PITCH);
+        Camera { position: self.position, yaw, pitch }
+    }
+}
+
+impl Camera {
+    // Looking at the origin from `distance` away
+    pub fn orbit(distance: f32) -> Camera {
+        Camera { position: [0.0, 0.0, -distance], yaw: 0.0, pitch: 0.0 }
+    }
+
+    // A world-space point in camera space, z being its depth
+    pub fn view_space(&self, point: [f32; 3]) -> [f32; 3] {
+        let d = [point[0] - self.position[0], point[1] - self.position[1], point[2] - self.position[2]];
+        rotate_x(rotate_y(d, -self.yaw), -self.pitch)
+    }
+
+    // A camera-space direction in world space
+    pub fn world_dir(&self, dir: [f32; 3]) -> [f32; 3] {
+        rotate_y(rotate_x(dir, self.pitch), self.yaw)
+    }
+
+    // Part way from `a` to `b`, `t` going from 0.0 to 1.0
+    pub fn lerp(a: &Camera, b: &Camera, t: f32) -> Camera {
+        let mix = |x: f32, y: f32| x + (y - x) * t;
+        Camera {
+            position: [0, 1, 2].map(|i| mix(a.position[i], b.position[i])),
+            yaw: mix(a.yaw, b.yaw),
+            pitch: mix(a.pitch, b.pitch),
+        }
+    }
+
+    // Turn the view, pitch stopping short of looking straight up or down
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        self.yaw += yaw;
+        self.pitch = (self.pitch + pitch).clamp(-MAX_PITCH, MAX_PITCH);
+    }
+
+    // Move along the view direction, to the right of it and straight up
+    pub fn fly(&mut self, forward: f32, right: f32, up: f32) {
+        let moves = [([0.0, 0.0, 1.0], forward), ([1.0, 0.0, 0.0], right), ([0.0, 1.0, 0.0], up)];
+        for (axis, amount) in moves {
+            self.position = add(self.position, scale(self.world_dir(axis), amount));
+        }
+    }
+}
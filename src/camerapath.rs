@@ -0,0 +1,83 @@
+// Camera moves saved from the view with --save-camera and followed again
+// with --camera-path, in the view or in render and record. The file has a
+// line for each frame: the animation time in seconds, the position and the
+// yaw and pitch in radians
+//
+//   # teruminator camera path
+//   0.000000 0 0 -5 0 0
+//   0.016667 0 0 -4.98 0.01 0
+//
+// Between two lines the camera goes in a straight line, before the first
+// and after the last it stays put
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+use crate::camera::Camera;
+
+const HEADER: &str = "# teruminator camera path";
+
+pub struct CameraPath {
+    // Times never going down
+    samples: Vec<(f32, Camera)>,
+}
+
+impl CameraPath {
+    pub fn load(path: &Path) -> Result<CameraPath, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let mut samples: Vec<(f32, Camera)> = Vec::new();
+        for (i, line) in text.lines().enumerate().filter(|(_, line)| !line.starts_with('#') && !line.trim().is_empty()) {
+            let numbers: Option<Vec<f32>> = line.split_whitespace().map(|n| n.parse().ok().filter(|n: &f32| n.is_finite())).collect();
+            let Some([t, x, y, z, yaw, pitch]) = numbers.and_then(|n| <[f32; 6]>::try_from(n).ok()) else {
+                return Err(format!("{}: line {}: expected TIME X Y Z YAW PITCH", path.display(), i + 1));
+            };
+            if samples.last().is_some_and(|(last, _)| t < *last) {
+                return Err(format!("{}: line {}: times can't go down", path.display(), i + 1));
+            }
+            samples.push((t, Camera { position: [x, y, z], yaw, pitch }));
+        }
+        if samples.is_empty() { return Err(format!("{}: no camera positions in it", path.display())); }
+        Ok(CameraPath { samples })
+    }
+
+    // Where the camera is at animation time `t`
+    pub fn at(&self, t: f32) -> Camera {
+        let next = self.samples.partition_point(|(time, _)| *time <= t);
+        match self.samples.get(next) {
+            Some((to, b)) if next > 0 => {
+                let (from, a) = &self.samples[next - 1];
+                Camera::lerp(a, b, (t - from) / (to - from))
+            }
+            Some((_, first)) => *first,
+            None => self.samples[next - 1].1,
+        }
+    }
+}
+
+// Writes where the camera is each frame the animation has moved on. While it
+// is paused or played backwards nothing is written, a path having a single
+// camera for each time
+pub struct CameraRecorder {
+    out: BufWriter<File>,
+    last: Option<f32>,
+}
+
+impl CameraRecorder {
+    pub fn create(path: &Path) -> Result<CameraRecorder, String> {
+        let mut out = File::create(path).map(BufWriter::new).map_err(|e| format!("cannot save {}: {e}", path.display()))?;
+        writeln!(out, "{HEADER}").map_err(|e| format!("cannot save {}: {e}", path.display()))?;
+        Ok(CameraRecorder { out, last: None })
+    }
+
+    pub fn record(&mut self, t: f32, camera: &Camera) -> std::io::Result<()> {
+        if self.last.is_some_and(|last| t <= last) { return Ok(()); }
+        self.last = Some(t);
+        let [x, y, z] = camera.position;
+        writeln!(self.out, "{t:.6} {x} {y} {z} {} {}", camera.yaw, camera.pitch)
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,370 @@
+// The command line: a subcommand, the options and a scene or model to show.
+// The same options come from the config file, its profiles and the
+// environment too, each parsed on their own and put on top of the last
+//
+//   teruminator render --frames 3 --mode wireframe teapot.obj
+//
+// Where a setting has a word for leaving it to the program, like auto or
+// off, `Maybe` tells it apart from the setting not being given at all
+
+use std::ops::RangeBounds;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{Args, Parser, Subcommand, ValueEnum};
+
+use crate::batch::Recording;
+use crate::color::ColorSupport;
+use crate::framebuffer::{Ramp, RAMP_PRESETS};
+use crate::hud::{language, Words, LANGUAGES};
+use crate::keymap::PROFILE_NAMES;
+use crate::output::Backend;
+use crate::palette::Palette;
+use crate::post::{Crt, Fog};
+use crate::render::RenderMode;
+use crate::runstats::StatsTarget;
+use crate::scene::{shape_mesh, SCENE_NAMES};
+use crate::screenshot::ShotFormat;
+use crate::shading::Shading;
+use crate::shadow::ShadowQuality;
+use crate::subcell::SubCell;
+use crate::wireframe::HiddenLines;
+
+#[derive(Parser)]
+#[command(name = "teruminator", version, about = "3D scenes and models drawn in the terminal", args_conflicts_with_subcommands = true)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    #[command(flatten)]
+    pub run: Run,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    #[command(about = "The interactive view, also when no command is given and the output is a terminal, render otherwise")]
+    View(Run),
+    #[command(about = "Print frames and exit, only the first one unless --frames or --time say otherwise")]
+    Render(Run),
+    #[command(about = "Save --duration or --loops, 5 seconds if left out, at --fps, 30 if left out, in --format")]
+    Record(Run),
+    #[command(about = "Time rendering 300 frames")]
+    Bench(Run),
+    #[command(about = "Show a recording made with --format ansi")]
+    Play {
+        #[arg(value_name = "FILE")]
+        file: PathBuf,
+    },
+}
+
+// What the view, render, record and bench take
+#[derive(Args)]
+pub struct Run {
+    #[arg(value_name = "FILE", help = "A scene file ending in .toml, or an OBJ model", conflicts_with_all = ["scene", "shape"])]
+    pub file: Option<String>,
+    #[arg(long, help_heading = SHOWN, help = "The shapes and their settings, with the models in ~/.config/teruminator/shapes as shapes of their own")]
+    pub list_shapes: bool,
+    #[command(flatten)]
+    pub flags: Flags,
+}
+
+const SHOWN: &str = "What is shown";
+const DRAWN: &str = "How it is drawn";
+const RUNNING: &str = "Running";
+
+// The options, `None` where they aren't given. Switches are on with
+// --NAME and off again with --NAME=false, so a later layer can undo one
+#[derive(Args)]
+pub struct Flags {
+    #[arg(long, value_name = "NAME", help_heading = SHOWN, value_parser = PossibleValuesParser::new(SCENE_NAMES), conflicts_with = "shape", help = "A built-in scene")]
+    pub scene: Option<String>,
+    #[arg(long, value_name = "NAME", help_heading = SHOWN, value_parser = shape, help = "A single shape, with settings like sphere:rings=12,slices=24")]
+    pub shape: Option<String>,
+    #[arg(long, allow_hyphen_values = true, value_name = "X,Y,Z", help_heading = SHOWN, value_parser = |s: &str| triple(s, "X,Y,Z in radians per second"), help = "Turn rate around each axis, radians per second, 0.7,1.0,0.3 if left out")]
+    pub speed: Option<[f32; 3]>,
+    #[arg(long, allow_hyphen_values = true, value_name = "Y,P,R", help_heading = SHOWN, value_parser = |s: &str| triple(s, "YAW,PITCH,ROLL in degrees"), help = "Starting yaw, pitch and roll in degrees")]
+    pub orientation: Option<[f32; 3]>,
+    #[arg(long, value_name = "AXES", help_heading = SHOWN, value_parser = axes, help = "Axes not to turn around, like xz")]
+    pub freeze: Option<[bool; 3]>,
+    #[arg(long, allow_hyphen_values = true, value_name = "EXPR", help_heading = SHOWN, help = "Angle around x in radians, an expression of the time t like \"sin(t) * 2\"")]
+    pub rotate_x: Option<String>,
+    #[arg(long, allow_hyphen_values = true, value_name = "EXPR", help_heading = SHOWN, help = "Angle around y, like --rotate-x")]
+    pub rotate_y: Option<String>,
+    #[arg(long, allow_hyphen_values = true, value_name = "EXPR", help_heading = SHOWN, help = "Angle around z, like --rotate-x")]
+    pub rotate_z: Option<String>,
+    #[arg(long, allow_hyphen_values = true, value_name = "EXPR", help_heading = SHOWN, help = "Position of every object, like \"(sin(t), 0, 0)\"")]
+    pub position: Option<String>,
+    #[arg(long, allow_hyphen_values = true, value_name = "EXPR", help_heading = SHOWN, help = "Size of every object, like \"1 + sin(t) / 4\"")]
+    pub scale: Option<String>,
+    #[arg(long, value_name = "FILE", help_heading = SHOWN, help = "Image over every face")]
+    pub texture: Option<PathBuf>,
+    #[arg(long, value_name = "FILE", help_heading = SHOWN, help = "Steps like \"at 0s..2s rotate cube 360° about y\" animating the objects and cutting the camera")]
+    pub timeline: Option<PathBuf>,
+    #[arg(long, value_name = "FILE", help_heading = SHOWN, help = "Save where the view's camera is as the animation plays")]
+    pub save_camera: Option<PathBuf>,
+    #[arg(long, value_name = "FILE", help_heading = SHOWN, help = "Follow camera moves saved with --save-camera, in the view and in what render and record make")]
+    pub camera_path: Option<PathBuf>,
+    #[arg(long, value_name = "NAME", help_heading = SHOWN, value_enum, conflicts_with = "colorblind", help = "Colors of the faces")]
+    pub palette: Option<Palette>,
+    #[arg(long, value_name = "KIND", help_heading = SHOWN, value_parser = PossibleValuesParser::new(["protanopia", "deuteranopia", "tritanopia"]).map(|s| Palette::parse_colorblind(&s).expect("one of the possible values")), help = "Palette for a color vision deficiency")]
+    pub colorblind: Option<Palette>,
+
+    #[arg(long, value_name = "MODE", help_heading = DRAWN, value_enum, help = "How the objects are drawn")]
+    pub mode: Option<RenderMode>,
+    #[arg(long, value_name = "MODEL", help_heading = DRAWN, value_enum, help = "How light turns into characters")]
+    pub shading: Option<Shading>,
+    #[arg(long, value_name = "SET", help_heading = DRAWN, value_parser = charset, help = "Shade characters: ascii, blocks, dots, braille for braille dots at twice the resolution, or custom:CHARS")]
+    pub charset: Option<Charset>,
+    #[arg(long, value_name = "RAMP", help_heading = DRAWN, value_parser = Ramp::parse, help = "Shade characters darkest first, or the ascii, blocks or dots presets")]
+    // Written out so clap takes it as one value rather than a list
+    pub ramp: Option<::std::vec::Vec<char>>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = DRAWN, help = "Dither between shade characters")]
+    pub dither: Option<bool>,
+    #[arg(long, value_name = "MODE", help_heading = DRAWN, value_parser = or_auto::<ColorSupport>(), help = "Colors the terminal is sent, none (or never) for none, true (or truecolor) for 24-bit, or auto to detect what it takes")]
+    pub color: Option<Maybe<ColorSupport>>,
+    #[arg(long, value_name = "STYLE", help_heading = DRAWN, value_parser = PossibleValuesParser::new(["ramp", "solid"]).map(|s| s == "solid"), help = "Shade characters, or blocks of background color")]
+    pub fill: Option<bool>,
+    #[arg(long, value_name = "GLYPHS", help_heading = DRAWN, value_parser = or_auto::<SubCell>(), help = "Blocks splitting each cell, or auto for the finest the terminal draws")]
+    pub subcell: Option<Maybe<SubCell>>,
+    #[arg(long, value_name = "KIND", help_heading = DRAWN, value_parser = or_auto::<Backend>(), help = "Characters, or an image the terminal shows")]
+    pub output: Option<Maybe<Backend>>,
+    #[arg(long, value_name = "QUALITY", help_heading = DRAWN, value_enum, help = "Shadows on the ground, or on the objects too")]
+    pub shadows: Option<ShadowQuality>,
+    #[arg(long, value_name = "STYLE", help_heading = DRAWN, value_enum, help = "Wireframe edges behind faces")]
+    pub hidden_lines: Option<HiddenLines>,
+    #[arg(long, value_name = "FOG", help_heading = DRAWN, value_parser = fog, help = "Depth fog: off, linear[:START,END] or exp[:DENSITY]")]
+    pub fog: Option<Fog>,
+    #[arg(long, value_name = "G", help_heading = DRAWN, value_parser = |s: &str| number::<f32>(s, 0.1..=10.0, "0.1 to 10"), help = "Brightness curve, 0.1 to 10")]
+    pub gamma: Option<f32>,
+    #[arg(long, value_name = "CURVE", help_heading = DRAWN, value_parser = PossibleValuesParser::new(["clip", "reinhard"]).map(|s| s == "reinhard"), help = "What is too bright")]
+    pub tonemap: Option<bool>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = DRAWN, help = "Smooth the edges")]
+    pub antialias: Option<bool>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = DRAWN, help = "Glow around bright cells")]
+    pub bloom: Option<bool>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = DRAWN, help = "Show the depth instead of the light")]
+    pub depth: Option<bool>,
+    #[arg(long, value_name = "EFFECT", help_heading = DRAWN, value_enum, help = "An old screen's scanlines")]
+    pub crt: Option<Crt>,
+    #[arg(long, value_name = "DECAY", help_heading = DRAWN, value_parser = |s: &str| number::<f32>(s, 0.0..1.0, "0.0 up to but excluding 1.0"), help = "Share of the last frame kept, 0.0 up to 1.0")]
+    pub motion_blur: Option<f32>,
+    #[arg(long, value_name = "N", help_heading = DRAWN, value_parser = |s: &str| number::<usize>(s, 1..=4, "1 to 4"), help = "Samples per cell along each side, 1 to 4")]
+    pub supersample: Option<usize>,
+    #[arg(long, value_name = "N", help_heading = DRAWN, value_parser = |s: &str| number::<u32>(s, .., &format!("a whole number from 0 to {}", u32::MAX)), help = "What is random, the same seed drawing the same frames")]
+    pub seed: Option<u32>,
+    #[arg(long, value_name = "W:H", help_heading = DRAWN, value_parser = letterbox, help = "Centered viewport of that shape")]
+    pub letterbox: Option<f32>,
+    #[arg(long, value_name = "COLSxROWS", help_heading = DRAWN, value_parser = size, help = "View size whatever the terminal's, centered when it is smaller and cut off at the right and bottom when it is bigger")]
+    pub size: Option<(usize, usize)>,
+    #[arg(long, value_name = "RATIO", help_heading = DRAWN, value_parser = |s: &str| number::<f32>(s, 0.25..=8.0, "height over width, 0.25 to 8"), help = "Cell height over width, measured if left out")]
+    pub aspect: Option<f32>,
+
+    #[arg(long, value_name = "N", help_heading = RUNNING, value_parser = fps, help = "Frames per second at most, or off")]
+    pub fps: Option<Maybe<f32>>,
+    #[arg(long, value_name = "N", help_heading = RUNNING, value_parser = |s: &str| number::<f32>(s, 1.0..=1000.0, "steps per second from 1 to 1000"), help = "Steps per second the animation and throws move on by whatever the frame rate, 60 if left out, frames in between showing them part of the way")]
+    pub sim_rate: Option<f32>,
+    #[arg(long, value_name = "BOOL", num_args = 0..=1, require_equals = true, default_missing_value = "true", help_heading = RUNNING, help = "Start with the animation stopped, Space plays it")]
+    pub paused: Option<bool>,
+    #[arg(long, value_name = "FD|FILE", help_heading = RUNNING, value_parser = StatsTarget::parse, help = "Write statistics about the run as JSON to this file descriptor when it ends, 3 with 3>stats.json, or to this file")]
+    pub stats_json: Option<StatsTarget>,
+    #[arg(long, value_name = "TIME", help_heading = RUNNING, value_parser = duration, help = "Quit after this long, like 10s, 500ms or 2m")]
+    pub duration: Option<f32>,
+    #[arg(long, value_name = "N", help_heading = RUNNING, value_parser = |s: &str| number::<u32>(s, 1.., "1 or more"), help = "Quit after the animation went round this often, a loop being a whole turn around the fastest axis")]
+    pub loops: Option<u32>,
+    #[arg(long, value_name = "PROFILE", help_heading = RUNNING, value_parser = PossibleValuesParser::new(PROFILE_NAMES), help = "Key bindings")]
+    pub keys: Option<String>,
+    #[arg(long, value_name = "LANG", help_heading = RUNNING, value_parser = PossibleValuesParser::new(LANGUAGES.map(|words| words.code)).map(|s| language(&s).expect("one of the possible values")), help = "Language of the status line, from the locale if left out")]
+    pub lang: Option<&'static Words>,
+    #[arg(long, value_name = "NAME", help_heading = RUNNING, help = "The config file's [profile.NAME] settings on top of the others")]
+    pub profile: Option<String>,
+    #[arg(long, value_name = "D", help_heading = RUNNING, value_parser = |s: &str| number::<f32>(s, 0.0..=1.0, "0.0 to 1.0"), help = "Share of a throw lost per second, 0.0 to 1.0")]
+    pub damping: Option<f32>,
+    #[arg(long, value_name = "SECS", help_heading = RUNNING, value_parser = |s: &str| number::<f32>(s, 0.0..=10.0, "0 to 10 seconds"), help = "Time going to a camera bookmark takes")]
+    pub glide: Option<f32>,
+    #[arg(long, value_name = "FORMAT", help_heading = RUNNING, value_enum, help = "What the screenshot key saves, both being text and png")]
+    pub screenshot: Option<ShotFormat>,
+    #[arg(long, value_name = "N", help_heading = RUNNING, value_parser = |s: &str| number::<usize>(s, 1.., "1 or more"), help = "Frames render or record makes, 1/--fps seconds apart, record going by --duration if left out")]
+    pub frames: Option<usize>,
+    #[arg(long, allow_hyphen_values = true, value_name = "SECS", help_heading = RUNNING, value_parser = |s: &str| number::<f32>(s, f32::MIN..=f32::MAX, "seconds"), help = "Animation time render and record start at")]
+    pub time: Option<f32>,
+    #[arg(long, value_name = "FILE", help_heading = RUNNING, help = "Where render saves frames instead of printing them, as SVG if it ends in .svg and text otherwise, with # standing for the frame number, and where record saves to, teruminator.cast, .gif, -####.png, .html or .ansi if left out")]
+    pub out: Option<PathBuf>,
+    #[arg(long, value_name = "FORMAT", help_heading = RUNNING, value_enum, help = "What record saves: cast for asciinema, the default, gif, png-seq for a PNG a frame named like --out, html for a web page playing it, or ansi for play")]
+    pub format: Option<Recording>,
+}
+
+// The config file's, a profile's or an environment variable's options,
+// which can't name a command or a file
+#[derive(Parser)]
+#[command(no_binary_name = true, disable_help_flag = true)]
+struct Settings {
+    #[command(flatten)]
+    flags: Flags,
+}
+
+// The options in `args`, failing with the first line of what is wrong and
+// the values there are to pick from
+pub fn settings(args: Vec<String>) -> Result<Flags, String> {
+    Settings::try_parse_from(args).map(|settings| settings.flags).map_err(|e| {
+        let message = e.to_string();
+        let mut lines = message.lines();
+        let line = lines.next().unwrap_or_default();
+        let line = line.strip_prefix("error: ").unwrap_or(line);
+        match lines.map(str::trim).find(|line| line.starts_with("[possible values:")) {
+            Some(values) => format!("{line} {values}"),
+            None => line.to_string(),
+        }
+    })
+}
+
+// A setting with a word like auto or off for `None`
+#[derive(Clone, Copy)]
+pub struct Maybe<T>(pub Option<T>);
+
+// A ramp drawn a character per cell, or braille dots
+#[derive(Clone)]
+pub enum Charset {
+    Braille,
+    Ramp(Vec<char>),
+}
+
+// `auto`, or one of the values of `T`
+fn or_auto<T: ValueEnum + Send + Sync + 'static>() -> impl TypedValueParser<Value = Maybe<T>> {
+    let values = std::iter::once(PossibleValue::new("auto")).chain(T::value_variants().iter().filter_map(T::to_possible_value));
+    PossibleValuesParser::new(values).map(|s| Maybe(T::from_str(&s, false).ok()))
+}
+
+// A number in `range`, failing with what was `expected`
+fn number<T: FromStr + PartialOrd>(s: &str, range: impl RangeBounds<T>, expected: &str) -> Result<T, String> {
+    s.parse().ok().filter(|n| range.contains(n)).ok_or(format!("expected {expected}"))
+}
+
+fn shape(s: &str) -> Result<String, String> {
+    shape_mesh(s)?;
+    Ok(s.to_string())
+}
+
+// Three numbers separated by commas
+fn triple(s: &str, expected: &str) -> Result<[f32; 3], String> {
+    let numbers: Option<Vec<f32>> = s.split(',').map(|n| n.trim().parse().ok().filter(|n: &f32| n.is_finite())).collect();
+    numbers.and_then(|numbers| numbers.try_into().ok()).ok_or(format!("expected {expected}"))
+}
+
+fn axes(s: &str) -> Result<[bool; 3], String> {
+    let mut frozen = [false; 3];
+    for axis in s.chars() {
+        frozen["xyz".find(axis).ok_or("expected some of x, y and z")?] = true;
+    }
+    Ok(frozen)
+}
+
+fn charset(s: &str) -> Result<Charset, String> {
+    match s {
+        "braille" => Ok(Charset::Braille),
+        _ if RAMP_PRESETS.iter().any(|(name, _)| *name == s) => Ramp::parse(s).map(Charset::Ramp),
+        _ => match s.strip_prefix("custom:") {
+            Some(chars) => Ramp::parse(chars.trim_matches('"')).map(Charset::Ramp),
+            None => Err("expected ascii, blocks, dots, braille or custom:CHARS".to_string()),
+        },
+    }
+}
+
+fn fog(s: &str) -> Result<Fog, String> {
+    Fog::parse(s).ok_or("expected off, linear[:START,END] or exp[:DENSITY]".to_string())
+}
+
+// Width over height, as W:H or a number
+fn letterbox(s: &str) -> Result<f32, String> {
+    let ratio = match s.split_once(':') {
+        Some((w, h)) => w.parse::<f32>().ok().zip(h.parse::<f32>().ok()).map(|(w, h)| w / h),
+        None => s.parse().ok(),
+    };
+    ratio.filter(|r| r.is_finite() && (0.1..=10.0).contains(r)).ok_or("expected W:H like 16:9, or a number".to_string())
+}
+
+fn size(s: &str) -> Result<(usize, usize), String> {
+    s.split_once('x')
+        .and_then(|(w, h)| w.parse::<usize>().ok().zip(h.parse::<usize>().ok()))
+        .filter(|&(w, h)| (1..=4096).contains(&w) && (1..=4096).contains(&h))
+        .ok_or("expected COLSxROWS like 120x40".to_string())
+}
+
+fn fps(s: &str) -> Result<Maybe<f32>, String> {
+    match s {
+        "off" => Ok(Maybe(None)),
+        _ => number(s, 1.0..=240.0, "1 to 240, or off").map(|fps| Maybe(Some(fps))),
+    }
+}
+
+// Seconds in `10s`, `500ms`, `2m`, `1h` or `10`
+fn duration(s: &str) -> Result<f32, String> {
+    let (number, unit) = s.find(|c: char| c.is_ascii_alphabetic()).map_or((s, ""), |at| s.split_at(at));
+    let scale = match unit {
+        "ms" => 0.001,
+        "" | "s" => 1.0,
+        "m" => 60.0,
+        "h" => 3600.0,
+        _ => 0.0,
+    };
+    number.parse::<f32>().ok().map(|n| n * scale).filter(|s| s.is_finite() && *s > 0.0)
+        .ok_or("expected a time above 0 like 10s, 500ms or 2m".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use clap::CommandFactory;
+
+    use super::*;
+
+    #[test]
+    fn the_command_line_holds_together() {
+        Cli::command().debug_assert();
+    }
+
+    #[test]
+    fn every_option_parses() {
+        let args = "--shape sphere:rings=6 --speed 1,2,3 --orientation 10,20,30 --freeze xz --rotate-x sin(t) --rotate-y t \
+            --rotate-z 0 --position (0,t,0) --scale 2 --texture wood.png --timeline steps.txt --save-camera a.txt \
+            --camera-path b.txt --palette neon --mode wireframe --shading toon --charset braille --ramp .:#@ --dither \
+            --color truecolor --fill solid --subcell auto --output text --shadows map --hidden-lines faint --fog exp:0.2 \
+            --gamma 2 --tonemap reinhard --antialias --bloom --depth --crt flat --motion-blur 0.5 --supersample 2 --seed 7 \
+            --letterbox 16:9 --size 80x24 --aspect 2 --fps off --sim-rate 120 --paused --stats-json stats.json \
+            --duration 2m --loops 3 --keys vim --lang fr --profile ssh --damping 0.5 --glide 1 --screenshot both \
+            --frames 4 --time 1.5 --out frame-#.txt --format png-seq";
+        let flags = settings(args.split_whitespace().map(str::to_string).collect()).expect("every option parses");
+        assert_eq!(flags.ramp, Some(vec!['.', ':', '#', '@']));
+        assert!(matches!(flags.color, Some(Maybe(Some(ColorSupport::Truecolor)))));
+        assert!(matches!(flags.fps, Some(Maybe(None))));
+        assert_eq!(flags.duration, Some(120.0));
+        assert_eq!(flags.freeze, Some([true, false, true]));
+        assert_eq!((flags.dither, flags.paused, flags.bloom), (Some(true), Some(true), Some(true)));
+    }
+
+    #[test]
+    fn switches_turn_off() {
+        let args = vec!["--dither=false".to_string(), "--paused=true".to_string(), "--depth".to_string()];
+        let flags = settings(args).expect("switches parse");
+        assert_eq!((flags.dither, flags.paused, flags.depth), (Some(false), Some(true), Some(true)));
+    }
+
+    #[test]
+    fn negative_values_are_values() {
+        let args = "--speed -1,0,0 --orientation -30,0,0 --rotate-y -t --position (-1,0,0) --scale -2 --time -5";
+        let flags = settings(args.split_whitespace().map(str::to_string).collect()).expect("negative values parse");
+        assert_eq!(flags.speed, Some([-1.0, 0.0, 0.0]));
+        assert_eq!(flags.orientation, Some([-30.0, 0.0, 0.0]));
+        assert_eq!(flags.rotate_y.as_deref(), Some("-t"));
+        assert_eq!(flags.scale.as_deref(), Some("-2"));
+        assert_eq!(flags.time, Some(-5.0));
+        let cli = Cli::try_parse_from(["teruminator", "render", "--speed", "-0.5,1,0", "--time", "-5"]).expect("negative values parse");
+        assert!(matches!(cli.command, Some(Command::Render(run)) if run.flags.time == Some(-5.0)));
+    }
+
+    #[test]
+    fn a_bad_setting_is_one_line() {
+        let args = vec!["--gamma".to_string(), "20".to_string()];
+        assert_eq!(settings(args).err().as_deref(), Some("invalid value '20' for '--gamma <G>': expected 0.1 to 10"));
+        let args = vec!["--mode".to_string(), "nope".to_string()];
+        assert_eq!(settings(args).err().as_deref(), Some("invalid value 'nope' for '--mode <MODE>' [possible values: solid, raytrace, wireframe, points]"));
+    }
+}
@@ -0,0 +1,206 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+// Assumed terminal background, what fog fades towards
+pub const BACKGROUND_RGB: [u8; 3] = [0, 0, 0];
+
+// How many colors the terminal can show
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColorSupport {
+    // No colors at all, only the characters
+    #[value(name = "none", alias = "never")]
+    Monochrome,
+    #[value(name = "16")]
+    Ansi16,
+    // The xterm palette: 16 named colors, a 6x6x6 cube and 24 grays
+    #[value(name = "256")]
+    Ansi256,
+    #[value(name = "true", alias = "truecolor")]
+    Truecolor,
+}
+
+// What --color and :colors take, with never also meaning none and
+// truecolor also meaning true
+pub const COLOR_NAMES: [&str; 4] = ["none", "16", "256", "true"];
+
+impl ColorSupport {
+    pub fn parse(s: &str) -> Option<ColorSupport> {
+        ColorSupport::from_str(s, false).ok()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ColorSupport::Monochrome => "none",
+            ColorSupport::Ansi16 => "16",
+            ColorSupport::Ansi256 => "256",
+            ColorSupport::Truecolor => "true",
+        }
+    }
+
+    // A non-empty NO_COLOR turns colors off (https://no-color.org).
+    // COLORTERM is the de-facto way terminals advertise 24-bit color. Below
+    // that, trust a TERM naming 256 colors, then the terminfo entry
+    pub fn detect() -> ColorSupport {
+        if std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty()) {
+            return ColorSupport::Monochrome;
+        }
+        if matches!(std::env::var("COLORTERM").as_deref(), Ok("truecolor") | Ok("24bit")) {
+            return ColorSupport::Truecolor;
+        }
+        if std::env::var("TERM").is_ok_and(|term| term.contains("256color")) {
+            return ColorSupport::Ansi256;
+        }
+        let max_colors = terminfo::Database::from_env().ok()
+            .and_then(|db| db.get::<terminfo::capability::MaxColors>().map(|c| c.0));
+        match max_colors {
+            Some(n) if n >= 256 => ColorSupport::Ansi256,
+            _ => ColorSupport::Ansi16,
+        }
+    }
+
+    // Whether rendering should work with RGB colors; anything past 16 colors
+    // does and gets quantized on output
+    pub fn rgb(self) -> bool {
+        matches!(self, ColorSupport::Ansi256 | ColorSupport::Truecolor)
+    }
+
+    // The color actually sent to the terminal, `None` for no color
+    pub fn quantize(self, color: Color) -> Option<Color> {
+        match (self, color) {
+            (ColorSupport::Monochrome, _) => None,
+            (ColorSupport::Ansi256, Color::Rgb { r, g, b }) => Some(Color::AnsiValue(nearest_ansi256([r, g, b]))),
+            (ColorSupport::Ansi16, Color::Rgb { r, g, b }) => Some(nearest_ansi([r, g, b])),
+            _ => Some(color),
+        }
+    }
+}
+
+// Channel levels of the xterm 6x6x6 color cube
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+// Closest xterm-256 palette index, from the color cube or the gray ramp
+pub fn nearest_ansi256(rgb: [u8; 3]) -> u8 {
+    let dist = |c: [u8; 3]| (0..3).map(|i| (c[i] as i32 - rgb[i] as i32).pow(2)).sum::<i32>();
+    let level = |v: u8| (0..6).min_by_key(|&i| (CUBE_LEVELS[i] as i32 - v as i32).abs()).unwrap_or(0);
+    let [r, g, b] = rgb.map(level);
+    let cube = [CUBE_LEVELS[r], CUBE_LEVELS[g], CUBE_LEVELS[b]];
+
+    let average = (rgb[0] as u32 + rgb[1] as u32 + rgb[2] as u32) / 3;
+    let gray_step = (average.saturating_sub(3) / 10).min(23) as u8;
+    let gray = 8 + 10 * gray_step;
+
+    if dist([gray; 3]) < dist(cube) {
+        232 + gray_step
+    } else {
+        16 + 36 * r as u8 + 6 * g as u8 + b as u8
+    }
+}
+
+// RGB value of an xterm-256 palette index
+pub fn ansi256_rgb(index: u8) -> [u8; 3] {
+    match index {
+        0..16 => color_to_rgb(ANSI_COLORS[ANSI_ORDER[index as usize]]),
+        16..232 => {
+            let i = (index - 16) as usize;
+            [CUBE_LEVELS[i / 36], CUBE_LEVELS[i / 6 % 6], CUBE_LEVELS[i % 6]]
+        }
+        _ => [8 + 10 * (index - 232); 3],
+    }
+}
+
+// Approximate RGB values of the 16 named ANSI colors
+pub fn color_to_rgb(color: Color) -> [u8; 3] {
+    match color {
+        Color::Black => [0, 0, 0],
+        Color::DarkGrey => [128, 128, 128],
+        Color::Red => [255, 0, 0],
+        Color::DarkRed => [128, 0, 0],
+        Color::Green => [0, 255, 0],
+        Color::DarkGreen => [0, 128, 0],
+        Color::Yellow => [255, 255, 0],
+        Color::DarkYellow => [128, 128, 0],
+        Color::Blue => [0, 0, 255],
+        Color::DarkBlue => [0, 0, 128],
+        Color::Magenta => [255, 0, 255],
+        Color::DarkMagenta => [128, 0, 128],
+        Color::Cyan => [0, 255, 255],
+        Color::DarkCyan => [0, 128, 128],
+        Color::White => [255, 255, 255],
+        Color::Grey => [192, 192, 192],
+        Color::Rgb { r, g, b } => [r, g, b],
+        _ => [255, 255, 255],
+    }
+}
+
+// A color by name or as #rrggbb
+pub fn parse_rgb(s: &str) -> Option<[u8; 3]> {
+    if let Some(hex) = s.strip_prefix('#') {
+        let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6)?;
+        return Some([(value >> 16) as u8, (value >> 8) as u8, value as u8]);
+    }
+    let color = match s {
+        "black" => Color::Black,
+        "grey" | "gray" => Color::Grey,
+        "darkgrey" | "darkgray" => Color::DarkGrey,
+        "red" => Color::Red,
+        "green" => Color::Green,
+        "yellow" => Color::Yellow,
+        "blue" => Color::Blue,
+        "magenta" => Color::Magenta,
+        "cyan" => Color::Cyan,
+        "white" => Color::White,
+        _ => return None,
+    };
+    Some(color_to_rgb(color))
+}
+
+const ANSI_COLORS: [Color; 16] = [
+    Color::Black, Color::DarkGrey, Color::Red, Color::DarkRed,
+    Color::Green, Color::DarkGreen, Color::Yellow, Color::DarkYellow,
+    Color::Blue, Color::DarkBlue, Color::Magenta, Color::DarkMagenta,
+    Color::Cyan, Color::DarkCyan, Color::White, Color::Grey,
+];
+
+// Where each of the first 16 palette entries sits in `ANSI_COLORS`
+const ANSI_ORDER: [usize; 16] = [0, 3, 5, 7, 9, 11, 13, 15, 1, 2, 4, 6, 8, 10, 12, 14];
+
+// Closest named color to an RGB value, for terminals without truecolor
+pub fn nearest_ansi(rgb: [u8; 3]) -> Color {
+    let dist = |c: &Color| {
+        let [r, g, b] = color_to_rgb(*c);
+        let d = [r as i32 - rgb[0] as i32, g as i32 - rgb[1] as i32, b as i32 - rgb[2] as i32];
+        d[0] * d[0] + d[1] * d[1] + d[2] * d[2]
+    };
+    ANSI_COLORS.iter().copied().min_by_key(dist).unwrap_or(Color::White)
+}
+
+// Output color for a linear RGB value in 0.0..=1.0
+pub fn rgb_color(rgb: [f32; 3], truecolor: bool) -> Color {
+    let q = rgb.map(|c| (c.clamp(0.0, 1.0) * 255.0) as u8);
+    if truecolor {
+        Color::Rgb { r: q[0], g: q[1], b: q[2] }
+    } else {
+        nearest_ansi(q)
+    }
+}
+
+// Darkest a shaded color gets at zero intensity, so unlit faces stay visible
+const GRADIENT_FLOOR: f32 = 0.2;
+
+// Truecolor shading: scale a color by light intensity, which gives smooth
+// gradients the character ramp alone cannot show
+pub fn shade_rgb(color: Color, intensity: f32) -> Color {
+    let k = GRADIENT_FLOOR + (1.0 - GRADIENT_FLOOR) * intensity.clamp(0.0, 1.0);
+    let [r, g, b] = color_to_rgb(color).map(|c| (c as f32 * k) as u8);
+    Color::Rgb { r, g, b }
+}
+
+// Multiply a face color by the light tint in RGB space
+pub fn tint_color(color: Color, tint: [f32; 3]) -> Color {
+    let [r, g, b] = color_to_rgb(color);
+    Color::Rgb {
+        r: (r as f32 * tint[0]) as u8,
+        g: (g as f32 * tint[1]) as u8,
+        b: (b as f32 * tint[2]) as u8,
+    }
+}
@@ -0,0 +1,73 @@
+// Commands typed after `:`, for settings without a key of their own
+
+use crate::color::{parse_rgb, ColorSupport, COLOR_NAMES};
+use crate::framebuffer::Ramp;
+use crate::playback::{MAX_SPEED, MIN_SPEED};
+use crate::render::RenderMode;
+use crate::shading::Shading;
+
+pub enum Command {
+    // A built-in scene by name, or a Wavefront OBJ file
+    Load(String),
+    Mode(RenderMode),
+    Shading(Shading),
+    // Shade characters, darkest first
+    Ramp(Vec<char>),
+    Colors(ColorSupport),
+    // Frames per second at most, `None` draws as fast as the terminal keeps up
+    Fps(Option<f32>),
+    // Animation seconds per real second, below zero playing backwards
+    Speed(f32),
+    // `None` goes back to the terminal's own
+    Background(Option<[u8; 3]>),
+    Quit,
+}
+
+// Frames per second at most, `off` for no limit
+pub fn parse_fps(arg: &str) -> Result<Option<f32>, String> {
+    match arg {
+        "off" => Ok(None),
+        _ => arg.parse().ok().filter(|f| (1.0..=240.0).contains(f)).map(Some)
+            .ok_or(format!("invalid frame rate '{arg}' (expected 1 to 240, or off)")),
+    }
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Result<Command, String> {
+        let line = line.trim();
+        let (name, arg) = line.split_once(' ').map(|(n, a)| (n, a.trim())).unwrap_or((line, ""));
+        let need = |what: &str| if arg.is_empty() { Err(format!("{name} needs {what}")) } else { Ok(arg) };
+        match name {
+            "load" | "e" => Ok(Command::Load(need("a scene name or an OBJ file")?.to_string())),
+            "mode" => {
+                let arg = need("a render mode")?;
+                RenderMode::parse(arg).map(Command::Mode)
+                    .ok_or(format!("unknown render mode '{arg}' (expected solid, raytrace, wireframe or points)"))
+            }
+            "shading" => {
+                let arg = need("a shading")?;
+                Shading::parse(arg).map(Command::Shading)
+                    .ok_or(format!("unknown shading '{arg}' (expected smooth, toon or hatch)"))
+            }
+            "ramp" => Ok(Command::Ramp(Ramp::parse(need("a ramp name or characters")?)?)),
+            "colors" => {
+                let arg = need("a color mode")?;
+                ColorSupport::parse(arg).map(Command::Colors)
+                    .ok_or(format!("unknown color mode '{arg}' (expected one of: {})", COLOR_NAMES.join(", ")))
+            }
+            "fps" => parse_fps(need("a frame rate")?).map(Command::Fps),
+            "speed" => {
+                let arg = need("a time scale")?;
+                arg.trim_end_matches('x').parse::<f32>().ok().filter(|s| (MIN_SPEED..=MAX_SPEED).contains(&s.abs())).map(Command::Speed)
+                    .ok_or(format!("invalid time scale '{arg}' (expected {MIN_SPEED} to {MAX_SPEED}, negative to play backwards)"))
+            }
+            "bg" => match need("a color")? {
+                "none" => Ok(Command::Background(None)),
+                arg => parse_rgb(arg).map(|rgb| Command::Background(Some(rgb)))
+                    .ok_or(format!("unknown color '{arg}' (expected a color name, #rrggbb or none)")),
+            },
+            "q" | "quit" => Ok(Command::Quit),
+            _ => Err(format!("unknown command '{name}' (expected load, mode, shading, ramp, colors, fps, speed, bg, spawn, quit or NAME.PROPERTY = VALUE)")),
+        }
+    }
+}
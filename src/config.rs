@@ -0,0 +1,200 @@
+// Settings read from config.toml in the user's config directory. A missing
+// file is the same as an empty one. Top-level settings are command line
+// options without the dashes, which the command line still overrides;
+// switches like dither are turned on with true and off with false
+//
+//   charset = "blocks"
+//   color = "256"
+//   fps = 30
+//   palette = "solarized"
+//   dither = true
+//
+//   [keys]
+//   profile = "vim"
+//   zoom-in = ["+", "i"]
+//   quit = "Ctrl+q"
+//
+// Profiles are more settings --profile NAME puts on top of these, for
+// switching between setups with one flag
+//
+//   [profile.ssh]
+//   color = "16"
+//   fps = 15
+//
+// Environment variables named after the settings override the file, for
+// containers and login scripts where flags are awkward to pass:
+//
+//   TERUMINATOR_FPS=30
+//   TERUMINATOR_DITHER=true
+//   TERUMINATOR_KEYS_PROFILE=vim
+//   TERUMINATOR_KEYS_ZOOM_IN="+ i"
+//
+// Dashes are written as underscores, values as they would be in the file
+// with keys separated by spaces. Empty ones are ignored
+
+use std::ffi::OsString;
+use std::path::PathBuf;
+
+use crate::keymap::{Action, Key, PROFILE_NAMES};
+
+#[derive(Default)]
+pub struct Config {
+    // Key profile to start from, `None` leaves it to the command line
+    pub key_profile: Option<String>,
+    // Actions bound to other keys than the profile's
+    pub bindings: Vec<(Action, Vec<Key>)>,
+    // Command line options the file sets, as arguments
+    pub options: Vec<String>,
+    // The same for each profile, by name
+    pub profiles: Vec<(String, Vec<String>)>,
+    // Command line options environment variables set, with the variable
+    // each came from
+    pub env_options: Vec<(String, Vec<String>)>,
+}
+
+const ENV_PREFIX: &str = "TERUMINATOR_";
+
+// $XDG_CONFIG_HOME/teruminator/config.toml, or under ~/.config
+pub fn config_path() -> Option<PathBuf> {
+    let dir = std::env::var_os("XDG_CONFIG_HOME").filter(|d| !d.is_empty()).map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(dir.join("teruminator").join("config.toml"))
+}
+
+// Models in $XDG_CONFIG_HOME/teruminator/shapes, or under ~/.config, that
+// become shapes named after them
+pub fn shapes_dir() -> Option<PathBuf> {
+    config_path()?.parent().map(|dir| dir.join("shapes"))
+}
+
+impl Config {
+    pub fn load() -> Result<Config, String> {
+        let Some(path) = config_path() else { return Ok(Config::default()); };
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(format!("cannot read {}: {e}", path.display())),
+        };
+        let mut config = Config::parse(&text).map_err(|e| format!("{}: {e}", path.display()))?;
+        config.read_env(std::env::vars_os())?;
+        Ok(config)
+    }
+
+    // Take the settings of the TERUMINATOR_ variables among `vars`, after
+    // the file's so they win
+    fn read_env(&mut self, vars: impl Iterator<Item = (OsString, OsString)>) -> Result<(), String> {
+        let mut vars: Vec<(String, String)> = vars
+            .filter_map(|(var, value)| Some((var.into_string().ok()?, value.into_string().ok()?)))
+            .filter(|(var, value)| var.starts_with(ENV_PREFIX) && !value.is_empty())
+            .collect();
+        // The same order every time, whatever order the system keeps them in
+        vars.sort();
+        for (var, value) in vars {
+            let name = var[ENV_PREFIX.len()..].to_lowercase().replace('_', "-");
+            let at = |e: String| format!("{var}: {e}");
+            if name == "keys-profile" {
+                self.key_profile = Some(key_profile(&value).map_err(at)?);
+            } else if let Some(action) = name.strip_prefix("keys-") {
+                let keys: Vec<&str> = value.split_whitespace().collect();
+                self.bindings.push(binding(action, &keys).map_err(at)?);
+            } else {
+                let arg = format!("--{name}");
+                let args = match value.as_str() {
+                    "true" => vec![arg],
+                    "false" => vec![format!("{arg}=false")],
+                    _ => vec![arg, value],
+                };
+                self.env_options.push((var, args));
+            }
+        }
+        Ok(())
+    }
+
+    fn parse(text: &str) -> Result<Config, String> {
+        let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+        let mut config = Config {
+            options: options(table.iter().filter(|(name, _)| !["keys", "profile"].contains(&name.as_str())))?,
+            ..Config::default()
+        };
+        if let Some(profiles) = table.get("profile") {
+            let profiles = profiles.as_table().ok_or("profiles have to be written as [profile.NAME] sections")?;
+            for (name, profile) in profiles {
+                let profile = profile.as_table().ok_or(format!("profile {name} has to be a [profile.{name}] section"))?;
+                let args = options(profile.iter()).map_err(|e| format!("profile {name}: {e}"))?;
+                config.profiles.push((name.clone(), args));
+            }
+        }
+        let Some(keys) = table.get("keys") else { return Ok(config); };
+        let keys = keys.as_table().ok_or("keys has to be a table")?;
+
+        for (name, value) in keys {
+            if name == "profile" {
+                let profile = value.as_str().ok_or("the key profile has to be a string")?;
+                config.key_profile = Some(key_profile(profile)?);
+                continue;
+            }
+            let names: Vec<&str> = match value {
+                toml::Value::String(key) => vec![key.as_str()],
+                toml::Value::Array(keys) => keys.iter().map(|k| k.as_str()).collect::<Option<_>>()
+                    .ok_or(format!("keys for {name} have to be strings"))?,
+                _ => return Err(format!("keys for {name} have to be a string or a list of strings")),
+            };
+            config.bindings.push(binding(name, &names)?);
+        }
+        Ok(config)
+    }
+
+    // The options of the profile called `name`
+    pub fn profile(&self, name: &str) -> Result<Vec<String>, String> {
+        let names: Vec<&str> = self.profiles.iter().map(|(name, _)| name.as_str()).collect();
+        let (_, args) = self.profiles.iter().find(|(profile, _)| profile == name).ok_or(match names.is_empty() {
+            true => format!("unknown profile '{name}' (there are no [profile.NAME] sections)"),
+            false => format!("unknown profile '{name}' (expected one of: {})", names.join(", ")),
+        })?;
+        Ok(args.clone())
+    }
+}
+
+// Settings as the command line arguments setting them
+fn options<'a>(settings: impl Iterator<Item = (&'a String, &'a toml::Value)>) -> Result<Vec<String>, String> {
+    let mut args = Vec::new();
+    for (name, value) in settings {
+        let arg = format!("--{name}");
+        match value {
+            toml::Value::Boolean(true) => args.push(arg),
+            toml::Value::Boolean(false) => args.push(format!("{arg}=false")),
+            toml::Value::String(s) => args.extend([arg, s.clone()]),
+            toml::Value::Integer(_) | toml::Value::Float(_) => args.extend([arg, value.to_string()]),
+            _ => return Err(format!("{name} has to be a string, a number, true or false")),
+        }
+    }
+    Ok(args)
+}
+
+fn key_profile(name: &str) -> Result<String, String> {
+    PROFILE_NAMES.iter().find(|p| **p == name).map(|p| p.to_string())
+        .ok_or(format!("unknown key profile {name} (expected one of: {})", PROFILE_NAMES.join(", ")))
+}
+
+// The action named `name` and the keys named `keys` it goes on
+fn binding(name: &str, keys: &[&str]) -> Result<(Action, Vec<Key>), String> {
+    let action = Action::parse(name).ok_or(format!("unknown action '{name}' in keys"))?;
+    let bound = keys.iter().map(|k| Key::parse(k).ok_or(format!("unknown key '{k}' for {name}")))
+        .collect::<Result<Vec<Key>, String>>()?;
+    Ok((action, bound))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn false_switches_stay_as_arguments() {
+        let config = Config::parse("dither = false\nbloom = true\nfps = 30").expect("the settings parse");
+        assert_eq!(config.options, ["--bloom", "--dither=false", "--fps", "30"]);
+        let mut config = Config::default();
+        let vars = [("TERUMINATOR_PAUSED", "false")].map(|(var, value)| (OsString::from(var), OsString::from(value)));
+        config.read_env(vars.into_iter()).expect("the variables parse");
+        assert_eq!(config.env_options, [("TERUMINATOR_PAUSED".to_string(), vec!["--paused=false".to_string()])]);
+    }
+}
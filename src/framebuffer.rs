@@ -0,0 +1,298 @@
+use crossterm::style::Color;
+
+use crate::color::{color_to_rgb, rgb_color, shade_rgb};
+use unicode_width::UnicodeWidthChar;
+
+use crate::shading::{hatch_char, Stroke};
+
+pub const SHADE_CHARS: [char; 8] = [' ', '.', ':', '-', '=', '+', '#', '@'];
+pub const BLOCK_CHARS: [char; 5] = [' ', '░', '▒', '▓', '█'];
+pub const DOT_CHARS: [char; 5] = [' ', '.', '·', '•', '●'];
+
+// Built-in ramps that `--ramp` accepts by name
+pub const RAMP_PRESETS: [(&str, &[char]); 3] = [("ascii", &SHADE_CHARS), ("blocks", &BLOCK_CHARS), ("dots", &DOT_CHARS)];
+
+// Characters ordered from darkest to brightest that intensities map onto
+#[derive(Clone)]
+pub struct Ramp {
+    pub chars: Vec<char>,
+    // Spread the step between two neighbouring characters over a Bayer
+    // pattern instead of rounding every cell down
+    pub dither: bool,
+}
+
+impl Default for Ramp {
+    fn default() -> Ramp {
+        Ramp { chars: SHADE_CHARS.to_vec(), dither: false }
+    }
+}
+
+impl Ramp {
+    // A ramp by preset name, or from a string of characters, darkest first.
+    // Every character has to fill exactly one cell or the frame would no
+    // longer line up
+    pub fn parse(s: &str) -> Result<Vec<char>, String> {
+        if let Some((_, chars)) = RAMP_PRESETS.iter().find(|(name, _)| *name == s) {
+            return Ok(chars.to_vec());
+        }
+        let chars: Vec<char> = s.chars().collect();
+        if chars.len() < 2 {
+            return Err(format!("shade ramp '{s}' needs at least two characters"));
+        }
+        if let Some(c) = chars.iter().find(|c| c.width() != Some(1)) {
+            return Err(format!("shade ramp character {c:?} (U+{:04X}) is not one column wide", *c as u32));
+        }
+        Ok(chars)
+    }
+
+    pub fn char_at(&self, intensity: f32, x: usize, y: usize) -> char {
+        let last = self.chars.len() - 1;
+        let scaled = intensity.clamp(0.0, 1.0) * last as f32;
+        let mut idx = scaled as usize;
+        if self.dither && idx < last && scaled.fract() > bayer_threshold(x as i32, y as i32) {
+            idx += 1;
+        }
+        self.chars[idx.min(last)]
+    }
+}
+
+// How a cell's character is chosen when the frame is resolved
+#[derive(Clone, Copy, PartialEq)]
+pub enum Glyph {
+    // Always this character
+    Fixed(char),
+    // Picked from the shade ramp by intensity
+    Ramp,
+    // Pen strokes along a screen direction, denser where darker
+    Hatch(Stroke),
+}
+
+// What ends up in one terminal cell. Shaded cells keep their light intensity
+// until the frame is resolved so post-processing can still change it.
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub glyph: Glyph,
+    pub intensity: f32,
+    pub color: Color,
+    // Drawn behind the glyph, `None` leaves the terminal background
+    pub background: Option<Color>,
+}
+
+impl Cell {
+    pub const EMPTY: Cell = Cell { glyph: Glyph::Fixed(' '), intensity: 0.0, color: Color::Black, background: None };
+
+    pub fn shaded(intensity: f32, color: Color) -> Cell {
+        Cell { glyph: Glyph::Ramp, intensity, color, background: None }
+    }
+
+    pub fn glyph(c: char, color: Color) -> Cell {
+        Cell { glyph: Glyph::Fixed(c), intensity: 1.0, color, background: None }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.glyph == Glyph::Fixed(' ') && self.background.is_none()
+    }
+
+    // The color shown for this cell. With truecolor, shaded cells carry
+    // their intensity in the color too and the ramp only adds texture
+    pub fn shown_color(&self, truecolor: bool) -> Color {
+        match self.glyph {
+            Glyph::Ramp | Glyph::Hatch(_) if truecolor => shade_rgb(self.color, self.intensity),
+            _ => self.color,
+        }
+    }
+
+    // The character shown for this cell at grid position (x, y)
+    pub fn resolve(&self, x: usize, y: usize, ramp: &Ramp) -> char {
+        match self.glyph {
+            Glyph::Fixed(c) => c,
+            Glyph::Ramp => ramp.char_at(self.intensity, x, y),
+            Glyph::Hatch(stroke) => hatch_char(stroke, self.intensity, x, y),
+        }
+    }
+}
+
+// Quadrant block characters indexed by a coverage mask: bit 0 top-left,
+// bit 1 top-right, bit 2 bottom-left, bit 3 bottom-right
+pub const QUADRANT_CHARS: [char; 16] = [
+    ' ', '▘', '▝', '▀', '▖', '▌', '▞', '▛',
+    '▗', '▚', '▐', '▜', '▄', '▙', '▟', '█',
+];
+
+// Depth-tested grid of cells, one entry per terminal cell
+pub struct FrameBuffer {
+    pub width: usize,
+    pub height: usize,
+    pub depth: Vec<f32>,
+    pub cells: Vec<Cell>,
+    // Quadrant coverage of cells only partly covered by surfaces (0 when
+    // the cell is empty or solid). Anything solid drawn later replaces such
+    // a cell whatever its depth.
+    partial: Vec<u8>,
+}
+
+impl FrameBuffer {
+    pub fn new(width: usize, height: usize) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            depth: vec![f32::MAX; width * height],
+            cells: vec![Cell::EMPTY; width * height],
+            partial: vec![0; width * height],
+        }
+    }
+
+    pub fn index(&self, x: i32, y: i32) -> Option<usize> {
+        if x < 0 || y < 0 || x >= self.width as i32 || y >= self.height as i32 { return None; }
+        Some(y as usize * self.width + x as usize)
+    }
+
+    pub fn plot(&mut self, x: i32, y: i32, z: f32, cell: Cell) {
+        let Some(idx) = self.index(x, y) else { return; };
+        if z < self.depth[idx] || self.partial[idx] != 0 {
+            self.depth[idx] = z;
+            self.cells[idx] = cell;
+            self.partial[idx] = 0;
+        }
+    }
+
+    // Plot a cell the surface only covers the `mask` quadrants of. Coverage
+    // from neighbouring faces accumulates, and a cell that ends up fully
+    // covered becomes a regular one showing the nearest surface.
+    pub fn plot_partial(&mut self, x: i32, y: i32, z: f32, cell: Cell, mask: u8) {
+        let Some(idx) = self.index(x, y) else { return; };
+        if self.depth[idx] != f32::MAX && self.partial[idx] == 0 { return; }
+
+        if z < self.depth[idx] {
+            self.depth[idx] = z;
+            self.cells[idx] = cell;
+        }
+        self.partial[idx] |= mask;
+        if self.partial[idx] == 0b1111 {
+            self.partial[idx] = 0;
+        }
+    }
+
+    // Turn the remaining partly covered cells into quadrant block glyphs
+    pub fn resolve_partial(&mut self) {
+        for (cell, mask) in self.cells.iter_mut().zip(self.partial.iter_mut()) {
+            if *mask != 0 {
+                *cell = Cell::glyph(QUADRANT_CHARS[*mask as usize], cell.color);
+                *mask = 0;
+            }
+        }
+    }
+
+    // Composite a semi-transparent sample over the cell without touching the
+    // depth buffer. With truecolor the colors are mixed; otherwise a Bayer
+    // screen-door pattern decides per cell which of the two shows.
+    pub fn blend(&mut self, x: i32, y: i32, z: f32, over: Cell, alpha: f32, truecolor: bool) {
+        let Some(idx) = self.index(x, y) else { return; };
+        if z >= self.depth[idx] { return; }
+
+        let under = self.cells[idx];
+        self.cells[idx] = if truecolor {
+            let under_rgb = if under.is_empty() { [0, 0, 0] } else { color_to_rgb(under.color) };
+            let over_rgb = color_to_rgb(over.color);
+            let mix = |i: usize| (over_rgb[i] as f32 * alpha + under_rgb[i] as f32 * (1.0 - alpha)) as u8;
+            let glyph = if under.is_empty() || alpha >= 0.5 { over.glyph } else { under.glyph };
+            Cell {
+                glyph,
+                intensity: over.intensity * alpha + under.intensity * (1.0 - alpha),
+                color: Color::Rgb { r: mix(0), g: mix(1), b: mix(2) },
+                background: None,
+            }
+        } else if alpha > bayer_threshold(x, y) {
+            over
+        } else {
+            under
+        };
+    }
+}
+
+impl FrameBuffer {
+    // Average every n x n block of samples into one cell. Partly covered
+    // cells get proportionally less intensity, which smooths silhouettes;
+    // fixed glyphs such as edges survive when enough samples hit the cell.
+    pub fn downsample(&self, n: usize, truecolor: bool) -> FrameBuffer {
+        let (width, height) = (self.width / n, self.height / n);
+        let mut out = FrameBuffer::new(width, height);
+        let total = (n * n) as f32;
+
+        for cy in 0..height {
+            for cx in 0..width {
+                let mut covered = 0usize;
+                let mut intensity = 0.0;
+                let mut rgb = [0.0f32; 3];
+                let mut depth = f32::MAX;
+                let mut fixed: Vec<(Cell, usize)> = Vec::new();
+                let mut shaded: Option<Glyph> = None;
+                let mut colors: Vec<(Color, usize)> = Vec::new();
+
+                for sy in 0..n {
+                    for sx in 0..n {
+                        let idx = (cy * n + sy) * self.width + cx * n + sx;
+                        let cell = self.cells[idx];
+                        if cell.is_empty() { continue; }
+
+                        covered += 1;
+                        depth = depth.min(self.depth[idx]);
+                        let c = color_to_rgb(cell.color);
+                        for (acc, v) in rgb.iter_mut().zip(c) {
+                            *acc += v as f32 / 255.0;
+                        }
+                        match colors.iter_mut().find(|(color, _)| *color == cell.color) {
+                            Some(entry) => entry.1 += 1,
+                            None => colors.push((cell.color, 1)),
+                        }
+
+                        if let Glyph::Fixed(_) = cell.glyph {
+                            match fixed.iter_mut().find(|(f, _)| f.glyph == cell.glyph) {
+                                Some(entry) => entry.1 += 1,
+                                None => fixed.push((cell, 1)),
+                            }
+                        } else {
+                            shaded.get_or_insert(cell.glyph);
+                            intensity += cell.intensity;
+                        }
+                    }
+                }
+
+                if covered * 2 < n * n && fixed.iter().all(|(_, count)| *count < n) { continue; }
+
+                let color = if truecolor {
+                    rgb_color(rgb.map(|c| c / covered as f32), true)
+                } else {
+                    colors.iter().max_by_key(|(_, count)| *count).map_or(Color::White, |(c, _)| *c)
+                };
+
+                let dominant = fixed.iter().max_by_key(|(_, count)| *count);
+                let shaded_count = covered - fixed.iter().map(|(_, count)| count).sum::<usize>();
+                let cell = match (dominant, shaded) {
+                    (Some((f, count)), _) if *count >= n || shaded_count == 0 => Cell { color, ..*f },
+                    (_, Some(glyph)) => {
+                        let coverage = covered as f32 / total;
+                        Cell { glyph, intensity: intensity / shaded_count as f32 * coverage, color, background: None }
+                    }
+                    _ => continue,
+                };
+                out.plot(cx as i32, cy as i32, depth, cell);
+            }
+        }
+
+        out
+    }
+}
+
+// 4x4 ordered dither matrix
+const BAYER_4X4: [[u8; 4]; 4] = [
+    [ 0,  8,  2, 10],
+    [12,  4, 14,  6],
+    [ 3, 11,  1,  9],
+    [15,  7, 13,  5],
+];
+
+// Per-cell threshold in 0.0..1.0 from the Bayer matrix
+pub fn bayer_threshold(x: i32, y: i32) -> f32 {
+    (BAYER_4X4[(y & 3) as usize][(x & 3) as usize] as f32 + 0.5) / 16.0
+}
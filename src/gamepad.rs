@@ -0,0 +1,61 @@
+// Game controllers, for when there is no keyboard. With the orbit camera the
+// left stick turns the objects and the right stick zooms; with the fly
+// camera the left stick flies and the right one looks around. Start
+// pauses, Select changes the render mode and the guide button quits
+
+use gilrs::{Axis, Button, EventType, Gilrs};
+
+use crate::keymap::Action;
+
+// How far a stick pushed all the way moves things per second: radians the
+// objects turn and the fly camera looks, zoom steps, and units flown at
+// normal flying speed
+pub const TURN_RATE: f32 = 2.0;
+pub const LOOK_RATE: f32 = 1.5;
+pub const ZOOM_RATE: f32 = 4.0;
+pub const FLY_RATE: f32 = 3.0;
+
+// What the buttons do, as the keys bound to the same actions would
+const BUTTONS: [(Button, Action); 3] = [
+    (Button::Start, Action::Pause),
+    (Button::Select, Action::NextMode),
+    (Button::Mode, Action::Quit),
+];
+
+// Where the sticks of the first controller are, each axis from -1.0 to
+// 1.0 with up and right positive
+pub struct Sticks {
+    pub left: (f32, f32),
+    pub right: (f32, f32),
+}
+
+pub struct Gamepad {
+    gilrs: Gilrs,
+}
+
+impl Gamepad {
+    // `None` when controllers can't be read at all, none being plugged in
+    // yet is fine
+    pub fn new() -> Option<Gamepad> {
+        Gilrs::new().ok().map(|gilrs| Gamepad { gilrs })
+    }
+
+    // Take the events since the last call: the actions of the buttons
+    // pressed, and where the sticks are now
+    pub fn poll(&mut self) -> (Vec<Action>, Sticks) {
+        let mut actions = Vec::new();
+        while let Some(event) = self.gilrs.next_event() {
+            if let EventType::ButtonPressed(button, _) = event.event
+                && let Some((_, action)) = BUTTONS.iter().find(|(b, _)| *b == button) {
+                actions.push(*action);
+            }
+        }
+        let pad = self.gilrs.gamepads().next().map(|(_, pad)| pad);
+        let axis = |axis: Axis| pad.map_or(0.0, |pad| pad.value(axis));
+        let sticks = Sticks {
+            left: (axis(Axis::LeftStickX), axis(Axis::LeftStickY)),
+            right: (axis(Axis::RightStickX), axis(Axis::RightStickY)),
+        };
+        (actions, sticks)
+    }
+}
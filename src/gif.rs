@@ -0,0 +1,126 @@
+// Animated GIFs (https://www.w3.org/Graphics/GIF/spec-gif89a.txt), for
+// recordings that go in READMEs and posts. Colors go through the xterm
+// 256-color palette as with sixel, so every frame shares one color table
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use crate::color::{ansi256_rgb, nearest_ansi256};
+use crate::pixels::Bitmap;
+
+// Codes are at most 12 bits, so a table holds 4096 of them
+const MAX_CODES: u16 = 4096;
+// Pixels are 8-bit palette indices, so codes start at 9 bits
+const MIN_CODE_SIZE: u8 = 8;
+const CLEAR: u16 = 256;
+const END: u16 = 257;
+
+pub struct Gif<W: Write> {
+    out: W,
+    width: usize,
+    height: usize,
+}
+
+impl<W: Write> Gif<W> {
+    // Start a GIF of `width` by `height` pixels that plays over and over
+    pub fn new(mut out: W, width: usize, height: usize) -> std::io::Result<Gif<W>> {
+        out.write_all(b"GIF89a")?;
+        out.write_all(&(width as u16).to_le_bytes())?;
+        out.write_all(&(height as u16).to_le_bytes())?;
+        // A global table of 256 colors, background color 0, square pixels
+        out.write_all(&[0xf7, 0, 0])?;
+        for i in 0..=255 {
+            out.write_all(&ansi256_rgb(i))?;
+        }
+        // Loop forever
+        out.write_all(b"\x21\xff\x0bNETSCAPE2.0\x03\x01\x00\x00\x00")?;
+        Ok(Gif { out, width, height })
+    }
+
+    // Add `image` shown for `delay` hundredths of a second. Most browsers
+    // show anything under 2 for 10, so very high frame rates play slower
+    pub fn frame(&mut self, image: &Bitmap, delay: u16) -> std::io::Result<()> {
+        debug_assert!(image.width == self.width && image.height == self.height);
+        self.out.write_all(&[0x21, 0xf9, 4, 0])?;
+        self.out.write_all(&delay.to_le_bytes())?;
+        self.out.write_all(&[0, 0])?;
+        // The whole screen, not interlaced, with the global color table
+        self.out.write_all(&[0x2c, 0, 0, 0, 0])?;
+        self.out.write_all(&(self.width as u16).to_le_bytes())?;
+        self.out.write_all(&(self.height as u16).to_le_bytes())?;
+        self.out.write_all(&[0, MIN_CODE_SIZE])?;
+        let indices: Vec<u8> = image.pixels.iter().map(|&rgb| nearest_ansi256(rgb)).collect();
+        for block in compress(&indices).chunks(255) {
+            self.out.write_all(&[block.len() as u8])?;
+            self.out.write_all(block)?;
+        }
+        self.out.write_all(&[0])
+    }
+
+    pub fn finish(mut self) -> std::io::Result<()> {
+        self.out.write_all(b"\x3b")?;
+        self.out.flush()
+    }
+}
+
+// LZW codes packed least significant bit first
+struct Bits {
+    bytes: Vec<u8>,
+    pending: u32,
+    count: u8,
+}
+
+impl Bits {
+    fn push(&mut self, code: u16, size: u8) {
+        self.pending |= (code as u32) << self.count;
+        self.count += size;
+        while self.count >= 8 {
+            self.bytes.push(self.pending as u8);
+            self.pending >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn finish(mut self) -> Vec<u8> {
+        if self.count > 0 { self.bytes.push(self.pending as u8); }
+        self.bytes
+    }
+}
+
+// `indices` LZW compressed the way GIF decoders expect: codes grow a bit
+// once the table has outgrown their size, and the table starts over when
+// it is full
+fn compress(indices: &[u8]) -> Vec<u8> {
+    let mut bits = Bits { bytes: Vec::new(), pending: 0, count: 0 };
+    let mut table: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut size = MIN_CODE_SIZE + 1;
+    let mut next = END + 1;
+    bits.push(CLEAR, size);
+    let Some((&first, rest)) = indices.split_first() else {
+        bits.push(END, size);
+        return bits.finish();
+    };
+    let mut prefix = first as u16;
+    for &index in rest {
+        if let Some(&code) = table.get(&(prefix, index)) {
+            prefix = code;
+            continue;
+        }
+        bits.push(prefix, size);
+        table.insert((prefix, index), next);
+        next += 1;
+        // Decoders are a code behind, so they grow the size one later
+        if next > 1 << size && size < 12 { size += 1; }
+        if next == MAX_CODES {
+            bits.push(CLEAR, size);
+            table.clear();
+            (size, next) = (MIN_CODE_SIZE + 1, END + 1);
+        }
+        prefix = index as u16;
+    }
+    bits.push(prefix, size);
+    // Having caught up, the decoder reads the end with the grown size
+    if next == 1 << size && size < 12 { size += 1; }
+    bits.push(END, size);
+    bits.finish()
+}
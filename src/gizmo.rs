@@ -0,0 +1,169 @@
+// Handles for moving, turning and resizing the selected object. One handle
+// per world axis sticks out of the object's position; dragging along a
+// handle's on-screen direction changes the transform along that axis
+
+use crossterm::style::Color;
+
+use crate::framebuffer::{Cell, FrameBuffer};
+use crate::render::{for_each_line_cell, slope_char, Projection};
+use crate::scene::Object;
+
+const AXES: [[f32; 3]; 3] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+const AXIS_COLORS: [Color; 3] = [Color::Red, Color::Green, Color::Blue];
+const AXIS_LABELS: [char; 3] = ['X', 'Y', 'Z'];
+// Slot of `Object::turn` rotating around each axis
+const TURN_SLOTS: [usize; 3] = [1, 0, 2];
+// Handle length over the object's extent, so they reach past its surface
+const HANDLE_REACH: f32 = 1.2;
+// Cells away from a handle a press still grabs it
+const GRAB_DISTANCE: f32 = 1.5;
+// Handles pointing almost straight at the eye can't be dragged along,
+// measured in cells per world unit
+const MIN_SCREEN_LENGTH: f32 = 0.5;
+// What one key press changes: world units, radians and a factor
+const MOVE_STEP: f32 = 0.1;
+const ROTATE_STEP: f32 = 0.1;
+const SCALE_STEP: f32 = 1.1;
+const MIN_SCALE: f32 = 0.05;
+
+// What dragging a handle or nudging with keys changes
+#[derive(Clone, Copy, PartialEq)]
+pub enum Tool {
+    Move,
+    Rotate,
+    Scale,
+}
+
+impl Tool {
+    pub fn name(self) -> &'static str {
+        match self {
+            Tool::Move => "move",
+            Tool::Rotate => "rotate",
+            Tool::Scale => "scale",
+        }
+    }
+
+    pub fn next(self) -> Tool {
+        match self {
+            Tool::Move => Tool::Rotate,
+            Tool::Rotate => Tool::Scale,
+            Tool::Scale => Tool::Move,
+        }
+    }
+}
+
+// A handle being dragged
+struct Held {
+    axis: usize,
+    // Screen cell the pointer was last seen at
+    last: (u16, u16),
+    // On-screen movement in cells for one world unit along the axis, taken
+    // when grabbed so the handle moving along doesn't feed back
+    per_unit: (f32, f32),
+}
+
+pub struct Gizmo {
+    pub tool: Tool,
+    held: Option<Held>,
+}
+
+impl Gizmo {
+    pub fn new() -> Gizmo {
+        Gizmo { tool: Tool::Move, held: None }
+    }
+
+    // Grab the handle of `object` nearest to `cell` of the view, if a
+    // press there reaches one. `screen` is the same cell on the terminal
+    pub fn grab(&mut self, object: &Object, proj: &Projection, cell: (i32, i32), screen: (u16, u16)) -> bool {
+        let point = (cell.0 as f32 + 0.5, cell.1 as f32 + 0.5);
+        self.held = handles(object, proj).iter()
+            .enumerate()
+            .filter_map(|(axis, handle)| handle.map(|(from, to)| (axis, from, to)))
+            .map(|(axis, from, to)| (segment_distance(point, from, to), axis, from, to))
+            .filter(|(distance, ..)| *distance <= GRAB_DISTANCE)
+            .min_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(_, axis, from, to)| {
+                let reach = object.extent() * HANDLE_REACH;
+                Held { axis, last: screen, per_unit: ((to.0 - from.0) / reach, (to.1 - from.1) / reach) }
+            })
+            .filter(|held| held.per_unit.0.hypot(held.per_unit.1) >= MIN_SCREEN_LENGTH);
+        self.held.is_some()
+    }
+
+    pub fn holding(&self) -> bool {
+        self.held.is_some()
+    }
+
+    // The pointer moved to `screen` while holding a handle
+    pub fn drag(&mut self, object: &mut Object, screen: (u16, u16)) {
+        let Some(held) = &mut self.held else { return; };
+        let delta = (screen.0 as f32 - held.last.0 as f32, screen.1 as f32 - held.last.1 as f32);
+        held.last = screen;
+        let (ux, uy) = held.per_unit;
+        // World units along the axis that move the handle closest to the pointer
+        let amount = (delta.0 * ux + delta.1 * uy) / (ux * ux + uy * uy);
+        let axis = held.axis;
+        match self.tool {
+            Tool::Move => object.position[axis] += amount,
+            Tool::Rotate => object.turn[TURN_SLOTS[axis]] += amount,
+            // The handle tip follows the pointer, so its length sets the size
+            Tool::Scale => {
+                let reach = object.extent() * HANDLE_REACH;
+                object.scale = (object.scale * (reach + amount) / reach).max(MIN_SCALE);
+            }
+        }
+    }
+
+    pub fn release(&mut self) {
+        self.held = None;
+    }
+
+    // One key press worth of the tool along `axis`, `sign` picks the way
+    pub fn nudge(&self, object: &mut Object, axis: usize, sign: f32) {
+        match self.tool {
+            Tool::Move => object.position[axis] += MOVE_STEP * sign,
+            Tool::Rotate => object.turn[TURN_SLOTS[axis]] += ROTATE_STEP * sign,
+            Tool::Scale => object.scale = (object.scale * SCALE_STEP.powf(sign)).max(MIN_SCALE),
+        }
+    }
+}
+
+// Screen start and end of a handle in fractional cells
+type Handle = ((f32, f32), (f32, f32));
+
+// Each axis' handle, `None` when either end is behind the eye
+fn handles(object: &Object, proj: &Projection) -> [Option<Handle>; 3] {
+    let reach = object.extent() * HANDLE_REACH;
+    let center = proj.project_f(object.position);
+    AXES.map(|axis| {
+        let tip = proj.project_f(std::array::from_fn(|i| object.position[i] + axis[i] * reach));
+        center.zip(tip).map(|(c, t)| ((c.0, c.1), (t.0, t.1)))
+    })
+}
+
+// Distance from `p` to the segment from `a` to `b`
+fn segment_distance(p: (f32, f32), a: (f32, f32), b: (f32, f32)) -> f32 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length2 = dx * dx + dy * dy;
+    let t = if length2 > 0.0 { (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / length2).clamp(0.0, 1.0) } else { 0.0 };
+    (p.0 - a.0 - dx * t).hypot(p.1 - a.1 - dy * t)
+}
+
+// Draw the handles of `object` over everything else, each ending in the
+// name of its axis
+pub fn draw_handles(fb: &mut FrameBuffer, object: &Object, proj: &Projection) {
+    for (axis, handle) in handles(object, proj).iter().enumerate() {
+        let Some((from, to)) = *handle else { continue; };
+        let color = AXIS_COLORS[axis];
+        let (p0, p1) = ((from.0, from.1, 0.0), (to.0, to.1, 0.0));
+        let mut put = |x: i32, y: i32, c: char| {
+            if x >= 0 && y >= 0 && (x as usize) < fb.width && (y as usize) < fb.height {
+                fb.cells[y as usize * fb.width + x as usize] = Cell::glyph(c, color);
+            }
+        };
+        for_each_line_cell((from.0 as i32, from.1 as i32, 1.0), (to.0 as i32, to.1 as i32, 1.0), |x, y, _| {
+            put(x, y, slope_char(p0, p1, x, y, proj.aspect));
+        });
+        put(to.0 as i32, to.1 as i32, AXIS_LABELS[axis]);
+    }
+}
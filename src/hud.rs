@@ -0,0 +1,179 @@
+use std::time::Duration;
+
+use crossterm::style::Color;
+
+use crate::framebuffer::{Cell, FrameBuffer, Glyph};
+use crate::gizmo::Tool;
+use crate::picking::Pick;
+use crate::render::RenderMode;
+
+const HUD_COLOR: Color = Color::Grey;
+const HELP_COLOR: Color = Color::White;
+const HELP_BACKGROUND: Color = Color::Black;
+// What is left of the light of shaded cells under the help panel
+const HELP_DIM: f32 = 0.3;
+// Weight of the newest frame in the smoothed frame rate
+const FPS_SMOOTHING: f32 = 0.1;
+
+// What the status line reports about one frame
+pub struct FrameStats {
+    // Time spent rendering and drawing, without waiting for the next frame
+    pub frame_time: Duration,
+    pub triangles: usize,
+    pub width: usize,
+    pub height: usize,
+    pub mode: RenderMode,
+    // Whether the fly camera is in use
+    pub flying: bool,
+    // Animation speed, and whether it is stopped
+    pub speed: f32,
+    pub paused: bool,
+    // Seconds of animation shown, to tell stepped frames apart
+    pub time: f32,
+    // The face last clicked on, and what editing it changes
+    pub selected: Option<Pick>,
+    pub tool: Tool,
+}
+
+// The words of the status line in one language. What is typed, like the
+// render modes and the tools, stays as it is
+pub struct Words {
+    pub code: &'static str,
+    fps: &'static str,
+    triangles: &'static str,
+    paused_at: &'static str,
+    object: &'static str,
+    face: &'static str,
+    fly: &'static str,
+    orbit: &'static str,
+}
+
+pub const LANGUAGES: [Words; 2] = [
+    Words { code: "en", fps: "fps", triangles: "triangles", paused_at: "paused at", object: "object", face: "face",
+        fly: "fly", orbit: "orbit" },
+    Words { code: "fr", fps: "i/s", triangles: "triangles", paused_at: "en pause à", object: "objet", face: "face",
+        fly: "vol", orbit: "orbite" },
+];
+
+pub fn language(code: &str) -> Option<&'static Words> {
+    LANGUAGES.iter().find(|words| words.code == code)
+}
+
+// The language the locale asks for, like fr_FR.UTF-8, English for one
+// there are no words for
+pub fn locale_language() -> &'static Words {
+    let locale = ["LC_ALL", "LC_MESSAGES", "LANG"].iter()
+        .filter_map(|var| std::env::var(var).ok())
+        .find(|value| !value.is_empty())
+        .unwrap_or_default();
+    let code = locale.split(['_', '.', '@']).next().unwrap_or("");
+    language(code).unwrap_or(&LANGUAGES[0])
+}
+
+// The status line below the frame and the help panel, toggled at runtime.
+// The status line's row also holds the command line
+pub struct Hud {
+    pub visible: bool,
+    pub help: bool,
+    // What is typed after `:` so far, `None` when not typing a command
+    pub command: Option<String>,
+    // Answer to the last command, until the next key
+    pub message: Option<String>,
+    pub words: &'static Words,
+    fps: f32,
+}
+
+impl Hud {
+    pub fn new() -> Hud {
+        Hud { visible: true, help: false, command: None, message: None, words: &LANGUAGES[0], fps: 0.0 }
+    }
+
+    // Rows the HUD takes from the frame
+    pub fn rows(&self) -> u16 {
+        (self.visible || self.command.is_some() || self.message.is_some()) as u16
+    }
+
+    // Account for `interval` having passed since the previous frame
+    pub fn tick(&mut self, interval: Duration) {
+        let fps = 1.0 / interval.as_secs_f32().max(1e-6);
+        self.fps = if self.fps == 0.0 { fps } else { self.fps + (fps - self.fps) * FPS_SMOOTHING };
+    }
+
+    // The bottom row: the command being typed, the last command's answer,
+    // or the status line
+    pub fn bottom_line(&self, stats: &FrameStats) -> String {
+        match (&self.command, &self.message) {
+            (Some(command), _) => format!(":{command}_"),
+            (None, Some(message)) => format!(" {message}"),
+            (None, None) => self.line(stats),
+        }
+    }
+
+    pub fn line(&self, stats: &FrameStats) -> String {
+        let words = self.words;
+        let playing = match stats.paused {
+            true => format!("{} {:.3}s", words.paused_at, stats.time),
+            false => format!("{:.2}x", stats.speed),
+        };
+        let camera = if stats.flying { words.fly } else { words.orbit };
+        // Counted from 1 like everything else the user sees
+        let selected = match stats.selected {
+            Some(pick) => format!("| {} {} {} {} | {} ", words.object, pick.object + 1, words.face, pick.face + 1, stats.tool.name()),
+            None => String::new(),
+        };
+        format!(" {:.1} {} | {:.1} ms | {} {} | {}x{} | {} | {} | {} {}",
+            self.fps, words.fps, stats.frame_time.as_secs_f32() * 1000.0, stats.triangles, words.triangles,
+            stats.width, stats.height, stats.mode.name(), camera, playing, selected)
+    }
+}
+
+// `fb` with `text` as an extra row below it, cut to the frame's width
+pub fn append_line(fb: &FrameBuffer, text: &str) -> FrameBuffer {
+    let mut out = FrameBuffer::new(fb.width, fb.height + 1);
+    out.cells[..fb.cells.len()].copy_from_slice(&fb.cells);
+    out.depth[..fb.depth.len()].copy_from_slice(&fb.depth);
+    for (x, c) in text.chars().take(fb.width).enumerate() {
+        out.cells[fb.cells.len() + x] = Cell::glyph(c, HUD_COLOR);
+    }
+    out
+}
+
+// Rows of the help panel: `title`, then each key next to what it does
+pub fn help_lines(title: &str, keys: &[(&str, &str)]) -> Vec<String> {
+    let key_width = keys.iter().map(|(key, _)| key.chars().count()).max().unwrap_or(0);
+    let mut lines = vec![format!(" {title} "), String::new()];
+    lines.extend(keys.iter().map(|(key, action)| format!(" {key:<key_width$}  {action} ")));
+    lines
+}
+
+// Top-left cell of the panel holding `lines`, centered in a `width` x
+// `height` area
+pub fn help_origin(width: usize, height: usize, lines: &[String]) -> (usize, usize) {
+    let panel_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    (width.saturating_sub(panel_width) / 2, height.saturating_sub(lines.len()) / 2)
+}
+
+// `fb` with the help panel over its middle. The frame shows through dimmed
+// where a line is shorter than the panel
+pub fn with_help(fb: &FrameBuffer, lines: &[String]) -> FrameBuffer {
+    let mut out = FrameBuffer::new(fb.width, fb.height);
+    out.cells.copy_from_slice(&fb.cells);
+    out.depth.copy_from_slice(&fb.depth);
+    let (x0, y0) = help_origin(fb.width, fb.height, lines);
+    let panel_width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0);
+    for (y, line) in lines.iter().enumerate().take(fb.height - y0) {
+        let row = (y0 + y) * fb.width;
+        for x in x0..(x0 + panel_width).min(fb.width) {
+            let cell = &mut out.cells[row + x];
+            match cell.glyph {
+                Glyph::Ramp | Glyph::Hatch(_) => cell.intensity *= HELP_DIM,
+                Glyph::Fixed(_) => cell.color = HUD_COLOR,
+            }
+            cell.background = Some(HELP_BACKGROUND);
+        }
+        for (x, c) in line.chars().enumerate().take(fb.width.saturating_sub(x0)) {
+            out.cells[row + x0 + x] = Cell { background: Some(HELP_BACKGROUND), ..Cell::glyph(c, HELP_COLOR) };
+        }
+    }
+    out
+}
@@ -0,0 +1,31 @@
+// iTerm2 inline images: the frame is sent as a PNG file inside an OSC 1337
+// sequence, which iTerm2 decodes and draws over the cells
+
+use std::io::Write;
+
+use base64::{engine::general_purpose::STANDARD, Engine};
+use image::codecs::png::{CompressionType, FilterType, PngEncoder};
+use image::{ExtendedColorType, ImageEncoder};
+
+use crate::pixels::Bitmap;
+
+// Whether the terminal is iTerm2, which also sets these over ssh
+pub fn iterm_supported() -> bool {
+    std::env::var("TERM_PROGRAM").is_ok_and(|p| p == "iTerm.app")
+        || std::env::var("LC_TERMINAL").is_ok_and(|t| t == "iTerm2")
+}
+
+// Write `image` as an inline image at the cursor, sized to exactly its own
+// pixels. A new image is sent every frame, so compression is kept cheap
+pub fn write_iterm(out: &mut impl Write, image: &Bitmap) -> std::io::Result<()> {
+    let mut png = Vec::new();
+    let raw: Vec<u8> = image.pixels.iter().flatten().copied().collect();
+    PngEncoder::new_with_quality(&mut png, CompressionType::Fast, FilterType::Sub)
+        .write_image(&raw, image.width as u32, image.height as u32, ExtendedColorType::Rgb8)
+        .map_err(std::io::Error::other)?;
+
+    write!(out, "\x1b]1337;File=inline=1;size={};width={}px;height={}px;preserveAspectRatio=0:",
+        png.len(), image.width, image.height)?;
+    out.write_all(STANDARD.encode(&png).as_bytes())?;
+    out.write_all(b"\x07")
+}
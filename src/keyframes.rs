@@ -0,0 +1,80 @@
+// Keyframes: values at given times with the ones in between worked out,
+// eased on the way into each key. In statements and scene files they are
+// written keys(TIME: VALUE EASE, ...), the easing being optional
+//
+//   cube.position = keys(0: (0, 0, 0), 1: (0, 2, 0) cubic, 2: (0, 0, 0) bounce)
+//   camera.position = keys(0: (0, 1, -6), 4: (6, 2, 0) cubic)
+//
+// Before the first key the property holds the first value, after the last
+// one the last value. Two keys at the same time jump from one to the other
+
+use std::f32::consts::TAU;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Ease {
+    Linear,
+    // Slow at both ends
+    Cubic,
+    // Overshoots and springs back
+    Elastic,
+    // Drops onto the value and bounces off it
+    Bounce,
+}
+
+pub const EASE_NAMES: [&str; 4] = ["linear", "cubic", "elastic", "bounce"];
+
+impl Ease {
+    pub fn parse(s: &str) -> Option<Ease> {
+        match s {
+            "linear" => Some(Ease::Linear),
+            "cubic" => Some(Ease::Cubic),
+            "elastic" => Some(Ease::Elastic),
+            "bounce" => Some(Ease::Bounce),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Ease::Linear => "linear",
+            Ease::Cubic => "cubic",
+            Ease::Elastic => "elastic",
+            Ease::Bounce => "bounce",
+        }
+    }
+
+    // How far along the way `u`, from 0 to 1, of the time between two keys
+    // gets the value. Elastic goes past 1 for a while
+    pub fn apply(self, u: f32) -> f32 {
+        match self {
+            Ease::Linear => u,
+            Ease::Cubic if u < 0.5 => 4.0 * u * u * u,
+            Ease::Cubic => 1.0 - (2.0 - 2.0 * u).powi(3) / 2.0,
+            Ease::Elastic if u <= 0.0 || u >= 1.0 => u.clamp(0.0, 1.0),
+            Ease::Elastic => 2f32.powf(-10.0 * u) * ((u * 10.0 - 0.75) * TAU / 3.0).sin() + 1.0,
+            Ease::Bounce => {
+                // Four arcs, each lower than the one before
+                const SPRING: f32 = 7.5625;
+                const WIDTH: f32 = 2.75;
+                match u {
+                    u if u < 1.0 / WIDTH => SPRING * u * u,
+                    u if u < 2.0 / WIDTH => SPRING * (u - 1.5 / WIDTH).powi(2) + 0.75,
+                    u if u < 2.5 / WIDTH => SPRING * (u - 2.25 / WIDTH).powi(2) + 0.9375,
+                    u => SPRING * (u - 2.625 / WIDTH).powi(2) + 0.984375,
+                }
+            }
+        }
+    }
+}
+
+// Where time `t` falls among keys at `times`, which never go down: the key
+// before it, the key after it and how far the value has got from one to the
+// other
+pub fn segment(times: &[f32], eases: &[Ease], t: f32) -> (usize, usize, f32) {
+    let last = times.len() - 1;
+    if t <= times[0] { return (0, 0, 0.0); }
+    if t >= times[last] { return (last, last, 0.0); }
+    let next = times.iter().position(|time| *time > t).unwrap_or(last);
+    let u = (t - times[next - 1]) / (times[next] - times[next - 1]);
+    (next - 1, next, eases[next].apply(u))
+}
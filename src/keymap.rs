@@ -0,0 +1,329 @@
+// Which key does what. Bindings start from a named profile and single
+// actions can be bound to other keys in the config file. Ctrl+C always
+// quits and the digits always go to camera bookmarks, whatever the map says
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Action {
+    Quit,
+    NextMode,
+    Culling,
+    Edges,
+    NextShading,
+    PlaceLight,
+    Pause,
+    Step,
+    ScrubBack,
+    ScrubForward,
+    Reverse,
+    Faster,
+    Slower,
+    StatusLine,
+    Help,
+    Menu,
+    Command,
+    Screenshot,
+    Undo,
+    Redo,
+    Fly,
+    NextTool,
+    Nudge { axis: usize, back: bool },
+    TurnLeft,
+    TurnRight,
+    TurnUp,
+    TurnDown,
+    // Change the flying speed instead while flying
+    ZoomIn,
+    ZoomOut,
+    // Only while flying
+    FlyForward,
+    FlyBack,
+    FlyLeft,
+    FlyRight,
+    Rise,
+    Sink,
+    LookLeft,
+    LookRight,
+    LookUp,
+    LookDown,
+}
+
+// Every action with its name in the config file
+const ACTIONS: [(Action, &str); 44] = [
+    (Action::Quit, "quit"),
+    (Action::NextMode, "next-mode"),
+    (Action::Culling, "culling"),
+    (Action::Edges, "edges"),
+    (Action::NextShading, "next-shading"),
+    (Action::PlaceLight, "place-light"),
+    (Action::Pause, "pause"),
+    (Action::Step, "step"),
+    (Action::ScrubBack, "scrub-back"),
+    (Action::ScrubForward, "scrub-forward"),
+    (Action::Reverse, "reverse"),
+    (Action::Faster, "faster"),
+    (Action::Slower, "slower"),
+    (Action::StatusLine, "status-line"),
+    (Action::Help, "help"),
+    (Action::Menu, "menu"),
+    (Action::Command, "command"),
+    (Action::Screenshot, "screenshot"),
+    (Action::Undo, "undo"),
+    (Action::Redo, "redo"),
+    (Action::Fly, "fly"),
+    (Action::NextTool, "next-tool"),
+    (Action::Nudge { axis: 0, back: false }, "nudge-x"),
+    (Action::Nudge { axis: 0, back: true }, "nudge-x-back"),
+    (Action::Nudge { axis: 1, back: false }, "nudge-y"),
+    (Action::Nudge { axis: 1, back: true }, "nudge-y-back"),
+    (Action::Nudge { axis: 2, back: false }, "nudge-z"),
+    (Action::Nudge { axis: 2, back: true }, "nudge-z-back"),
+    (Action::TurnLeft, "turn-left"),
+    (Action::TurnRight, "turn-right"),
+    (Action::TurnUp, "turn-up"),
+    (Action::TurnDown, "turn-down"),
+    (Action::ZoomIn, "zoom-in"),
+    (Action::ZoomOut, "zoom-out"),
+    (Action::FlyForward, "fly-forward"),
+    (Action::FlyBack, "fly-back"),
+    (Action::FlyLeft, "fly-left"),
+    (Action::FlyRight, "fly-right"),
+    (Action::Rise, "rise"),
+    (Action::Sink, "sink"),
+    (Action::LookLeft, "look-left"),
+    (Action::LookRight, "look-right"),
+    (Action::LookUp, "look-up"),
+    (Action::LookDown, "look-down"),
+];
+
+// Rows of the help panel: actions listed together and what they do while
+// orbiting and while flying, `None` leaving the row out
+type HelpRow = (&'static [Action], Option<&'static str>, Option<&'static str>);
+const HELP_ROWS: [HelpRow; 26] = [
+    (&[Action::TurnLeft, Action::TurnRight, Action::TurnUp, Action::TurnDown], Some("turn the objects left, right, up, down"), None),
+    (&[Action::FlyForward, Action::FlyBack, Action::FlyLeft, Action::FlyRight], None, Some("fly forward, back, left, right")),
+    (&[Action::Rise, Action::Sink], None, Some("rise, sink")),
+    (&[Action::LookLeft, Action::LookRight, Action::LookUp, Action::LookDown], None, Some("look left, right, up, down")),
+    (&[Action::ZoomIn, Action::ZoomOut], Some("zoom in, out"), Some("fly faster, slower")),
+    (&[Action::Fly], Some("fly camera"), Some("orbit camera")),
+    (&[Action::NextMode], Some("next render mode"), Some("next render mode")),
+    (&[Action::Culling], Some("backface culling on, off"), Some("backface culling on, off")),
+    (&[Action::Edges], Some("edges on, off"), Some("edges on, off")),
+    (&[Action::NextShading], Some("next shading model"), Some("next shading model")),
+    (&[Action::PlaceLight], Some("the key light follows the pointer"), Some("the key light follows the pointer")),
+    (&[Action::Pause], Some("pause"), Some("pause")),
+    (&[Action::Step], Some("pause and step one frame"), Some("pause and step one frame")),
+    (&[Action::ScrubBack, Action::ScrubForward], Some("pause and go back, on in time"), Some("pause and go back, on in time")),
+    (&[Action::Slower, Action::Faster], Some("slower, faster"), Some("slower, faster")),
+    (&[Action::Reverse], Some("play backwards, forwards"), Some("play backwards, forwards")),
+    (&[Action::NextTool], Some("next editing tool"), Some("next editing tool")),
+    (&[Action::Nudge { axis: 0, back: false }, Action::Nudge { axis: 1, back: false }, Action::Nudge { axis: 2, back: false }],
+        Some("nudge the selected object along x, y, z"), Some("nudge the selected object along x, y, z")),
+    (&[Action::Nudge { axis: 0, back: true }, Action::Nudge { axis: 1, back: true }, Action::Nudge { axis: 2, back: true }],
+        Some("nudge it back"), Some("nudge it back")),
+    (&[Action::Undo, Action::Redo], Some("undo, redo an edit"), Some("undo, redo an edit")),
+    (&[Action::StatusLine], Some("status line"), Some("status line")),
+    (&[Action::Command], Some("command line: load, mode, speed, spawn, quit..."), Some("command line: load, mode, speed, spawn, quit...")),
+    (&[Action::Screenshot], Some("save the frame to a file"), Some("save the frame to a file")),
+    (&[Action::Menu], Some("options menu"), Some("options menu")),
+    (&[Action::Help], Some("this help"), Some("this help")),
+    (&[Action::Quit], Some("quit"), Some("quit")),
+];
+
+pub const PROFILE_NAMES: [&str; 2] = ["default", "vim"];
+
+impl Action {
+    pub fn parse(s: &str) -> Option<Action> {
+        ACTIONS.iter().find(|(_, name)| *name == s).map(|(action, _)| *action)
+    }
+
+    // Actions that only do something while flying
+    pub fn flying(self) -> bool {
+        matches!(self, Action::FlyForward | Action::FlyBack | Action::FlyLeft | Action::FlyRight
+            | Action::Rise | Action::Sink | Action::LookLeft | Action::LookRight | Action::LookUp | Action::LookDown)
+    }
+
+    // Actions that are taken over by flying ones while flying
+    fn orbiting(self) -> bool {
+        matches!(self, Action::TurnLeft | Action::TurnRight | Action::TurnUp | Action::TurnDown)
+    }
+}
+
+// A key, with Ctrl or without. Shifted characters are told apart by the
+// character itself
+#[derive(Clone, Copy, PartialEq)]
+pub struct Key {
+    pub code: KeyCode,
+    pub ctrl: bool,
+}
+
+// Keys that aren't a character, by name
+const KEY_NAMES: [(&str, KeyCode); 13] = [
+    ("Space", KeyCode::Char(' ')),
+    ("Esc", KeyCode::Esc),
+    ("Enter", KeyCode::Enter),
+    ("Tab", KeyCode::Tab),
+    ("Backspace", KeyCode::Backspace),
+    ("Left", KeyCode::Left),
+    ("Right", KeyCode::Right),
+    ("Up", KeyCode::Up),
+    ("Down", KeyCode::Down),
+    ("PgUp", KeyCode::PageUp),
+    ("PgDn", KeyCode::PageDown),
+    ("Home", KeyCode::Home),
+    ("End", KeyCode::End),
+];
+
+impl Key {
+    // A character or a name from `KEY_NAMES`, either after Ctrl+ or not
+    pub fn parse(s: &str) -> Option<Key> {
+        let (ctrl, s) = match s.strip_prefix("Ctrl+") {
+            Some(rest) => (true, rest),
+            None => (false, s),
+        };
+        let mut chars = s.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) => KeyCode::Char(c),
+            _ => KEY_NAMES.iter().find(|(name, _)| *name == s)?.1,
+        };
+        Some(Key { code, ctrl })
+    }
+
+    pub fn name(self) -> String {
+        let name = match KEY_NAMES.iter().find(|(_, code)| *code == self.code) {
+            Some((name, _)) => name.to_string(),
+            None => match self.code {
+                KeyCode::Char(c) => c.to_string(),
+                _ => "?".to_string(),
+            },
+        };
+        if self.ctrl { format!("Ctrl+{name}") } else { name }
+    }
+
+    fn matches(self, event: &KeyEvent) -> bool {
+        self.code == event.code && self.ctrl == event.modifiers.contains(KeyModifiers::CONTROL)
+    }
+}
+
+pub struct Keymap {
+    bindings: Vec<(Key, Action)>,
+}
+
+impl Keymap {
+    // The bindings of a profile in `PROFILE_NAMES`
+    pub fn profile(name: &str) -> Option<Keymap> {
+        let mut map = Keymap { bindings: Vec::new() };
+        let bind = |map: &mut Keymap, action: Action, keys: &[&str]| {
+            map.bind(action, keys.iter().map(|k| Key::parse(k).expect("profile keys are valid")).collect());
+        };
+        for (action, keys) in DEFAULT_KEYS {
+            bind(&mut map, action, keys);
+        }
+        match name {
+            "default" => {}
+            // hjkl turn the objects and move the fly camera, which leaves
+            // the status line on H
+            "vim" => {
+                bind(&mut map, Action::TurnLeft, &["h", "Left"]);
+                bind(&mut map, Action::TurnDown, &["j", "Down"]);
+                bind(&mut map, Action::TurnUp, &["k", "Up"]);
+                bind(&mut map, Action::TurnRight, &["l", "Right"]);
+                bind(&mut map, Action::FlyLeft, &["h"]);
+                bind(&mut map, Action::FlyBack, &["j"]);
+                bind(&mut map, Action::FlyForward, &["k"]);
+                bind(&mut map, Action::FlyRight, &["l"]);
+                bind(&mut map, Action::Rise, &["Ctrl+u", "PgUp"]);
+                bind(&mut map, Action::Sink, &["Ctrl+d", "PgDn"]);
+                bind(&mut map, Action::StatusLine, &["H"]);
+                bind(&mut map, Action::PlaceLight, &["L"]);
+            }
+            _ => return None,
+        }
+        Some(map)
+    }
+
+    // Bind `action` to `keys` instead of whatever it was bound to
+    pub fn bind(&mut self, action: Action, keys: Vec<Key>) {
+        self.bindings.retain(|(_, a)| *a != action);
+        self.bindings.extend(keys.into_iter().map(|key| (key, action)));
+    }
+
+    // What `event` does. Flying actions take the key over while flying and
+    // do nothing otherwise
+    pub fn action(&self, event: &KeyEvent, flying: bool) -> Option<Action> {
+        let mut actions = self.bindings.iter().filter(|(key, _)| key.matches(event)).map(|(_, a)| *a);
+        if flying {
+            let actions: Vec<Action> = actions.collect();
+            actions.iter().find(|a| a.flying()).or(actions.iter().find(|a| !a.orbiting())).copied()
+        } else {
+            actions.find(|a| !a.flying())
+        }
+    }
+
+    // Rows for the help panel with the camera in use: the keys of a group
+    // of actions, next to what they do
+    pub fn help(&self, flying: bool) -> Vec<(String, &'static str)> {
+        HELP_ROWS.iter()
+            .filter_map(|(actions, orbiting, flying_what)| {
+                let what = if flying { *flying_what } else { *orbiting }?;
+                let keys: Vec<String> = actions.iter()
+                    .map(|action| {
+                        let names: Vec<String> = self.bindings.iter().filter(|(_, a)| a == action).map(|(k, _)| k.name()).collect();
+                        names.join("/")
+                    })
+                    .filter(|names| !names.is_empty())
+                    .collect();
+                (!keys.is_empty()).then(|| (keys.join(" "), what))
+            })
+            .collect()
+    }
+}
+
+const DEFAULT_KEYS: [(Action, &[&str]); 44] = [
+    (Action::Quit, &["q"]),
+    (Action::NextMode, &["m"]),
+    (Action::Culling, &["c"]),
+    (Action::Edges, &["e"]),
+    (Action::NextShading, &["n"]),
+    (Action::PlaceLight, &["l"]),
+    (Action::Pause, &["Space"]),
+    (Action::Step, &["."]),
+    (Action::ScrubBack, &["Ctrl+Left"]),
+    (Action::ScrubForward, &["Ctrl+Right"]),
+    (Action::Reverse, &["r"]),
+    (Action::Faster, &["]"]),
+    (Action::Slower, &["["]),
+    (Action::StatusLine, &["h"]),
+    (Action::Help, &["?"]),
+    (Action::Menu, &["Esc"]),
+    (Action::Command, &[":"]),
+    // s itself turns the objects and flies back
+    (Action::Screenshot, &["Ctrl+s"]),
+    (Action::Undo, &["u"]),
+    (Action::Redo, &["Ctrl+r"]),
+    (Action::Fly, &["f"]),
+    (Action::NextTool, &["t"]),
+    (Action::Nudge { axis: 0, back: false }, &["x"]),
+    (Action::Nudge { axis: 0, back: true }, &["X"]),
+    (Action::Nudge { axis: 1, back: false }, &["y"]),
+    (Action::Nudge { axis: 1, back: true }, &["Y"]),
+    (Action::Nudge { axis: 2, back: false }, &["z"]),
+    (Action::Nudge { axis: 2, back: true }, &["Z"]),
+    (Action::TurnLeft, &["Left", "a"]),
+    (Action::TurnRight, &["Right", "d"]),
+    (Action::TurnUp, &["Up", "w"]),
+    (Action::TurnDown, &["Down", "s"]),
+    (Action::ZoomIn, &["+", "="]),
+    (Action::ZoomOut, &["-"]),
+    (Action::FlyForward, &["w"]),
+    (Action::FlyBack, &["s"]),
+    (Action::FlyLeft, &["a"]),
+    (Action::FlyRight, &["d"]),
+    (Action::Rise, &["PgUp"]),
+    (Action::Sink, &["PgDn"]),
+    (Action::LookLeft, &["Left"]),
+    (Action::LookRight, &["Right"]),
+    (Action::LookUp, &["Up"]),
+    (Action::LookDown, &["Down"]),
+];
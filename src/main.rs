@@ -1,345 +1,1194 @@
+mod batch;
+mod bookmarks;
+mod camera;
+mod camerapath;
+mod cli;
+mod color;
+mod command;
+mod config;
+mod framebuffer;
+#[cfg(feature = "gamepad")]
+mod gamepad;
+mod gif;
+mod gizmo;
+mod hud;
+mod iterm;
+mod keyframes;
+mod keymap;
+mod math;
+mod menu;
+#[cfg(feature = "midi")]
+mod midi;
+mod obj;
+mod orbit;
+mod output;
+mod palette;
+mod picking;
+mod pixels;
+mod playback;
+mod post;
+mod raytrace;
+mod render;
+mod replay;
+mod runstats;
+mod scene;
+mod scenefile;
+mod screenshot;
+mod script;
+mod shading;
+mod shadow;
+mod spline;
+mod sixel;
+mod subcell;
+mod terminal;
+mod texture;
+mod timeline;
+mod undo;
+mod wireframe;
+
+use std::io::{stdout, BufWriter, IsTerminal, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use clap::Parser;
 use crossterm::{
-    cursor::position,
-    terminal::size,
-    style::{Color, SetForegroundColor, ResetColor},
+    cursor::MoveTo,
+    event::{poll, read, Event, KeyCode, KeyEventKind, KeyModifiers, MouseButton, MouseEventKind},
+    queue,
+    style::Print,
+    terminal::{size, Clear, ClearType},
 };
 
-fn usable_space() -> std::io::Result<(u16, u16)> {
+use batch::{Batch, Recording};
+use bookmarks::{slot_key, Bookmarks, Pose};
+use camera::Camera;
+use cli::{settings, Charset, Cli, Flags, Maybe};
+use color::{rgb_color, ColorSupport, BACKGROUND_RGB};
+use command::Command;
+use config::{config_path, shapes_dir, Config};
+use framebuffer::RAMP_PRESETS;
+use gizmo::Gizmo;
+use obj::load_obj;
+use hud::{append_line, help_lines, help_origin, locale_language, with_help, FrameStats, Hud, Words};
+use post::{apply_motion_blur, letterbox};
+use iterm::write_iterm;
+use keymap::{Action, Keymap};
+use menu::{Item, Menu};
+use orbit::{Orbit, DRAG_STEP};
+use output::{Backend, Screen};
+use palette::Palette;
+use picking::{pick_cell, Pick};
+use playback::{FixedStep, Playback, DEFAULT_SIM_RATE};
+use pixels::{render_bitmap, DEFAULT_CELL_PIXELS};
+use render::{render_frame, Projection, DEFAULT_ASPECT, DISTANCE, RenderSettings};
+use runstats::{Counted, RunStats, StatsTarget};
+use scenefile::{is_scene_file, load_scene_file, Loaded, Watch};
+use scene::{build_scene, is_shape, model_scene, register_shape_files, shape_mesh, shapes, Object};
+use screenshot::{screenshot, ShotFormat};
+use script::Script;
+use sixel::write_sixel;
+use subcell::SubCell;
+use terminal::{cell_pixel_size, enable_key_releases, install_signal_handler, quit_requested, TerminalGuard};
+use camerapath::{CameraPath, CameraRecorder};
+use texture::Texture;
+use timeline::Timeline;
+use undo::{History, Snapshot};
+
+// The whole screen but the `reserved` bottom rows
+fn usable_space(reserved: u16) -> std::io::Result<(u16, u16)> {
     let (cols, rows) = size()?;
-    let (cur_col, cur_row) = position()?;
+    Ok((cols, rows.saturating_sub(reserved)))
+}
 
-    let lines_below = rows.saturating_sub(cur_row + 1);
-    let cols_avail = cols.saturating_sub(cur_col);
+// Share of the turn rate a thrown object loses per second
+const DEFAULT_DAMPING: f32 = 0.9;
 
-    Ok((cols_avail, lines_below))
-}
+// Seconds recalling a camera bookmark glides for, 0 jumps
+const DEFAULT_GLIDE: f32 = 0.6;
 
-// Cube vertices (unit cube centered at origin)
-const CUBE_VERTICES: [[f32; 3]; 8] = [
-    [-1.0, -1.0, -1.0],
-    [ 1.0, -1.0, -1.0],
-    [ 1.0,  1.0, -1.0],
-    [-1.0,  1.0, -1.0],
-    [-1.0, -1.0,  1.0],
-    [ 1.0, -1.0,  1.0],
-    [ 1.0,  1.0,  1.0],
-    [-1.0,  1.0,  1.0],
-];
+// Radians one arrow key press turns the objects by
+const TURN_STEP: f32 = 0.1;
+// Factor one zoom step changes the camera distance by
+const ZOOM_STEP: f32 = 1.1;
+// Farthest the camera zooms out. The nearest keeps the whole scene past the
+// near plane
+const MAX_DISTANCE: f32 = 50.0;
+const NEAR_MARGIN: f32 = 0.5;
 
-// Cube edges (pairs of vertex indices)
-const CUBE_EDGES: [(usize, usize); 12] = [
-    (0, 1), (1, 2), (2, 3), (3, 0), // back face
-    (4, 5), (5, 6), (6, 7), (7, 4), // front face
-    (0, 4), (1, 5), (2, 6), (3, 7), // connecting edges
-];
+// Units one key press moves the fly camera at normal speed, and the
+// factor one speed step changes that by
+const FLY_STEP: f32 = 0.25;
+const FLY_SPEED_STEP: f32 = 1.25;
+// Radians the fly camera turns per column dragged or arrow key press
+const LOOK_STEP: f32 = 0.02;
+const LOOK_KEY_STEP: f32 = 0.05;
+// Units per second a held key flies at normal speed, and radians per
+// second it looks, when the terminal reports key releases
+const HELD_FLY_RATE: f32 = 3.0;
+const HELD_LOOK_RATE: f32 = 1.5;
 
-// Face definitions for coloring (4 vertices per face, with color)
-const CUBE_FACES: [([usize; 4], Color); 6] = [
-    ([0, 1, 2, 3], Color::Red),      // back
-    ([4, 5, 6, 7], Color::Green),    // front
-    ([0, 4, 7, 3], Color::Blue),     // left
-    ([1, 5, 6, 2], Color::Yellow),   // right
-    ([3, 2, 6, 7], Color::Magenta),  // top
-    ([0, 1, 5, 4], Color::Cyan),     // bottom
+// What the help overlay lists besides the key map: the mouse, with the
+// orbit camera and with the fly camera, then keys that can't be changed
+const ORBIT_MOUSE_HELP: &[(&str, &str)] = &[
+    ("drag", "turn the objects"),
+    ("wheel", "zoom"),
+];
+const FLY_MOUSE_HELP: &[(&str, &str)] = &[
+    ("drag", "look around"),
+    ("wheel", "flying speed"),
+];
+const FIXED_KEY_HELP: &[(&str, &str)] = &[
+    ("click", "select a face"),
+    ("drag a handle", "edit the selected object"),
+    ("1-9", "go to a bookmark"),
+    ("Shift+1-9", "save a bookmark"),
+    ("Ctrl+C", "quit"),
 ];
 
-fn rotate_x(point: [f32; 3], angle: f32) -> [f32; 3] {
-    let cos_a = angle.cos();
-    let sin_a = angle.sin();
-    [
-        point[0],
-        point[1] * cos_a - point[2] * sin_a,
-        point[1] * sin_a + point[2] * cos_a,
-    ]
-}
-
-fn rotate_y(point: [f32; 3], angle: f32) -> [f32; 3] {
-    let cos_a = angle.cos();
-    let sin_a = angle.sin();
-    [
-        point[0] * cos_a + point[2] * sin_a,
-        point[1],
-        -point[0] * sin_a + point[2] * cos_a,
-    ]
+// Move the orbit camera `steps` zoom steps out, no nearer than `min`, even
+// when a scene that big leaves it past the usual farthest
+fn zoom(distance: &mut f32, min: f32, steps: f32) {
+    *distance = (*distance * ZOOM_STEP.powf(steps)).clamp(min, MAX_DISTANCE.max(min));
 }
 
-fn rotate_z(point: [f32; 3], angle: f32) -> [f32; 3] {
-    let cos_a = angle.cos();
-    let sin_a = angle.sin();
-    [
-        point[0] * cos_a - point[1] * sin_a,
-        point[0] * sin_a + point[1] * cos_a,
-        point[2],
-    ]
+// What every object gets, whichever scene it is in or however it got there
+struct Dressing {
+    palette: Palette,
+    texture: Option<Arc<Texture>>,
+    // Turn rate and starting orientation, `None` keeps the object's own
+    spin: Option<[f32; 3]>,
+    turn: Option<[f32; 3]>,
+    // Axes objects don't spin around at all
+    frozen: [bool; 3],
+    // Properties bound to expressions of the time, like ("scale", "1 + sin(t) / 4")
+    motion: Vec<(&'static str, String)>,
+    // Animates the objects after everything else and cuts the camera
+    timeline: Option<Timeline>,
 }
 
-fn project(point: [f32; 3], width: usize, height: usize, fov: f32, distance: f32) -> Option<(i32, i32, f32)> {
-    let z = point[2] + distance;
-    if z <= 0.1 { return None; }
-
-    let factor = fov / z;
-    let x = (point[0] * factor * 2.0) + (width as f32 / 2.0); // *2 for aspect ratio correction
-    let y = (-point[1] * factor) + (height as f32 / 2.0);
-
-    Some((x as i32, y as i32, z))
+fn dress(object: &mut Object, dressing: &Dressing) {
+    for face in &mut object.mesh.faces {
+        face.color = dressing.palette.recolor(face.color);
+    }
+    if let Some(texture) = &dressing.texture { object.material.texture = Some(texture.clone()); }
+    if let Some(spin) = dressing.spin { object.spin = spin; }
+    if let Some(turn) = dressing.turn { object.turn = turn; }
+    for (rate, frozen) in object.spin.iter_mut().zip(dressing.frozen) {
+        if frozen { *rate = 0.0; }
+    }
 }
 
-// Bresenham's line algorithm
-fn draw_line(x0: i32, y0: i32, x1: i32, y1: i32, buffer: &mut Vec<(i32, i32, f32, char, Color)>, z: f32, color: Color) {
-    let dx = (x1 - x0).abs();
-    let dy = -(y1 - y0).abs();
-    let sx = if x0 < x1 { 1 } else { -1 };
-    let sy = if y0 < y1 { 1 } else { -1 };
-    let mut err = dx + dy;
-    let mut x = x0;
-    let mut y = y0;
-
-    loop {
-        buffer.push((x, y, z, '#', color));
-
-        if x == x1 && y == y1 { break; }
-
-        let e2 = 2 * err;
-        if e2 >= dy {
-            if x == x1 { break; }
-            err += dy;
-            x += sx;
+// A built-in scene, a single shape, an OBJ model or a scene file, dressed
+// for the scene, with the scene file's properties at time `t`
+fn load_scene(name: &str, dressing: &Dressing, t: f32) -> Result<Loaded, String> {
+    let mut loaded = if is_scene_file(name) {
+        load_scene_file(Path::new(name), t, |object| dress(object, dressing))?
+    } else {
+        let mut scene = match build_scene(name) {
+            Some(scene) => scene,
+            None if is_shape(name) => model_scene(shape_mesh(name)?),
+            None => model_scene(load_obj(Path::new(name))?),
+        };
+        for object in &mut scene.objects {
+            dress(object, dressing);
         }
-        if e2 <= dx {
-            if y == y1 { break; }
-            err += dx;
-            y += sy;
+        let mut loaded = Loaded::plain(scene);
+        // A single shape goes by its name, so timelines can say rotate cube
+        if loaded.scene.objects.len() == 1 {
+            loaded.script.name(name.split(':').next().unwrap_or(name), 0);
         }
-    }
-}
-
-// Simple face filling using scanlines
-#[allow(clippy::too_many_arguments)]
-fn fill_face(vertices: &[[f32; 3]; 4], width: usize, height: usize, fov: f32, distance: f32,
-             buffer: &mut Vec<(i32, i32, f32, char, Color)>, color: Color, shade_char: char) {
-    let mut projected: Vec<(i32, i32, f32)> = Vec::new();
-
-    for v in vertices {
-        if let Some(p) = project(*v, width, height, fov, distance) {
-            projected.push(p);
+        loaded
+    };
+    // Over what the scene file says, for every object
+    for i in 0..loaded.scene.objects.len() {
+        for (property, expr) in &dressing.motion {
+            loaded.script.run(&format!("object{}.{property} = {expr}", i + 1), &mut loaded.scene, t, |_| {})
+                .map_err(|e| format!("{property} {expr}: {e}"))?;
         }
     }
-
-    if projected.len() < 3 { return; }
-
-    // Get bounding box
-    let min_x = projected.iter().map(|p| p.0).min().unwrap_or(0);
-    let max_x = projected.iter().map(|p| p.0).max().unwrap_or(0);
-    let min_y = projected.iter().map(|p| p.1).min().unwrap_or(0);
-    let max_y = projected.iter().map(|p| p.1).max().unwrap_or(0);
-
-    let avg_z: f32 = projected.iter().map(|p| p.2).sum::<f32>() / projected.len() as f32;
-
-    // Simple point-in-polygon for quad
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            if point_in_quad(x, y, &projected) {
-                buffer.push((x, y, avg_z, shade_char, color));
-            }
+    if let Some(timeline) = &dressing.timeline {
+        let compiled = timeline.compile(&loaded.script, &loaded.scene)?;
+        for statement in &compiled.statements {
+            loaded.script.run(statement, &mut loaded.scene, t, |_| {})?;
         }
+        loaded.cuts = compiled.cuts;
     }
+    Ok(loaded)
 }
 
-fn point_in_quad(px: i32, py: i32, vertices: &[(i32, i32, f32)]) -> bool {
-    if vertices.len() < 3 { return false; }
-
-    let mut inside = true;
-    let n = vertices.len();
-
-    for i in 0..n {
-        let j = (i + 1) % n;
-        let edge_x = vertices[j].0 - vertices[i].0;
-        let edge_y = vertices[j].1 - vertices[i].1;
-        let point_x = px - vertices[i].0;
-        let point_y = py - vertices[i].1;
-
-        let cross = edge_x * point_y - edge_y * point_x;
-        if cross < 0 {
-            inside = false;
-            break;
-        }
+// The options of the config file, the profile and the environment, in the
+// order they go on. The profile is `picked` on the command line, or else in
+// the environment
+fn settings_layers(config: &mut Config, picked: Option<&str>) -> Result<Vec<Flags>, String> {
+    let file = config_path().unwrap_or_default().display().to_string();
+    let mut layers = vec![settings(std::mem::take(&mut config.options)).map_err(|e| format!("{file}: {e}"))?];
+    let env = std::mem::take(&mut config.env_options).into_iter()
+        .map(|(var, args)| settings(args).map_err(|e| format!("{var}: {e}")))
+        .collect::<Result<Vec<_>, _>>()?;
+    let picked = picked.map(str::to_string).or_else(|| env.iter().find_map(|flags| flags.profile.clone()));
+    if let Some(name) = picked {
+        let args = config.profile(&name).map_err(|e| format!("{file}: {e}"))?;
+        layers.push(settings(args).map_err(|e| format!("{file}: profile {name}: {e}"))?);
     }
+    layers.extend(env);
+    Ok(layers)
+}
 
-    if inside { return true; }
-
-    // Try other winding
-    inside = true;
-    for i in 0..n {
-        let j = (i + 1) % n;
-        let edge_x = vertices[j].0 - vertices[i].0;
-        let edge_y = vertices[j].1 - vertices[i].1;
-        let point_x = px - vertices[i].0;
-        let point_y = py - vertices[i].1;
-
-        let cross = edge_x * point_y - edge_y * point_x;
-        if cross > 0 {
-            inside = false;
-            break;
+// What --list-shapes prints, every shape and under it its settings with
+// their defaults
+fn shape_list() -> String {
+    let mut list = String::new();
+    for shape in shapes().iter() {
+        list += &format!("{:<10}{}\n", shape.name, shape.about);
+        for param in shape.params {
+            let setting = format!("{}={}", param.name, param.default);
+            list += &format!("  {setting:<14}{}, {} to {}\n", param.about, param.min, param.max);
         }
     }
-
-    inside
+    list
 }
 
-fn get_face_normal(v0: [f32; 3], v1: [f32; 3], v2: [f32; 3]) -> [f32; 3] {
-    let edge1 = [v1[0] - v0[0], v1[1] - v0[1], v1[2] - v0[2]];
-    let edge2 = [v2[0] - v0[0], v2[1] - v0[1], v2[2] - v0[2]];
-
-    [
-        edge1[1] * edge2[2] - edge1[2] * edge2[1],
-        edge1[2] * edge2[0] - edge1[0] * edge2[2],
-        edge1[0] * edge2[1] - edge1[1] * edge2[0],
-    ]
+// What the menu shows `item` is set to, `speed` being the playback's
+fn menu_value(item: Item, options: &Options, colors: ColorSupport, speed: f32) -> String {
+    match item {
+        Item::Shape => options.scene.clone(),
+        Item::Mode => options.render.mode.name().to_string(),
+        Item::Shading => options.render.shading.name().to_string(),
+        Item::Characters => RAMP_PRESETS.iter().find(|(_, chars)| *chars == options.render.ramp.chars)
+            .map_or("custom", |(name, _)| name).to_string(),
+        Item::Colors => colors.name().to_string(),
+        Item::Speed => format!("{speed:.2}x"),
+    }
 }
 
-const SHADE_CHARS: [char; 8] = [' ', '.', ':', '-', '=', '+', '#', '@'];
-
-fn render_cube(width: usize, height: usize, angle_x: f32, angle_y: f32, angle_z: f32) -> String {
-    let mut buffer: Vec<(i32, i32, f32, char, Color)> = Vec::new();
-    let fov = 40.0;
-    let distance = 5.0;
-
-    // Transform vertices
-    let mut transformed: [[f32; 3]; 8] = [[0.0; 3]; 8];
-    for (i, v) in CUBE_VERTICES.iter().enumerate() {
-        let mut p = *v;
-        p = rotate_x(p, angle_x);
-        p = rotate_y(p, angle_y);
-        p = rotate_z(p, angle_z);
-        transformed[i] = p;
+// Move or turn the fly camera if `action` is one that does, by `step`
+// units or `look` radians. Returns whether it was
+fn fly_action(camera: &mut Camera, action: Action, step: f32, look: f32) -> bool {
+    match action {
+        Action::FlyForward => camera.fly(step, 0.0, 0.0),
+        Action::FlyBack => camera.fly(-step, 0.0, 0.0),
+        Action::FlyRight => camera.fly(0.0, step, 0.0),
+        Action::FlyLeft => camera.fly(0.0, -step, 0.0),
+        Action::Rise => camera.fly(0.0, 0.0, step),
+        Action::Sink => camera.fly(0.0, 0.0, -step),
+        Action::LookLeft => camera.look(-look, 0.0),
+        Action::LookRight => camera.look(look, 0.0),
+        Action::LookUp => camera.look(0.0, -look),
+        Action::LookDown => camera.look(0.0, look),
+        _ => return false,
     }
+    true
+}
 
-    // Sort faces by depth and render back-to-front
-    let mut face_depths: Vec<(usize, f32, [f32; 3])> = Vec::new();
-
-    for (i, (indices, _)) in CUBE_FACES.iter().enumerate() {
-        let v0 = transformed[indices[0]];
-        let v1 = transformed[indices[1]];
-        let v2 = transformed[indices[2]];
-
-        // Calculate face center depth
-        let center_z = (v0[2] + v1[2] + transformed[indices[2]][2] + transformed[indices[3]][2]) / 4.0;
-
-        // Calculate normal for backface culling
-        let normal = get_face_normal(v0, v1, v2);
-
-        face_depths.push((i, center_z, normal));
+// A key as its release is reported: without Shift, which may have been let
+// go first
+fn unshifted(code: KeyCode) -> KeyCode {
+    match code {
+        KeyCode::Char(c) => KeyCode::Char(c.to_ascii_lowercase()),
+        code => code,
     }
+}
 
-    // Sort back to front
-    face_depths.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+// What the program does, named by the subcommand
+#[derive(Clone, Copy, PartialEq)]
+enum Subcommand {
+    View,
+    Render,
+    Record,
+    Bench,
+}
 
-    // Render faces
-    for (face_idx, _, normal) in &face_depths {
-        let (indices, color) = &CUBE_FACES[*face_idx];
+// Command line options
+struct Options {
+    command: Subcommand,
+    scene: String,
+    // `None` detects what the terminal supports
+    colors: Option<ColorSupport>,
+    // `None` detects what the terminal supports
+    backend: Option<Backend>,
+    texture: Option<std::path::PathBuf>,
+    timeline: Option<std::path::PathBuf>,
+    // Camera moves to follow, and where to save the view's
+    camera_path: Option<std::path::PathBuf>,
+    save_camera: Option<std::path::PathBuf>,
+    palette: Palette,
+    // Share of the turn rate a thrown object loses per second
+    damping: f32,
+    // Seconds recalling a camera bookmark takes to get there
+    glide: f32,
+    // Width over height of a centered viewport, `None` uses the whole area
+    letterbox: Option<f32>,
+    // Columns and rows of the view whatever the terminal's size, `None`
+    // fills the terminal
+    size: Option<(usize, usize)>,
+    // Height of a terminal cell over its width, `None` measures it
+    aspect: Option<f32>,
+    // Key profile, `None` takes the config file's or the default one
+    keys: Option<String>,
+    // What the screenshot key saves
+    shot: ShotFormat,
+    // Where `render` and `record` save to, and what `record` saves
+    out: Option<std::path::PathBuf>,
+    format: Recording,
+    // How many frames `render` and `record` make, and the animation time of
+    // the first one
+    frames: Option<usize>,
+    time: f32,
+    // Turn rate and starting yaw, pitch and roll of every object, `None`
+    // keeping the scene's own
+    spin: Option<[f32; 3]>,
+    turn: Option<[f32; 3]>,
+    // Axes no object spins around
+    frozen: [bool; 3],
+    // Expressions of the time `t` for the angle around x, y and z, and for
+    // the position and the scale
+    rotate: [Option<String>; 3],
+    position: Option<String>,
+    scale: Option<String>,
+    // Frames per second at most, `None` draws as fast as the terminal keeps up
+    fps: Option<f32>,
+    // Steps per second the animation moves on by in the view
+    sim_rate: f32,
+    // Seconds until quitting, and animation loops, `None` running until
+    // asked to
+    duration: Option<f32>,
+    loops: Option<u32>,
+    // Start with the animation stopped at its beginning
+    paused: bool,
+    // Where the run's statistics are written to when it ends
+    stats_json: Option<StatsTarget>,
+    // Language of the status line, `None` takes the locale's
+    lang: Option<&'static Words>,
+    render: RenderSettings,
+}
 
-        // Backface culling - skip faces pointing away
-        if normal[2] < 0.0 { continue; }
+impl Options {
+    fn new() -> Options {
+        Options {
+            command: Subcommand::View, scene: "cube".to_string(), colors: None, backend: None, texture: None, timeline: None, camera_path: None, save_camera: None, palette: Palette::Classic, damping: DEFAULT_DAMPING, glide: DEFAULT_GLIDE, letterbox: None, size: None, aspect: None, keys: None, shot: ShotFormat::Text, out: None, format: Recording::Cast, frames: None, time: 0.0, spin: None, turn: None, frozen: [false; 3], rotate: [None, None, None], position: None, scale: None, fps: None, sim_rate: DEFAULT_SIM_RATE, duration: None, loops: None, paused: false, stats_json: None, lang: None, render: RenderSettings::default() }
+    }
 
-        // Calculate shading based on normal
-        let light_dir = [0.0, 0.0, 1.0];
-        let dot = normal[0] * light_dir[0] + normal[1] * light_dir[1] + normal[2] * light_dir[2];
-        let len = (normal[0] * normal[0] + normal[1] * normal[1] + normal[2] * normal[2]).sqrt();
-        let intensity = if len > 0.0 { (dot / len).max(0.0) } else { 0.0 };
+    // The properties --rotate-*, --position and --scale bind. An angle
+    // given stands for the orientation and the spin around that axis
+    fn motion(&mut self) -> Vec<(&'static str, String)> {
+        let mut motion = Vec::new();
+        if self.rotate.iter().any(Option::is_some) {
+            let turn = self.turn.unwrap_or([0.0; 3]);
+            // Turns are yaw, pitch and roll: around y, x and then z
+            let angle = |axis: usize, i: usize| self.rotate[axis].clone().unwrap_or(turn[i].to_string());
+            motion.push(("turn", format!("({}, {}, {})", angle(1, 0), angle(0, 1), angle(2, 2))));
+            for (frozen, rotate) in self.frozen.iter_mut().zip(&self.rotate) {
+                *frozen |= rotate.is_some();
+            }
+        }
+        if let Some(position) = &self.position { motion.push(("position", position.clone())); }
+        if let Some(scale) = &self.scale { motion.push(("scale", scale.clone())); }
+        motion
+    }
 
-        let shade_idx = ((intensity * (SHADE_CHARS.len() - 1) as f32) as usize).min(SHADE_CHARS.len() - 1);
-        let shade_char = SHADE_CHARS[shade_idx];
+    // Take the options in `flags` on top of what is already set
+    fn apply(&mut self, flags: Flags) {
+        let options = self;
+        if let Some(scene) = flags.scene.or(flags.shape) { options.scene = scene; }
+        if let Some(spin) = flags.speed { options.spin = Some(spin); }
+        if let Some(degrees) = flags.orientation { options.turn = Some(degrees.map(f32::to_radians)); }
+        if let Some(frozen) = flags.freeze { options.frozen = frozen; }
+        for (rotate, angle) in options.rotate.iter_mut().zip([flags.rotate_x, flags.rotate_y, flags.rotate_z]) {
+            if angle.is_some() { *rotate = angle; }
+        }
+        if flags.position.is_some() { options.position = flags.position; }
+        if flags.scale.is_some() { options.scale = flags.scale; }
+        if flags.texture.is_some() { options.texture = flags.texture; }
+        if flags.timeline.is_some() { options.timeline = flags.timeline; }
+        if flags.save_camera.is_some() { options.save_camera = flags.save_camera; }
+        if flags.camera_path.is_some() { options.camera_path = flags.camera_path; }
+        if let Some(palette) = flags.palette.or(flags.colorblind) { options.palette = palette; }
 
-        let face_verts = [
-            transformed[indices[0]],
-            transformed[indices[1]],
-            transformed[indices[2]],
-            transformed[indices[3]],
-        ];
+        let render = &mut options.render;
+        if let Some(mode) = flags.mode { render.mode = mode; }
+        if let Some(shading) = flags.shading { render.shading = shading; }
+        // A ramp drawn a character per cell, or the sub-cell glyphs
+        match flags.charset {
+            Some(Charset::Braille) => render.subcell = SubCell::Braille,
+            Some(Charset::Ramp(chars)) => (render.ramp.chars, render.subcell) = (chars, SubCell::Off),
+            None => {}
+        }
+        if let Some(chars) = flags.ramp { render.ramp.chars = chars; }
+        if let Some(dither) = flags.dither { render.ramp.dither = dither; }
+        if let Some(Maybe(colors)) = flags.color { options.colors = colors; }
+        if let Some(solid) = flags.fill { render.solid_fill = solid; }
+        if let Some(Maybe(subcell)) = flags.subcell { render.subcell = subcell.unwrap_or_else(SubCell::detect); }
+        if let Some(Maybe(backend)) = flags.output { options.backend = backend; }
+        if let Some(shadows) = flags.shadows { render.shadows = shadows; }
+        if let Some(hidden_lines) = flags.hidden_lines { render.hidden_lines = hidden_lines; }
+        if let Some(fog) = flags.fog { render.fog = fog; }
+        if let Some(gamma) = flags.gamma { render.tone.gamma = gamma; }
+        if let Some(reinhard) = flags.tonemap { render.tone.reinhard = reinhard; }
+        if let Some(antialias) = flags.antialias { render.antialias = antialias; }
+        if let Some(bloom) = flags.bloom { render.bloom = bloom; }
+        if let Some(depth) = flags.depth { render.depth_view = depth; }
+        if let Some(crt) = flags.crt { render.crt = crt; }
+        if let Some(decay) = flags.motion_blur { render.motion_blur = decay; }
+        if let Some(factor) = flags.supersample { render.supersample = factor; }
+        if let Some(seed) = flags.seed { render.seed = seed; }
+        if flags.letterbox.is_some() { options.letterbox = flags.letterbox; }
+        if flags.size.is_some() { options.size = flags.size; }
+        if flags.aspect.is_some() { options.aspect = flags.aspect; }
 
-        fill_face(&face_verts, width, height, fov, distance, &mut buffer, *color, shade_char);
+        if let Some(Maybe(fps)) = flags.fps { options.fps = fps; }
+        if let Some(rate) = flags.sim_rate { options.sim_rate = rate; }
+        if let Some(paused) = flags.paused { options.paused = paused; }
+        if flags.stats_json.is_some() { options.stats_json = flags.stats_json; }
+        if flags.duration.is_some() { options.duration = flags.duration; }
+        if flags.loops.is_some() { options.loops = flags.loops; }
+        if flags.keys.is_some() { options.keys = flags.keys; }
+        if flags.lang.is_some() { options.lang = flags.lang; }
+        if let Some(damping) = flags.damping { options.damping = damping; }
+        if let Some(glide) = flags.glide { options.glide = glide; }
+        if let Some(shot) = flags.screenshot { options.shot = shot; }
+        if flags.frames.is_some() { options.frames = flags.frames; }
+        if let Some(time) = flags.time { options.time = time; }
+        if flags.out.is_some() { options.out = flags.out; }
+        if let Some(format) = flags.format { options.format = format; }
     }
+}
 
-    // Draw edges on top
-    for (i, j) in CUBE_EDGES.iter() {
-        if let (Some(p1), Some(p2)) = (
-            project(transformed[*i], width, height, fov, distance),
-            project(transformed[*j], width, height, fov, distance),
-        ) {
-            let avg_z = (p1.2 + p2.2) / 2.0;
-            draw_line(p1.0, p1.1, p2.0, p2.1, &mut buffer, avg_z - 0.1, Color::White);
+fn main() -> std::io::Result<()> {
+    let mut config = match Config::load() {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("teruminator: {e}");
+            std::process::exit(2);
         }
+    };
+    // Before the options, which may name them
+    if let Err(e) = shapes_dir().map_or(Ok(()), |dir| register_shape_files(&dir)) {
+        eprintln!("teruminator: {e}");
+        std::process::exit(2);
     }
+    // Clap prints what is wrong with the command line, and the help
+    let cli = Cli::parse();
+    let explicit_view = matches!(cli.command, Some(cli::Command::View(_)));
+    let (command, run) = match cli.command {
+        None => (Subcommand::View, cli.run),
+        Some(cli::Command::View(run)) => (Subcommand::View, run),
+        Some(cli::Command::Render(run)) => (Subcommand::Render, run),
+        Some(cli::Command::Record(run)) => (Subcommand::Record, run),
+        Some(cli::Command::Bench(run)) => (Subcommand::Bench, run),
+        Some(cli::Command::Play { file }) => {
+            if !file.is_file() {
+                eprintln!("teruminator: play needs a file made with record --format ansi");
+                std::process::exit(2);
+            }
+            let played = install_signal_handler().and_then(|_| replay::play(&file));
+            if let Err(e) = played {
+                eprintln!("teruminator: {e}");
+                std::process::exit(1);
+            }
+            return Ok(());
+        }
+    };
+    if run.list_shapes {
+        print!("{}", shape_list());
+        std::process::exit(0);
+    }
+    // The config file's options first, then the profile's, then the
+    // environment's, so the command line overrides them
+    let mut options = Options::new();
+    match settings_layers(&mut config, run.flags.profile.as_deref()) {
+        Ok(layers) => layers.into_iter().for_each(|flags| options.apply(flags)),
+        Err(e) => {
+            eprintln!("teruminator: {e}");
+            std::process::exit(2);
+        }
+    }
+    options.command = command;
+    options.apply(run.flags);
+    if let Some(file) = run.file { options.scene = file; }
+    // Piped to a file or another program, where the view's cursor moves would
+    // be garbage, the frames are printed the way render prints them
+    if options.command == Subcommand::View && !explicit_view && !stdout().is_terminal() {
+        options.command = Subcommand::Render;
+    }
+    let profile = options.keys.as_deref().or(config.key_profile.as_deref()).unwrap_or("default");
+    let mut keymap = Keymap::profile(profile).expect("key profile is validated");
+    for (action, keys) in config.bindings {
+        keymap.bind(action, keys);
+    }
+    let mut colors = options.colors.unwrap_or_else(ColorSupport::detect);
+    let backend = options.backend.unwrap_or_else(Backend::detect);
+    options.render.truecolor = colors.rgb();
+    // Blocks of background color need colors to show anything, what was
+    // asked for is kept for when the colors change
+    let solid_fill = options.render.solid_fill;
+    options.render.solid_fill &= colors != ColorSupport::Monochrome;
 
-    // Create z-buffer for proper depth
-    let mut z_buffer: Vec<f32> = vec![f32::MAX; width * height];
-    let mut char_buffer: Vec<(char, Color)> = vec![(' ', Color::Black); width * height];
-
-    for (x, y, z, c, color) in buffer {
-        if x >= 0 && x < width as i32 && y >= 0 && y < height as i32 {
-            let idx = y as usize * width + x as usize;
-            if z < z_buffer[idx] {
-                z_buffer[idx] = z;
-                char_buffer[idx] = (c, color);
+    let mut last_frame = std::time::Instant::now();
+    let mut hud = Hud::new();
+    hud.words = options.lang.unwrap_or_else(locale_language);
+    let mut playback = Playback::new();
+    playback.paused = options.paused;
+    let mut simulation = FixedStep::new(options.sim_rate);
+    // How far the last step moved the animation clock and threw the
+    // objects, and how much of that throw isn't shown yet
+    let mut last_step = (0.0, (0.0, 0.0));
+    let mut throw_lag = (0.0, 0.0);
+    let mut orbit = Orbit::new(options.damping);
+    let mut fly_speed = 1.0f32;
+    let mut bookmarks = Bookmarks::new(options.glide);
+    // Where the left button went down, forgotten once it drags elsewhere,
+    // and a finished click waiting for the next frame's view to be picked
+    let mut click: Option<(u16, u16)> = None;
+    let mut pick_at: Option<(u16, u16)> = None;
+    let mut selected: Option<Pick> = None;
+    // Last cell the pointer was seen over
+    let mut pointer: Option<(u16, u16)> = None;
+    // Whether the key light follows the pointer
+    let mut place_light = false;
+    let mut gizmo = Gizmo::new();
+    let mut history = History::new();
+    let mut menu = Menu::new();
+    // Command lines waiting to run, typed after `:` or changed in the menu
+    let mut pending: Vec<String> = Vec::new();
+    #[cfg(feature = "gamepad")]
+    let mut gamepad = gamepad::Gamepad::new();
+    #[cfg(feature = "midi")]
+    let mut midi = midi::Midi::connect();
+    // The previous frame's view and where it sits on the screen, for
+    // finding the handles a press lands on
+    let mut last_view: Option<(Projection, (usize, usize))> = None;
+    let mut previous_frame = None;
+    let mut screen = Screen::new(colors);
+    let texture = options.texture.as_ref().map(|path| match Texture::load(path) {
+        Ok(texture) => Arc::new(texture),
+        Err(e) => {
+            eprintln!("teruminator: {e}");
+            std::process::exit(1);
+        }
+    });
+    let motion = options.motion();
+    let timeline = options.timeline.as_ref().map(|path| Timeline::load(path).unwrap_or_else(|e| {
+        eprintln!("teruminator: {e}");
+        std::process::exit(1);
+    }));
+    let dressing = Dressing { palette: options.palette, texture, spin: options.spin, turn: options.turn, frozen: options.frozen, motion, timeline };
+    let camera_path = options.camera_path.as_ref().map(|path| CameraPath::load(path).unwrap_or_else(|e| {
+        eprintln!("teruminator: {e}");
+        std::process::exit(1);
+    }));
+    let loaded = match load_scene(&options.scene, &dressing, 0.0) {
+        Ok(loaded) => loaded,
+        Err(e) => {
+            eprintln!("teruminator: {e}");
+            std::process::exit(1);
+        }
+    };
+    let mut scene = loaded.scene;
+    // Objects spawned and properties bound on the command line and in the
+    // scene file
+    let mut script = loaded.script;
+    // The free camera while flying, `None` orbits the scene
+    let mut fly = loaded.fly;
+    // The timeline's camera cuts, and the animation time of the last frame
+    // so each is made once when the time gets to it
+    let mut cuts = loaded.cuts;
+    let mut cut_time = f32::NEG_INFINITY;
+    // The scene file's camera move along a curve, over the animation time
+    let mut camera_spline = loaded.spline;
+    // The scene file shown, loaded again whenever it is saved
+    let mut watch = is_scene_file(&options.scene).then(|| Watch::new(&loaded.files));
+    options.render.background = options.palette.background();
+    if options.command != Subcommand::View {
+        // The terminal's size if there is one, with a row for the prompt after
+        let (cols, rows) = options.size
+            .or(size().ok().map(|(c, r)| (c as usize, r.saturating_sub(1) as usize)))
+            .unwrap_or((80, 24));
+        let proj = Projection::new(cols, rows, options.aspect.unwrap_or(DEFAULT_ASPECT));
+        let distance = loaded.distance.unwrap_or(DISTANCE);
+        let camera = Pose { fly, distance }.camera();
+        let cuts = cuts.iter().map(|(time, view)| (*time, view.camera(distance))).collect();
+        let mut batch = Batch { scene, script, settings: &options.render, proj, colors, camera, cuts, spline: camera_spline, path: camera_path, stats: RunStats::new() };
+        let done = match options.command {
+            Subcommand::Render => {
+                let step = 1.0 / options.fps.unwrap_or(30.0);
+                match &options.out {
+                    Some(path) => batch.render_files(&path.to_string_lossy(), options.time, step, options.frames.unwrap_or(1))
+                        .map(|done| eprintln!("teruminator: {done}")),
+                    // Whatever reads the frames may stop early, like head
+                    None => {
+                        let mut out = Counted::new(stdout().lock());
+                        let printed = batch.render(&mut out, options.time, step, options.frames.unwrap_or(1));
+                        batch.stats.bytes_written(out.bytes);
+                        match printed {
+                            Err(e) if e.kind() != std::io::ErrorKind::BrokenPipe => Err(e.to_string()),
+                            _ => Ok(()),
+                        }
+                    }
+                }
+            }
+            Subcommand::Record => {
+                let path = options.out.clone().unwrap_or_else(|| options.format.default_path().into());
+                let looped = options.loops.zip(batch.scene.loop_period()).map(|(loops, period)| loops as f32 * period);
+                let duration = options.duration.or(looped).unwrap_or(5.0);
+                let fps = options.fps.unwrap_or(30.0);
+                let frames = options.frames.unwrap_or((duration * fps).round().max(1.0) as usize);
+                batch.record(&path, options.format, options.time, fps, frames).map(|done| eprintln!("teruminator: {done}"))
             }
+            _ => {
+                println!("{}", batch.bench());
+                Ok(())
+            }
+        };
+        let statted = done.and_then(|_| options.stats_json.as_ref().map_or(Ok(()), |target| target.write(&batch.stats)));
+        if let Err(e) = statted {
+            eprintln!("teruminator: {e}");
+            std::process::exit(1);
         }
+        return Ok(());
+    }
+    let mut min_distance = scene.radius() + NEAR_MARGIN;
+    let mut distance = loaded.distance.unwrap_or(DISTANCE).max(min_distance);
+    // Frames per second at most, set with --fps and :fps
+    let mut frame_cap = options.fps;
+    // Rows the HUD took last frame, the screen is cleared when that changes
+    let mut hud_rows = hud.rows();
+    let mut camera_recorder = options.save_camera.as_ref().map(|path| CameraRecorder::create(path).unwrap_or_else(|e| {
+        eprintln!("teruminator: {e}");
+        std::process::exit(1);
+    }));
+
+    if let Err(e) = install_signal_handler() {
+        eprintln!("teruminator: {e}");
+        std::process::exit(1);
     }
+    // Raw mode so single key presses arrive without waiting for Enter
+    let _terminal = TerminalGuard::new()?;
+    // Everything for a frame is queued here and written with one flush
+    let mut out = BufWriter::with_capacity(1 << 16, Counted::new(stdout().lock()));
+    let mut run_stats = RunStats::new();
+    // Measured once, fonts don't change size mid-run often enough to matter
+    let cell = cell_pixel_size();
+    let aspect = options.aspect
+        .or(cell.map(|(w, h)| h as f32 / w as f32))
+        .unwrap_or(DEFAULT_ASPECT);
+    let key_releases = enable_key_releases();
+    // Fly keys held down, moving the camera every frame until let go. Only
+    // when the terminal says when that is, key repeat moves it otherwise
+    let mut held: Vec<(KeyCode, Action)> = Vec::new();
 
-    // Build output string with colors
-    let mut output = String::with_capacity(width * height * 20);
-    let mut current_color: Option<Color> = None;
+    let started = std::time::Instant::now();
 
-    for y in 0..height {
-        for x in 0..width {
-            let (c, color) = char_buffer[y * width + x];
+    'frames: loop {
+        if quit_requested() { break; }
+        if options.duration.is_some_and(|seconds| started.elapsed().as_secs_f32() >= seconds) { break; }
+        // Counted in animation time, so pausing holds the count
+        if let (Some(loops), Some(period)) = (options.loops, scene.loop_period())
+            && playback.time.abs() >= loops as f32 * period { break; }
 
-            if c != ' ' {
-                if current_color != Some(color) {
-                    output.push_str(&format!("{}", SetForegroundColor(color)));
-                    current_color = Some(color);
+        // Keys do what the key map binds them to, shifted digits save the
+        // camera to a bookmark and digits go back to it, Ctrl+C quits.
+        // Dragging with the left mouse button turns the objects and letting
+        // go throws them, the wheel zooms, clicking selects the face under
+        // the pointer, dragging a handle edits the selected object and the
+        // face under the pointer lights up
+        while poll(Duration::ZERO)? {
+            let key = match read()? {
+                Event::Key(key) => key,
+                // The terminal may have reflowed or kept stale parts of the
+                // old frame, so everything is drawn again at the new size
+                Event::Resize(..) => {
+                    queue!(out, Clear(ClearType::All))?;
+                    screen.invalidate();
+                    previous_frame = None;
+                    continue;
+                }
+                Event::Mouse(mouse) => {
+                    pointer = Some((mouse.column, mouse.row));
+                    match mouse.kind {
+                        MouseEventKind::Down(MouseButton::Left) => {
+                            let at = (mouse.column, mouse.row);
+                            // A press on a handle edits the selected object instead
+                            if let (Some(pick), Some((proj, origin))) = (selected, &last_view) {
+                                let cell = (at.0 as i32 - origin.0 as i32, at.1 as i32 - origin.1 as i32);
+                                if gizmo.grab(&scene.objects[pick.object], proj, cell, at) {
+                                    history.record(Snapshot::take(&scene, &script));
+                                    continue;
+                                }
+                            }
+                            orbit.grab(at);
+                            click = Some(at);
+                        }
+                        MouseEventKind::Drag(MouseButton::Left) if gizmo.holding() => {
+                            if let Some(pick) = selected {
+                                gizmo.drag(&mut scene.objects[pick.object], (mouse.column, mouse.row));
+                            }
+                        }
+                        MouseEventKind::Up(MouseButton::Left) if gizmo.holding() => gizmo.release(),
+                        MouseEventKind::Drag(MouseButton::Left) => {
+                            click = click.filter(|at| *at == (mouse.column, mouse.row));
+                            let (yaw, pitch) = orbit.drag((mouse.column, mouse.row), aspect);
+                            match &mut fly {
+                                Some(camera) => camera.look(yaw / DRAG_STEP * LOOK_STEP, pitch / DRAG_STEP * LOOK_STEP),
+                                None => {
+                                    playback.paused = true;
+                                    scene.turn(yaw, pitch);
+                                }
+                            }
+                        }
+                        MouseEventKind::Up(MouseButton::Left) => {
+                            // Looking around is never thrown
+                            if fly.is_some() { orbit.stop(); } else { orbit.release(); }
+                            pick_at = click.take();
+                        }
+                        MouseEventKind::ScrollUp if fly.is_some() => fly_speed = (fly_speed * FLY_SPEED_STEP).min(64.0),
+                        MouseEventKind::ScrollDown if fly.is_some() => fly_speed = (fly_speed / FLY_SPEED_STEP).max(1.0 / 64.0),
+                        MouseEventKind::ScrollUp => zoom(&mut distance, min_distance, -1.0),
+                        MouseEventKind::ScrollDown => zoom(&mut distance, min_distance, 1.0),
+                        _ => {}
+                    }
+                    continue;
+                }
+                _ => continue,
+            };
+            if key.kind == KeyEventKind::Release {
+                held.retain(|(code, _)| *code != unshifted(key.code));
+                continue;
+            }
+            hud.message = None;
+            if let Some(line) = &mut hud.command {
+                match key.code {
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => break 'frames,
+                    KeyCode::Esc => hud.command = None,
+                    // Deleting past the start leaves the command line
+                    KeyCode::Backspace if line.is_empty() => hud.command = None,
+                    KeyCode::Backspace => { line.pop(); }
+                    KeyCode::Char(c) => line.push(c),
+                    KeyCode::Enter => pending.push(hud.command.take().unwrap_or_default()),
+                    _ => {}
+                }
+                continue;
+            }
+            if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) { break 'frames; }
+            if menu.open {
+                match key.code {
+                    KeyCode::Up => menu.select(-1),
+                    KeyCode::Down => menu.select(1),
+                    KeyCode::Left | KeyCode::Right => {
+                        let step = if key.code == KeyCode::Left { -1 } else { 1 };
+                        pending.push(menu.change(step, |item| menu_value(item, &options, colors, playback.speed)));
+                    }
+                    KeyCode::Esc | KeyCode::Enter => {
+                        menu.open = false;
+                        queue!(out, Clear(ClearType::All))?;
+                        screen.invalidate();
+                    }
+                    _ => {}
+                }
+                continue;
+            }
+            if let KeyCode::Char(c) = key.code
+                && let Some((slot, save)) = slot_key(c, key.modifiers.contains(KeyModifiers::SHIFT)) {
+                let pose = Pose { fly, distance };
+                if save {
+                    bookmarks.save(slot, pose);
+                } else if let Some(saved) = bookmarks.recall(slot, pose.camera()) {
+                    (fly, distance) = (saved.fly, saved.distance);
+                }
+                continue;
+            }
+            let Some(action) = keymap.action(&key, fly.is_some()) else { continue; };
+            if let Some(camera) = &mut fly {
+                if key_releases && action.flying() {
+                    let press = (unshifted(key.code), action);
+                    if !held.contains(&press) { held.push(press); }
+                    continue;
+                }
+                if fly_action(camera, action, FLY_STEP * fly_speed, LOOK_KEY_STEP) { continue; }
+            }
+            match action {
+                Action::Quit => break 'frames,
+                Action::NextMode => options.render.mode = options.render.mode.next(),
+                Action::Culling => {
+                    options.render.cull = !options.render.cull;
+                    hud.message = Some(format!("backface culling {}", if options.render.cull { "on" } else { "off" }));
+                }
+                Action::Edges => {
+                    options.render.edges = !options.render.edges;
+                    hud.message = Some(format!("edges {}", if options.render.edges { "on" } else { "off" }));
                 }
-                output.push(c);
-            } else {
-                if current_color.is_some() {
-                    output.push_str(&format!("{}", ResetColor));
-                    current_color = None;
+                Action::PlaceLight => {
+                    place_light = !place_light;
+                    hud.message = Some(if place_light { "the key light follows the pointer" } else { "the key light stays" }.to_string());
                 }
-                output.push(' ');
+                Action::NextShading => {
+                    options.render.shading = options.render.shading.next();
+                    hud.message = Some(format!("{} shading", options.render.shading.name()));
+                }
+                // Flying starts from where the orbit camera is
+                Action::Fly => fly = if fly.is_some() { None } else { Some(Camera::orbit(distance)) },
+                Action::Pause => playback.paused = !playback.paused,
+                Action::Step => playback.step(),
+                Action::ScrubBack => playback.scrub(-1.0),
+                Action::ScrubForward => playback.scrub(1.0),
+                Action::Faster => playback.faster(),
+                Action::Slower => playback.slower(),
+                Action::Reverse => playback.reverse(),
+                Action::ZoomIn if fly.is_some() => fly_speed = (fly_speed * FLY_SPEED_STEP).min(64.0),
+                Action::ZoomOut if fly.is_some() => fly_speed = (fly_speed / FLY_SPEED_STEP).max(1.0 / 64.0),
+                Action::ZoomIn => zoom(&mut distance, min_distance, -1.0),
+                Action::ZoomOut => zoom(&mut distance, min_distance, 1.0),
+                Action::TurnLeft | Action::TurnRight | Action::TurnUp | Action::TurnDown => {
+                    let (yaw, pitch) = match action {
+                        Action::TurnLeft => (-TURN_STEP, 0.0),
+                        Action::TurnRight => (TURN_STEP, 0.0),
+                        Action::TurnUp => (0.0, -TURN_STEP),
+                        _ => (0.0, TURN_STEP),
+                    };
+                    playback.paused = true;
+                    scene.turn(yaw, pitch);
+                }
+                Action::NextTool => gizmo.tool = gizmo.tool.next(),
+                Action::Nudge { axis, back } => {
+                    if let Some(pick) = selected {
+                        history.record(Snapshot::take(&scene, &script));
+                        gizmo.nudge(&mut scene.objects[pick.object], axis, if back { -1.0 } else { 1.0 });
+                    }
+                }
+                Action::StatusLine => hud.visible = !hud.visible,
+                Action::Command => hud.command = Some(String::new()),
+                // The view of the frame on screen, without the letterbox
+                Action::Screenshot => if let Some((proj, _)) = &last_view {
+                    let shot = screenshot(&scene, proj, playback.time, &options.render, options.shot,
+                        cell.unwrap_or(DEFAULT_CELL_PIXELS), colors);
+                    hud.message = Some(shot.unwrap_or_else(|e| e));
+                },
+                Action::Menu => {
+                    (menu.open, hud.help) = (true, false);
+                    queue!(out, Clear(ClearType::All))?;
+                    screen.invalidate();
+                }
+                Action::Undo | Action::Redo => {
+                    let undo = action == Action::Undo;
+                    let done = if undo { history.undo(&mut scene, &mut script) } else { history.redo(&mut scene, &mut script) };
+                    // Spawned objects may have come or gone
+                    min_distance = scene.radius() + NEAR_MARGIN;
+                    selected = selected.filter(|pick| pick.object < scene.objects.len());
+                    gizmo.release();
+                    hud.message = Some(match (undo, done) {
+                        (true, true) => "undone",
+                        (true, false) => "nothing to undo",
+                        (false, true) => "redone",
+                        (false, false) => "nothing to redo",
+                    }.to_string());
+                }
+                Action::Help => {
+                    hud.help = !hud.help;
+                    // The panel is text, images don't paint over it
+                    queue!(out, Clear(ClearType::All))?;
+                    screen.invalidate();
+                }
+                _ => {}
             }
         }
-    }
 
-    if current_color.is_some() {
-        output.push_str(&format!("{}", ResetColor));
-    }
+        // Lines from the command line and the menu, once the input so far
+        // is read
+        for line in std::mem::take(&mut pending) {
+            // Statements run in turn until one fails
+            for statement in line.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                if Script::handles(statement) {
+                    let before = Snapshot::take(&scene, &script);
+                    let answer = script.run(statement, &mut scene, playback.time, |object| dress(object, &dressing));
+                    // Spawning or moving may have grown the scene
+                    min_distance = scene.radius() + NEAR_MARGIN;
+                    let failed = answer.is_err();
+                    if !failed { history.record(before); }
+                    hud.message = Some(answer.unwrap_or_else(|e| e));
+                    if failed { break; }
+                    continue;
+                }
+                match Command::parse(statement) {
+                    Ok(Command::Load(name)) => match load_scene(&name, &dressing, playback.time) {
+                        Ok(loaded) => {
+                            (scene, script, cuts, camera_spline) = (loaded.scene, loaded.script, loaded.cuts, loaded.spline);
+                            history.clear();
+                            min_distance = scene.radius() + NEAR_MARGIN;
+                            distance = loaded.distance.unwrap_or(distance);
+                            zoom(&mut distance, min_distance, 0.0);
+                            if loaded.fly.is_some() { fly = loaded.fly; }
+                            (selected, pick_at) = (None, None);
+                            gizmo.release();
+                            hud.message = Some(format!("loaded {name}"));
+                            watch = is_scene_file(&name).then(|| Watch::new(&loaded.files));
+                            options.scene = name;
+                        }
+                        Err(e) => {
+                            hud.message = Some(e);
+                            break;
+                        }
+                    },
+                    Ok(Command::Mode(mode)) => options.render.mode = mode,
+                    Ok(Command::Shading(shading)) => options.render.shading = shading,
+                    Ok(Command::Ramp(chars)) => options.render.ramp.chars = chars,
+                    Ok(Command::Colors(new)) => {
+                        colors = new;
+                        options.render.truecolor = colors.rgb();
+                        options.render.solid_fill = solid_fill && colors != ColorSupport::Monochrome;
+                        screen.set_colors(colors);
+                    }
+                    Ok(Command::Fps(fps)) => frame_cap = fps,
+                    Ok(Command::Speed(speed)) => playback.speed = speed,
+                    Ok(Command::Background(background)) => options.render.background = background,
+                    Ok(Command::Quit) => break 'frames,
+                    Err(e) => {
+                        hud.message = Some(e);
+                        break;
+                    }
+                }
+            }
+        }
 
-    output
-}
+        // The camera and the time stay, so the edit shows where it was looked at
+        if watch.as_mut().is_some_and(Watch::changed) {
+            match load_scene(&options.scene, &dressing, playback.time) {
+                Ok(loaded) => {
+                    (scene, script, cuts, camera_spline) = (loaded.scene, loaded.script, loaded.cuts, loaded.spline);
+                    history.clear();
+                    min_distance = scene.radius() + NEAR_MARGIN;
+                    zoom(&mut distance, min_distance, 0.0);
+                    (selected, pick_at) = (None, None);
+                    gizmo.release();
+                    // The files it names may have changed too
+                    watch = Some(Watch::new(&loaded.files));
+                    hud.message = Some(format!("reloaded {}", options.scene));
+                }
+                // The last good version stays up until the file is fixed
+                Err(e) => hud.message = Some(e),
+            }
+        }
 
-fn main() -> std::io::Result<()> {
-    let mut last_render_time = std::time::Instant::now();
-    let start_time = std::time::Instant::now();
+        let frame_start = std::time::Instant::now();
+        hud.tick(frame_start - last_frame);
+        let dt = (frame_start - last_frame).as_secs_f32();
+        // The animation and throws move on in fixed steps, input and the
+        // camera follow the real time between frames
+        let mut thrown = (0.0, 0.0);
+        for _ in 0..simulation.steps(dt) {
+            let before = playback.time;
+            playback.advance(simulation.step());
+            last_step.0 = playback.time - before;
+            last_step.1 = orbit.step(simulation.step());
+            thrown = (thrown.0 + last_step.1.0, thrown.1 + last_step.1.1);
+        }
+        // Drawn between the last two steps, as far along as the time left
+        // over says, so motion stays smooth when frames come faster than
+        // steps or between them
+        let behind = 1.0 - simulation.fraction();
+        let time = playback.time - behind * last_step.0;
+        let lag = (behind * last_step.1.0, behind * last_step.1.1);
+        thrown = (thrown.0 + throw_lag.0 - lag.0, thrown.1 + throw_lag.1 - lag.1);
+        throw_lag = lag;
+        script.update(&mut scene, time);
+        // Played back past a cut: the camera jumps there, flying from then on
+        if let Some((_, view)) = cuts.iter().rev().find(|(at, _)| cut_time < *at && *at <= time) {
+            fly = Some(view.camera(distance));
+        }
+        cut_time = time;
+        // Sticks move things as far as they are pushed for as long as they
+        // are held, buttons are taken like keys
+        #[cfg(feature = "gamepad")]
+        if let Some(pad) = &mut gamepad {
+            let (actions, sticks) = pad.poll();
+            for action in actions {
+                match action {
+                    Action::Quit => break 'frames,
+                    Action::Pause => playback.paused = !playback.paused,
+                    Action::NextMode => options.render.mode = options.render.mode.next(),
+                    _ => {}
+                }
+            }
+            match &mut fly {
+                Some(camera) => {
+                    let step = gamepad::FLY_RATE * fly_speed * dt;
+                    camera.fly(sticks.left.1 * step, sticks.left.0 * step, 0.0);
+                    let look = gamepad::LOOK_RATE * dt;
+                    camera.look(sticks.right.0 * look, -sticks.right.1 * look);
+                }
+                None => {
+                    if sticks.left != (0.0, 0.0) {
+                        playback.paused = true;
+                        let turn = gamepad::TURN_RATE * dt;
+                        scene.turn(sticks.left.0 * turn, -sticks.left.1 * turn);
+                    }
+                    zoom(&mut distance, min_distance, -sticks.right.1 * gamepad::ZOOM_RATE * dt);
+                }
+            }
+        }
+        #[cfg(feature = "midi")]
+        if let Some(midi) = &mut midi {
+            midi.perform(&mut scene, &mut playback);
+        }
+        match &mut fly {
+            Some(camera) => for (_, action) in &held {
+                fly_action(camera, *action, HELD_FLY_RATE * fly_speed * dt, HELD_LOOK_RATE * dt);
+            },
+            None => held.clear(),
+        }
+        scene.turn(thrown.0, thrown.1);
+        last_frame = frame_start;
+        // Images leave the HUD's last row behind otherwise
+        if hud.rows() != hud_rows {
+            hud_rows = hud.rows();
+            queue!(out, Clear(ClearType::All))?;
+            screen.invalidate();
+        }
+        let (cols, lines) = usable_space(hud_rows)?;
+        let area = options.size.unwrap_or((cols as usize, lines as usize));
+        let (mut proj, mut origin) = match options.letterbox {
+            // Boxed inside what the terminal shows of the area
+            Some(ratio) => Projection::letterboxed(area.0.min(cols as usize), area.1.min(lines as usize), aspect, ratio),
+            None => (Projection::new(area.0, area.1, aspect), (0, 0)),
+        };
+        origin.0 += (cols as usize).saturating_sub(area.0) / 2;
+        origin.1 += (lines as usize).saturating_sub(area.1) / 2;
+        proj.camera = bookmarks.view(Pose { fly, distance }.camera(), dt);
+        // On the animation clock like the objects, so pausing, speeding up
+        // and stepping keep the camera with them
+        if let Some(aim) = &scene.camera { proj.camera = aim.camera(); }
+        if let Some(spline) = &camera_spline { proj.camera = spline.at(time); }
+        if let Some(path) = &camera_path { proj.camera = path.at(time); }
+        if let Some(recorder) = &mut camera_recorder { recorder.record(time, &proj.camera)?; }
+        #[cfg(feature = "midi")]
+        if let Some(midi) = &midi {
+            proj.fov *= midi.lens;
+        }
+        last_view = Some((proj, origin));
+        if place_light
+            && let (Some((x, y)), Some(key)) = (pointer, scene.lights.first_mut()) {
+            key.dir = proj.hemisphere_dir(x as f32 - origin.0 as f32 + 0.5, y as f32 - origin.1 as f32 + 0.5);
+        }
+        let mut stats = FrameStats {
+            frame_time: Duration::ZERO,
+            triangles: scene.triangle_count(),
+            width: proj.width,
+            height: proj.height,
+            mode: options.render.mode,
+            flying: fly.is_some(),
+            speed: playback.speed,
+            paused: playback.paused,
+            time: playback.time,
+            selected: None,
+            tool: gizmo.tool,
+        };
 
-    loop {
-        let (cols, lines) = usable_space()?;
+        // Rotation is driven by the animation clock
+        let elapsed = time;
+        // Screen cells are relative to the letterbox and may be outside it
+        let pick_screen = |(x, y): (u16, u16)| {
+            let (x, y) = (x as i32 - origin.0 as i32, y as i32 - origin.1 as i32);
+            let inside = (0..proj.width as i32).contains(&x) && (0..proj.height as i32).contains(&y);
+            if inside { pick_cell(&scene, &proj, elapsed, x, y) } else { None }
+        };
+        if let Some(at) = pick_at.take() {
+            selected = pick_screen(at);
+        }
+        // Looked up every frame, the face under a still pointer changes as
+        // the objects turn
+        options.render.hover = pointer.and_then(pick_screen);
+        options.render.highlight = selected;
+        options.render.gizmo = selected.map(|pick| pick.object);
+        stats.selected = selected;
 
-        // Calculate rotation based on time
-        let elapsed = start_time.elapsed().as_secs_f32();
-        let angle_x = elapsed * 0.7;
-        let angle_y = elapsed * 1.0;
-        let angle_z = elapsed * 0.3;
+        let help = if menu.open {
+            let rows = menu.rows(|item| menu_value(item, &options, colors, playback.speed));
+            let keys: Vec<(&str, &str)> = rows.iter().map(|(label, value)| (label.as_str(), value.as_str())).collect();
+            Some(help_lines("Options | Up, Down pick | Left, Right change | Esc close", &keys))
+        } else { hud.help.then(|| {
+            let mouse = if fly.is_some() { FLY_MOUSE_HELP } else { ORBIT_MOUSE_HELP };
+            let mapped = keymap.help(fly.is_some());
+            let keys: Vec<(&str, &str)> = mapped.iter().map(|(keys, what)| (keys.as_str(), *what))
+                .chain(mouse.iter().chain(FIXED_KEY_HELP).copied())
+                .collect();
+            let title = format!("Keys | {} | {} camera | {} tool", options.render.mode.name(),
+                if fly.is_some() { "fly" } else { "orbit" }, gizmo.tool.name());
+            help_lines(&title, &keys)
+        }) };
 
-        let screen = render_cube(cols as usize, lines as usize, angle_x, angle_y, angle_z);
+        if backend.is_bitmap() {
+            let image = render_bitmap(&scene, &proj, elapsed, &options.render, cell.unwrap_or(DEFAULT_CELL_PIXELS));
+            stats.frame_time = frame_start.elapsed();
+            run_stats.frame_cells(proj.width * proj.height);
+            queue!(out, MoveTo(origin.0 as u16, origin.1 as u16))?;
+            match backend {
+                Backend::Iterm => write_iterm(&mut out, &image)?,
+                _ => write_sixel(&mut out, &image)?,
+            }
+            if hud_rows > 0 {
+                // Cut to the width, a wrapped line would scroll the screen
+                let line: String = hud.bottom_line(&stats).chars().take(cols as usize).collect();
+                queue!(out, MoveTo(0, lines), Print(line), Clear(ClearType::UntilNewLine))?;
+            }
+            if let Some(help) = &help {
+                let (x, y) = help_origin(cols as usize, lines as usize, help);
+                for (i, line) in help.iter().enumerate().take((lines as usize).saturating_sub(y)) {
+                    let line: String = line.chars().take((cols as usize).saturating_sub(x)).collect();
+                    queue!(out, MoveTo(x as u16, (y + i) as u16), Print(line))?;
+                }
+            }
+        } else {
+            let mut fb = render_frame(&scene, &proj, elapsed, &options.render);
+            if let Some(previous) = &previous_frame {
+                apply_motion_blur(&mut fb, previous, options.render.motion_blur, options.render.truecolor);
+            }
+            stats.frame_time = frame_start.elapsed();
+            let fill = rgb_color(options.render.background.unwrap_or(BACKGROUND_RGB).map(|c| c as f32 / 255.0), options.render.truecolor);
+            let boxed = options.letterbox.is_some() || options.size.is_some();
+            let framed = boxed.then(|| letterbox(&fb, cols as usize, lines as usize, origin, fill));
+            let mut shown = framed.as_ref().unwrap_or(&fb);
+            let with_hud;
+            if hud_rows > 0 {
+                with_hud = append_line(shown, &hud.bottom_line(&stats));
+                shown = &with_hud;
+            }
+            let helped;
+            if let Some(help) = &help {
+                helped = with_help(shown, help);
+                shown = &helped;
+            }
+            run_stats.frame_cells(screen.draw(&mut out, shown, &options.render.ramp)?);
+            if options.render.motion_blur > 0.0 { previous_frame = Some(fb); }
+        }
 
-        // One line that wraps, \r goes back to start
-        print!("\r{}", screen);
+        out.flush()?;
+        run_stats.frame_time(frame_start.elapsed());
+        if let Some(fps) = frame_cap {
+            let next = frame_start + Duration::from_secs_f32(1.0 / fps);
+            std::thread::sleep(next.saturating_duration_since(std::time::Instant::now()));
+        }
+    }
 
-        print!(
-            " --- Temps écoulé depuis le dernier rendu : {:?} ms ---",
-            last_render_time.elapsed().as_millis()
-        );
+    if let Some(recorder) = camera_recorder { recorder.finish()?; }
+    if let Some(target) = &options.stats_json {
+        out.flush()?;
+        run_stats.bytes_written(out.get_ref().bytes);
+        target.write(&run_stats).map_err(std::io::Error::other)?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        std::io::Write::flush(&mut std::io::stdout())?;
-        last_render_time = std::time::Instant::now();
+    #[test]
+    fn zoom_out_of_a_scene_bigger_than_the_farthest() {
+        let mut distance = DISTANCE;
+        zoom(&mut distance, 62.0, 1.0);
+        assert_eq!(distance, 62.0);
+        zoom(&mut distance, 62.0, -1.0);
+        assert_eq!(distance, 62.0);
+        zoom(&mut distance, 1.0, 100.0);
+        assert_eq!(distance, MAX_DISTANCE);
     }
-}
\ No newline at end of file
+}
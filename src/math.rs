@@ -0,0 +1,103 @@
+// Small vector helpers over plain [f32; 3] arrays
+
+pub fn add(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+pub fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+pub fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub fn length(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let len = length(a);
+    if len > 0.0 { scale(a, 1.0 / len) } else { a }
+}
+
+// Mirror direction `d` about the unit normal `n`
+pub fn reflect(d: [f32; 3], n: [f32; 3]) -> [f32; 3] {
+    sub(d, scale(n, 2.0 * dot(d, n)))
+}
+
+pub fn rotate_x(point: [f32; 3], angle: f32) -> [f32; 3] {
+    let cos_a = angle.cos();
+    let sin_a = angle.sin();
+    [
+        point[0],
+        point[1] * cos_a - point[2] * sin_a,
+        point[1] * sin_a + point[2] * cos_a,
+    ]
+}
+
+pub fn rotate_y(point: [f32; 3], angle: f32) -> [f32; 3] {
+    let cos_a = angle.cos();
+    let sin_a = angle.sin();
+    [
+        point[0] * cos_a + point[2] * sin_a,
+        point[1],
+        -point[0] * sin_a + point[2] * cos_a,
+    ]
+}
+
+pub fn rotate_z(point: [f32; 3], angle: f32) -> [f32; 3] {
+    let cos_a = angle.cos();
+    let sin_a = angle.sin();
+    [
+        point[0] * cos_a - point[1] * sin_a,
+        point[0] * sin_a + point[1] * cos_a,
+        point[2],
+    ]
+}
+
+pub fn get_face_normal(v0: [f32; 3], v1: [f32; 3], v2: [f32; 3]) -> [f32; 3] {
+    cross(sub(v1, v0), sub(v2, v0))
+}
+
+// Barycentric weights of `p` (assumed on the triangle's plane) with respect
+// to the triangle (a, b, c)
+pub fn barycentric(p: [f32; 3], a: [f32; 3], b: [f32; 3], c: [f32; 3]) -> [f32; 3] {
+    let n = cross(sub(b, a), sub(c, a));
+    let area = dot(n, n);
+    if area <= 0.0 { return [1.0, 0.0, 0.0]; }
+
+    let wa = dot(n, cross(sub(b, p), sub(c, p))) / area;
+    let wb = dot(n, cross(sub(c, p), sub(a, p))) / area;
+    [wa, wb, 1.0 - wa - wb]
+}
+
+// Interpolate per-vertex UVs of a convex polygon at point `p` on it, using
+// the fan triangle that contains it best
+pub fn polygon_uv(p: [f32; 3], verts: &[[f32; 3]], uvs: &[[f32; 2]]) -> [f32; 2] {
+    let mut best = (f32::MIN, [0.0, 0.0]);
+    for i in 1..verts.len().saturating_sub(1) {
+        let w = barycentric(p, verts[0], verts[i], verts[i + 1]);
+        let worst = w[0].min(w[1]).min(w[2]);
+        if worst > best.0 {
+            let (ua, ub, uc) = (uvs[0], uvs[i], uvs[i + 1]);
+            best = (worst, [
+                w[0] * ua[0] + w[1] * ub[0] + w[2] * uc[0],
+                w[0] * ua[1] + w[1] * ub[1] + w[2] * uc[1],
+            ]);
+        }
+    }
+    best.1
+}
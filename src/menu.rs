@@ -0,0 +1,104 @@
+// The options menu Esc opens, for changing the main settings without
+// knowing their flags or keys. Up and Down pick a setting, Left and Right go
+// through its choices. A change becomes the command typed after `:` would
+// be, so both work the same way
+
+use crate::color::COLOR_NAMES;
+use crate::framebuffer::RAMP_PRESETS;
+use crate::scene::SCENE_NAMES;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum Item {
+    Shape,
+    Mode,
+    Shading,
+    Characters,
+    Colors,
+    Speed,
+}
+
+const ITEMS: [Item; 6] = [Item::Shape, Item::Mode, Item::Shading, Item::Characters, Item::Colors, Item::Speed];
+
+const MODE_NAMES: [&str; 4] = ["solid", "raytrace", "wireframe", "points"];
+const SHADING_NAMES: [&str; 3] = ["smooth", "toon", "hatch"];
+// Time scales Left and Right step through, playing backwards keeps going
+// backwards
+const SPEEDS: [f32; 9] = [0.1, 0.25, 0.5, 0.75, 1.0, 1.5, 2.0, 4.0, 10.0];
+
+impl Item {
+    pub fn label(self) -> &'static str {
+        match self {
+            Item::Shape => "shape",
+            Item::Mode => "render mode",
+            Item::Shading => "shading",
+            Item::Characters => "characters",
+            Item::Colors => "colors",
+            Item::Speed => "speed",
+        }
+    }
+
+    // The command changing this setting `step` choices on from `current`,
+    // what the panel shows for it
+    fn change(self, current: &str, step: isize) -> String {
+        let pick = |names: &[&str]| {
+            // Something not in the list, an OBJ file or typed characters,
+            // goes to the first or the last choice
+            let next = match names.iter().position(|name| *name == current) {
+                Some(i) => (i as isize + step).rem_euclid(names.len() as isize) as usize,
+                None if step > 0 => 0,
+                None => names.len() - 1,
+            };
+            names[next].to_string()
+        };
+        match self {
+            Item::Shape => format!("load {}", pick(&SCENE_NAMES)),
+            Item::Mode => format!("mode {}", pick(&MODE_NAMES)),
+            Item::Shading => format!("shading {}", pick(&SHADING_NAMES)),
+            Item::Characters => format!("ramp {}", pick(&RAMP_PRESETS.map(|(name, _)| name))),
+            Item::Colors => format!("colors {}", pick(&COLOR_NAMES)),
+            Item::Speed => {
+                let speed: f32 = current.trim_end_matches('x').parse().unwrap_or(1.0);
+                let magnitude = if step > 0 {
+                    SPEEDS.iter().find(|s| **s > speed.abs() + 1e-3).unwrap_or(&SPEEDS[SPEEDS.len() - 1])
+                } else {
+                    SPEEDS.iter().rev().find(|s| **s < speed.abs() - 1e-3).unwrap_or(&SPEEDS[0])
+                };
+                format!("speed {}", magnitude.copysign(speed))
+            }
+        }
+    }
+}
+
+pub struct Menu {
+    pub open: bool,
+    selected: usize,
+}
+
+impl Menu {
+    pub fn new() -> Menu {
+        Menu { open: false, selected: 0 }
+    }
+
+    // Pick the setting `rows` further down, wrapping round
+    pub fn select(&mut self, rows: isize) {
+        self.selected = (self.selected as isize + rows).rem_euclid(ITEMS.len() as isize) as usize;
+    }
+
+    // The command changing the picked setting `step` choices on, given what
+    // `value` says each setting is now
+    pub fn change(&self, step: isize, value: impl Fn(Item) -> String) -> String {
+        let item = ITEMS[self.selected];
+        item.change(&value(item), step)
+    }
+
+    // Keys of the panel, every setting with its value and the picked one
+    // marked, for `help_lines`
+    pub fn rows(&self, value: impl Fn(Item) -> String) -> Vec<(String, String)> {
+        ITEMS.iter().enumerate()
+            .map(|(i, item)| {
+                let marker = if i == self.selected { ">" } else { " " };
+                (format!("{marker} {}", item.label()), format!("< {} >", value(*item)))
+            })
+            .collect()
+    }
+}
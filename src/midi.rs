@@ -0,0 +1,112 @@
+// MIDI controllers, for playing the renderer along with music. Knobs and
+// faders sending control changes set the playback speed, where the key
+// light is and its color, and the field of view. Every input port is
+// listened to, and a control only takes over once it is moved
+
+use std::f32::consts::{FRAC_PI_2, TAU};
+use std::sync::mpsc::{channel, Receiver, Sender};
+
+use midir::{MidiInput, MidiInputConnection};
+
+use crate::playback::{Playback, MAX_SPEED, MIN_SPEED};
+use crate::scene::Scene;
+
+#[derive(Clone, Copy, PartialEq)]
+enum Control {
+    Speed,
+    LightAzimuth,
+    LightElevation,
+    FieldOfView,
+    LightHue,
+    LightSaturation,
+}
+
+// Controller numbers of each control, the general purpose sound
+// controllers most knob boxes send by default
+const CONTROLS: [(u8, Control); 6] = [
+    (70, Control::Speed),
+    (71, Control::LightAzimuth),
+    (72, Control::LightElevation),
+    (73, Control::FieldOfView),
+    (74, Control::LightHue),
+    (75, Control::LightSaturation),
+];
+
+// Most the field of view is narrowed or widened by, as a factor on the
+// projection's scale
+const MAX_LENS: f32 = 2.0;
+
+// Direction towards a light on the hemisphere over the scene: `azimuth`
+// radians around the vertical from the viewer's side, `elevation` radians up
+// from the horizon
+fn hemisphere_dir(azimuth: f32, elevation: f32) -> [f32; 3] {
+    [elevation.cos() * azimuth.sin(), elevation.sin(), -elevation.cos() * azimuth.cos()]
+}
+
+// Linear RGB of a fully bright color, `hue` going from red at 0.0 round to
+// red again at 1.0 and `saturation` from white at 0.0 to the pure hue
+fn hue_rgb(hue: f32, saturation: f32) -> [f32; 3] {
+    [5.0, 3.0, 1.0].map(|n: f32| {
+        let k = (n + hue * 6.0).rem_euclid(6.0);
+        1.0 - saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+    })
+}
+
+pub struct Midi {
+    // Kept open for as long as messages should arrive
+    _connections: Vec<MidiInputConnection<Sender<(u8, u8)>>>,
+    // Controller numbers and values, 0 to 127
+    changes: Receiver<(u8, u8)>,
+    // Factor on the projection's scale, over 1.0 narrowing the view
+    pub lens: f32,
+    // Key light position and color, in 0.0..=1.0 each
+    azimuth: f32,
+    elevation: f32,
+    hue: f32,
+    saturation: f32,
+}
+
+impl Midi {
+    // `None` when MIDI can't be used or there is no input port
+    pub fn connect() -> Option<Midi> {
+        let ids: Vec<String> = MidiInput::new("teruminator").ok()?.ports().iter().map(|port| port.id()).collect();
+        let (sender, changes) = channel();
+        // Connecting uses up the client, so each port gets its own
+        let connections: Vec<_> = ids.iter()
+            .filter_map(|id| {
+                let input = MidiInput::new("teruminator").ok()?;
+                let port = input.find_port_by_id(id)?;
+                input.connect(&port, "teruminator", |_, message, sender: &mut Sender<(u8, u8)>| {
+                    // Control change on any channel
+                    if let &[status, number, value] = message && status & 0xf0 == 0xb0 {
+                        let _ = sender.send((number, value));
+                    }
+                }, sender.clone()).ok()
+            })
+            .collect();
+        if connections.is_empty() { return None; }
+        Some(Midi { _connections: connections, changes, lens: 1.0, azimuth: 0.5, elevation: 0.5, hue: 0.0, saturation: 0.0 })
+    }
+
+    // Apply the controls moved since the last call
+    pub fn perform(&mut self, scene: &mut Scene, playback: &mut Playback) {
+        let mut light = (false, false);
+        while let Ok((number, value)) = self.changes.try_recv() {
+            let Some((_, control)) = CONTROLS.iter().find(|(n, _)| *n == number) else { continue; };
+            let v = value as f32 / 127.0;
+            match control {
+                // Evenly spread on a log scale, keeping the direction
+                Control::Speed => playback.speed = playback.speed.signum() * MIN_SPEED * (MAX_SPEED / MIN_SPEED).powf(v),
+                Control::FieldOfView => self.lens = MAX_LENS.powf(1.0 - 2.0 * v),
+                Control::LightAzimuth => (self.azimuth, light.0) = (v, true),
+                Control::LightElevation => (self.elevation, light.0) = (v, true),
+                Control::LightHue => (self.hue, light.1) = (v, true),
+                Control::LightSaturation => (self.saturation, light.1) = (v, true),
+            }
+        }
+        let Some(key) = scene.lights.first_mut() else { return; };
+        // All the way round, and from the horizon to straight overhead
+        if light.0 { key.dir = hemisphere_dir((self.azimuth - 0.5) * TAU, self.elevation * FRAC_PI_2); }
+        if light.1 { key.color = hue_rgb(self.hue, self.saturation); }
+    }
+}
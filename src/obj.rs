@@ -0,0 +1,93 @@
+// Wavefront OBJ models: vertex positions, texture coordinates and polygon
+// faces. Normals, materials and groups are ignored and every face gets the
+// same color
+
+use std::path::Path;
+
+use crossterm::style::Color;
+
+use crate::math::{length, scale, sub};
+use crate::scene::{face_edges, Face, Mesh};
+
+const MODEL_COLOR: Color = Color::White;
+// Models are centered and scaled so their farthest vertex is this far out,
+// about as big as the built-in cube
+const MODEL_EXTENT: f32 = 1.5;
+
+pub fn load_obj(path: &Path) -> Result<Mesh, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("cannot load model {}: {e}", path.display()))?;
+    parse_obj(&text).map_err(|e| format!("model {}: {e}", path.display()))
+}
+
+fn parse_obj(text: &str) -> Result<Mesh, String> {
+    let mut vertices: Vec<[f32; 3]> = Vec::new();
+    let mut uvs: Vec<[f32; 2]> = Vec::new();
+    let mut faces = Vec::new();
+
+    for (n, line) in text.lines().enumerate() {
+        let bad = || format!("line {}: cannot read '{}'", n + 1, line.trim());
+        let mut words = line.split_whitespace();
+        let Some(kind) = words.next() else { continue; };
+        let numbers = |words: std::str::SplitWhitespace, count: usize| -> Option<Vec<f32>> {
+            let numbers: Vec<f32> = words.take(count).map(|w| w.parse().ok()).collect::<Option<_>>()?;
+            if numbers.len() == count { Some(numbers) } else { None }
+        };
+        match kind {
+            // OBJ is right-handed, so z is flipped to look the same here
+            "v" => {
+                let p = numbers(words, 3).ok_or_else(bad)?;
+                vertices.push([p[0], p[1], -p[2]]);
+            }
+            "vt" => {
+                let t = numbers(words, 2).ok_or_else(bad)?;
+                uvs.push([t[0], t[1]]);
+            }
+            "f" => {
+                let mut indices = Vec::new();
+                let mut uv = Vec::new();
+                for corner in words {
+                    let mut refs = corner.split('/');
+                    let v = refs.next().and_then(|r| index(r, vertices.len())).ok_or_else(bad)?;
+                    indices.push(v);
+                    if let Some(r) = refs.next().filter(|r| !r.is_empty()) {
+                        uv.push(uvs[index(r, uvs.len()).ok_or_else(bad)?]);
+                    }
+                }
+                if indices.len() < 3 { return Err(bad()); }
+                // Only kept when every corner has one
+                if uv.len() != indices.len() { uv.clear(); }
+                // Flipping z turned the winding around too
+                indices.reverse();
+                uv.reverse();
+                faces.push(Face { indices, color: MODEL_COLOR, uv, art: None });
+            }
+            _ => {}
+        }
+    }
+    if faces.is_empty() {
+        return Err("no faces".to_string());
+    }
+
+    // Centered on its bounding box
+    let (low, high) = vertices.iter().fold(([f32::MAX; 3], [f32::MIN; 3]), |(low, high), v| {
+        (std::array::from_fn(|i| low[i].min(v[i])), std::array::from_fn(|i| high[i].max(v[i])))
+    });
+    let center: [f32; 3] = std::array::from_fn(|i| (low[i] + high[i]) / 2.0);
+    let extent = vertices.iter().map(|v| length(sub(*v, center))).fold(0.0, f32::max);
+    let factor = if extent > 0.0 { MODEL_EXTENT / extent } else { 1.0 };
+    for v in &mut vertices {
+        *v = scale(sub(*v, center), factor);
+    }
+
+    let edges = face_edges(&faces);
+    Ok(Mesh { vertices, faces, edges })
+}
+
+// An OBJ reference to one of `count` elements read so far: counted from 1,
+// or back from the latest when negative
+fn index(word: &str, count: usize) -> Option<usize> {
+    let i: i64 = word.parse().ok()?;
+    let i = if i < 0 { count as i64 + i } else { i - 1 };
+    (0..count as i64).contains(&i).then_some(i as usize)
+}
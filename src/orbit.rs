@@ -0,0 +1,77 @@
+use std::time::{Duration, Instant};
+
+// Radians the objects turn per column dragged across with the mouse, rows
+// count `aspect` times more
+pub const DRAG_STEP: f32 = 0.05;
+// Weight of the newest drag movement in the measured turn rate
+const VELOCITY_SMOOTHING: f32 = 0.5;
+// A release this long after the last movement means the pointer had
+// stopped, so nothing is thrown
+const THROW_WINDOW: Duration = Duration::from_millis(100);
+// Turn rate under which the objects come to rest, in radians per second
+const REST_SPEED: f32 = 0.01;
+
+// Turning the objects with the mouse. A drag turns them with the pointer,
+// letting go throws them: they keep turning at the rate of the drag and
+// slow down by `damping`
+pub struct Orbit {
+    // Share of the turn rate lost per second thrown
+    pub damping: f32,
+    // Cell and time the pointer was last seen at while dragging
+    last: Option<((u16, u16), Instant)>,
+    // Yaw and pitch rate in radians per second
+    velocity: [f32; 2],
+}
+
+impl Orbit {
+    pub fn new(damping: f32) -> Orbit {
+        Orbit { damping, last: None, velocity: [0.0; 2] }
+    }
+
+    // Start a drag at `cell`, catching anything still turning
+    pub fn grab(&mut self, cell: (u16, u16)) {
+        self.last = Some((cell, Instant::now()));
+        self.velocity = [0.0; 2];
+    }
+
+    // The pointer moved to `cell` while dragging. Returns the yaw and pitch
+    // to turn by
+    pub fn drag(&mut self, cell: (u16, u16), aspect: f32) -> (f32, f32) {
+        let now = Instant::now();
+        let Some((from, at)) = self.last.replace((cell, now)) else { return (0.0, 0.0); };
+        let yaw = (cell.0 as f32 - from.0 as f32) * DRAG_STEP;
+        let pitch = (cell.1 as f32 - from.1 as f32) * DRAG_STEP * aspect;
+
+        let dt = (now - at).as_secs_f32().max(1e-3);
+        for (v, turn) in self.velocity.iter_mut().zip([yaw, pitch]) {
+            *v += (turn / dt - *v) * VELOCITY_SMOOTHING;
+        }
+        (yaw, pitch)
+    }
+
+    pub fn release(&mut self) {
+        if self.last.take().is_some_and(|(_, at)| at.elapsed() > THROW_WINDOW) {
+            self.velocity = [0.0; 2];
+        }
+    }
+
+    // End a drag without throwing anything
+    pub fn stop(&mut self) {
+        self.last = None;
+        self.velocity = [0.0; 2];
+    }
+
+    // Whether the objects are still turning from a throw
+    pub fn moving(&self) -> bool {
+        self.last.is_none() && self.velocity.iter().any(|v| v.abs() > REST_SPEED)
+    }
+
+    // Yaw and pitch a throw turns the objects by over `dt` seconds
+    pub fn step(&mut self, dt: f32) -> (f32, f32) {
+        if !self.moving() { return (0.0, 0.0); }
+        let turn = (self.velocity[0] * dt, self.velocity[1] * dt);
+        let keep = (1.0 - self.damping).clamp(0.0, 1.0).powf(dt);
+        self.velocity = self.velocity.map(|v| v * keep);
+        turn
+    }
+}
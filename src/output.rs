@@ -0,0 +1,239 @@
+use std::io::Write;
+
+use clap::ValueEnum;
+use crossterm::{
+    cursor::MoveTo,
+    queue,
+    style::{Color, Print, ResetColor, SetBackgroundColor, SetForegroundColor},
+    terminal::{Clear, ClearType},
+};
+
+use crate::color::{ansi256_rgb, color_to_rgb, ColorSupport, BACKGROUND_RGB};
+use crate::framebuffer::{FrameBuffer, Ramp};
+use crate::iterm::iterm_supported;
+use crate::sixel::sixel_supported;
+
+// How frames get to the terminal
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Backend {
+    // Characters and colors, works everywhere
+    Text,
+    // A sixel image of the frame
+    Sixel,
+    // The frame as an iTerm2 inline image
+    Iterm,
+}
+
+impl Backend {
+    pub fn detect() -> Backend {
+        if iterm_supported() {
+            Backend::Iterm
+        } else if sixel_supported() {
+            Backend::Sixel
+        } else {
+            Backend::Text
+        }
+    }
+
+    // Whether frames are sent as images rendered by `render_bitmap`
+    pub fn is_bitmap(self) -> bool {
+        self != Backend::Text
+    }
+}
+
+// What a terminal cell shows once resolved: the character with its
+// foreground and background colors. Blank cells have no foreground
+type Shown = (char, Option<Color>, Option<Color>);
+
+fn shown(fb: &FrameBuffer, i: usize, ramp: &Ramp, colors: ColorSupport) -> Shown {
+    let cell = &fb.cells[i];
+    let c = cell.resolve(i % fb.width, i / fb.width, ramp);
+    let color = colors.quantize(cell.shown_color(colors.rgb()));
+    let background = cell.background.and_then(|bg| colors.quantize(bg));
+    (c, color.filter(|_| c != ' '), background)
+}
+
+// Write `fb` as lines of text, with the escape sequences for its colors
+// unless `colors` has none, for a file that `cat` shows as it was
+pub fn write_ansi(out: &mut impl Write, fb: &FrameBuffer, ramp: &Ramp, colors: ColorSupport) -> std::io::Result<()> {
+    for y in 0..fb.height {
+        let mut current: (Option<Color>, Option<Color>) = (None, None);
+        for x in 0..fb.width {
+            let (c, fg, bg) = shown(fb, y * fb.width + x, ramp, colors);
+            if (fg, bg) != current {
+                queue!(out, ResetColor)?;
+                if let Some(fg) = fg { queue!(out, SetForegroundColor(fg))?; }
+                if let Some(bg) = bg { queue!(out, SetBackgroundColor(bg))?; }
+                current = (fg, bg);
+            }
+            queue!(out, Print(c))?;
+        }
+        if current != (None, None) { queue!(out, ResetColor)?; }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+// Size of a cell in an SVG, and the font size whose monospace characters
+// fill it
+const SVG_CELL: (usize, usize) = (9, 18);
+const SVG_FONT_SIZE: usize = 15;
+// What blank cells and characters without a color of their own show
+const SVG_FOREGROUND: [u8; 3] = [204, 204, 204];
+
+// Write `fb` as an SVG image of its characters, colored as `colors` shows
+// them on a terminal. Cells are laid out one by one, so the picture holds
+// up whatever the font's widths
+pub fn write_svg(out: &mut impl Write, fb: &FrameBuffer, ramp: &Ramp, colors: ColorSupport) -> std::io::Result<()> {
+    let (width, height) = (fb.width * SVG_CELL.0, fb.height * SVG_CELL.1);
+    writeln!(out, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">")?;
+    writeln!(out, "<rect width=\"100%\" height=\"100%\" fill=\"{}\"/>", hex(BACKGROUND_RGB))?;
+    writeln!(out, "<g font-family=\"monospace\" font-size=\"{SVG_FONT_SIZE}\">")?;
+    for y in 0..fb.height {
+        let row: Vec<Shown> = (0..fb.width).map(|x| shown(fb, y * fb.width + x, ramp, colors)).collect();
+        // Backgrounds first, a rectangle for each run of one color
+        let mut x = 0;
+        while x < row.len() {
+            let run = row[x..].iter().take_while(|cell| cell.2 == row[x].2).count();
+            if let Some(bg) = row[x].2 {
+                writeln!(out, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"{}\"/>",
+                    x * SVG_CELL.0, y * SVG_CELL.1, run * SVG_CELL.0, SVG_CELL.1, hex(rgb(bg)))?;
+            }
+            x += run;
+        }
+        let baseline = y * SVG_CELL.1 + SVG_CELL.1 * 3 / 4;
+        for (x, (c, fg, _)) in row.iter().enumerate().filter(|(_, cell)| cell.0 != ' ') {
+            let fill = fg.map_or(SVG_FOREGROUND, rgb);
+            let c = match c {
+                '<' => "&lt;".to_string(),
+                '>' => "&gt;".to_string(),
+                '&' => "&amp;".to_string(),
+                c => c.to_string(),
+            };
+            writeln!(out, "<text x=\"{}\" y=\"{baseline}\" fill=\"{}\">{c}</text>",
+                x * SVG_CELL.0, hex(fill))?;
+        }
+    }
+    writeln!(out, "</g>\n</svg>")
+}
+
+// Write `fb` as HTML for inside a <pre>: its characters in spans colored
+// as `colors` shows them, a span for each run of the same colors
+pub fn write_html(out: &mut impl Write, fb: &FrameBuffer, ramp: &Ramp, colors: ColorSupport) -> std::io::Result<()> {
+    for y in 0..fb.height {
+        let mut current: Option<(Option<Color>, Option<Color>)> = None;
+        for x in 0..fb.width {
+            let (c, fg, bg) = shown(fb, y * fb.width + x, ramp, colors);
+            // A blank without a background looks the same in any span
+            let style = if c == ' ' && bg.is_none() { current.unwrap_or((None, None)) } else { (fg, bg) };
+            if current != Some(style) {
+                if current.is_some_and(|current| current != (None, None)) { out.write_all(b"</span>")?; }
+                let mut css = Vec::new();
+                if let Some(fg) = style.0 { css.push(format!("color:{}", hex(rgb(fg)))); }
+                if let Some(bg) = style.1 { css.push(format!("background:{}", hex(rgb(bg)))); }
+                if !css.is_empty() { write!(out, "<span style=\"{}\">", css.join(";"))?; }
+                current = Some(style);
+            }
+            match c {
+                '<' => out.write_all(b"&lt;")?,
+                '>' => out.write_all(b"&gt;")?,
+                '&' => out.write_all(b"&amp;")?,
+                c => write!(out, "{c}")?,
+            }
+        }
+        if current.is_some_and(|current| current != (None, None)) { out.write_all(b"</span>")?; }
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+fn rgb(color: Color) -> [u8; 3] {
+    match color {
+        Color::AnsiValue(i) => ansi256_rgb(i),
+        color => color_to_rgb(color),
+    }
+}
+
+fn hex([r, g, b]: [u8; 3]) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+// Remembers what is on the terminal so each frame only sends the cells that
+// changed since the previous one
+pub struct Screen {
+    width: usize,
+    height: usize,
+    shown: Vec<Shown>,
+    colors: ColorSupport,
+}
+
+impl Screen {
+    pub fn new(colors: ColorSupport) -> Screen {
+        Screen { width: 0, height: 0, shown: Vec::new(), colors }
+    }
+
+    // Show colors as `colors` from the next draw on
+    pub fn set_colors(&mut self, colors: ColorSupport) {
+        self.colors = colors;
+        self.invalidate();
+    }
+
+    // Forget what is on the terminal, the next draw clears it and starts over
+    pub fn invalidate(&mut self) {
+        self.width = 0;
+        self.height = 0;
+    }
+
+    // Queue the escape sequences turning the current terminal contents into
+    // `fb`, drawn from the top-left corner. A size change redraws everything.
+    // Returns how many cells changed
+    pub fn draw(&mut self, out: &mut impl Write, fb: &FrameBuffer, ramp: &Ramp) -> std::io::Result<usize> {
+        if fb.width != self.width || fb.height != self.height {
+            queue!(out, Clear(ClearType::All))?;
+            self.width = fb.width;
+            self.height = fb.height;
+            self.shown = vec![(' ', None, None); fb.width * fb.height];
+        }
+
+        // Where the next printed character lands, if known
+        let mut cursor: Option<(usize, usize)> = None;
+        let mut current: (Option<Color>, Option<Color>) = (None, None);
+        let mut changed = 0;
+
+        for i in 0..fb.cells.len() {
+            let (x, y) = (i % fb.width, i / fb.width);
+            let shown = shown(fb, i, ramp, self.colors);
+            if self.shown[i] == shown { continue; }
+            self.shown[i] = shown;
+            changed += 1;
+
+            if cursor != Some((x, y)) {
+                queue!(out, MoveTo(x as u16, y as u16))?;
+            }
+            let style = (shown.1, shown.2);
+            if style != current {
+                // Colors can only be dropped by resetting both
+                if (current.0.is_some() && style.0.is_none()) || (current.1.is_some() && style.1.is_none()) {
+                    queue!(out, ResetColor)?;
+                    current = (None, None);
+                }
+                if let Some(fg) = style.0.filter(|_| style.0 != current.0) {
+                    queue!(out, SetForegroundColor(fg))?;
+                }
+                if let Some(bg) = style.1.filter(|_| style.1 != current.1) {
+                    queue!(out, SetBackgroundColor(bg))?;
+                }
+                current = style;
+            }
+            queue!(out, Print(shown.0))?;
+            // Past the last column the terminal may or may not have wrapped
+            cursor = if x + 1 < fb.width { Some((x + 1, y)) } else { None };
+        }
+
+        if current != (None, None) {
+            queue!(out, ResetColor)?;
+        }
+
+        Ok(changed)
+    }
+}
@@ -0,0 +1,92 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+// Named color schemes for the built-in face colors and the background. The
+// scenes are colored with the six named cube colors plus white, and a
+// palette swaps each of those for its own
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Palette {
+    // The named colors as they are, which follows the terminal's own theme
+    Classic,
+    Pastel,
+    Solarized,
+    Grayscale,
+    Neon,
+    // Safe for the color vision deficiencies they are named after: red-weak,
+    // green-weak and blue-weak. Faces sharing an edge never differ only
+    // along the confused axis
+    Protanopia,
+    Deuteranopia,
+    Tritanopia,
+}
+
+// The colors a palette replaces, in the order of `Palette::faces`
+const NAMED: [Color; 7] = [Color::Red, Color::Green, Color::Blue, Color::Yellow, Color::Magenta, Color::Cyan, Color::White];
+
+impl Palette {
+    // The palettes `--colorblind` picks from
+    pub fn parse_colorblind(s: &str) -> Option<Palette> {
+        match s {
+            "protanopia" => Some(Palette::Protanopia),
+            "deuteranopia" => Some(Palette::Deuteranopia),
+            "tritanopia" => Some(Palette::Tritanopia),
+            _ => None,
+        }
+    }
+
+    // Replacements for red, green, blue, yellow, magenta, cyan and white
+    fn faces(self) -> Option<[[u8; 3]; 7]> {
+        match self {
+            Palette::Classic => None,
+            Palette::Pastel => Some([
+                [255, 179, 186], [186, 255, 201], [186, 225, 255], [255, 255, 186],
+                [226, 190, 255], [187, 246, 240], [250, 250, 250],
+            ]),
+            Palette::Solarized => Some([
+                [220, 50, 47], [133, 153, 0], [38, 139, 210], [181, 137, 0],
+                [211, 54, 130], [42, 161, 152], [238, 232, 213],
+            ]),
+            // Lightness alone still tells neighbouring cube faces apart
+            Palette::Grayscale => Some([
+                [96; 3], [224; 3], [128; 3], [192; 3], [255; 3], [160; 3], [255; 3],
+            ]),
+            Palette::Neon => Some([
+                [255, 0, 102], [57, 255, 20], [0, 191, 255], [255, 240, 31],
+                [255, 0, 255], [0, 255, 239], [255, 255, 255],
+            ]),
+            // Both from the Okabe-Ito set. Protans see red as dark, so their
+            // palette uses bluish green where the other has vermillion
+            Palette::Protanopia => Some([
+                [230, 159, 0], [0, 114, 178], [240, 228, 66], [86, 180, 233],
+                [0, 158, 115], [204, 121, 167], [255, 255, 255],
+            ]),
+            Palette::Deuteranopia => Some([
+                [230, 159, 0], [0, 114, 178], [240, 228, 66], [86, 180, 233],
+                [213, 94, 0], [204, 121, 167], [255, 255, 255],
+            ]),
+            // Blue and yellow are the confused pair, so reds, teals and
+            // lightness carry the difference
+            Palette::Tritanopia => Some([
+                [204, 37, 41], [0, 128, 128], [255, 140, 170], [110, 110, 110],
+                [160, 235, 235], [130, 20, 40], [255, 255, 255],
+            ]),
+        }
+    }
+
+    // Painted behind the frame, `None` leaves the terminal's own
+    pub fn background(self) -> Option<[u8; 3]> {
+        match self {
+            Palette::Solarized => Some([0, 43, 54]),
+            Palette::Neon => Some([10, 0, 20]),
+            _ => None,
+        }
+    }
+
+    pub fn recolor(self, color: Color) -> Color {
+        let Some(faces) = self.faces() else { return color; };
+        match NAMED.iter().position(|c| *c == color) {
+            Some(i) => Color::Rgb { r: faces[i][0], g: faces[i][1], b: faces[i][2] },
+            None => color,
+        }
+    }
+}
@@ -0,0 +1,98 @@
+// Finding what is under a cell of the screen, for the mouse. Rays go out
+// from the eye through the cell like the ray tracer's primary rays
+
+use crossterm::style::Color;
+
+use crate::framebuffer::{Cell, FrameBuffer, Glyph};
+use crate::raytrace::intersect_triangle;
+use crate::render::Projection;
+use crate::scene::Scene;
+
+const HIGHLIGHT_COLOR: Color = Color::White;
+// Dark cells are lifted to this so the highlight shows on them too
+const HIGHLIGHT_INTENSITY: f32 = 0.6;
+// Added to the intensity of the face under the pointer
+const HOVER_BOOST: f32 = 0.3;
+
+// A face of one of the scene's objects, by index
+#[derive(Clone, Copy, PartialEq)]
+pub struct Pick {
+    pub object: usize,
+    pub face: usize,
+}
+
+// The nearest face along the ray from `origin` towards `dir`
+pub fn pick_ray(scene: &Scene, t: f32, origin: [f32; 3], dir: [f32; 3]) -> Option<Pick> {
+    let mut nearest: Option<(f32, Pick)> = None;
+    for (object_idx, object) in scene.objects.iter().enumerate() {
+        let verts = object.transformed(t);
+        for (face_idx, face) in object.mesh.faces.iter().enumerate() {
+            let corner = |i: usize| verts[face.indices[i]];
+            for i in 1..face.indices.len().saturating_sub(1) {
+                let Some((distance, _, _)) = intersect_triangle(origin, dir, &[corner(0), corner(i), corner(i + 1)]) else { continue; };
+                if nearest.is_none_or(|(d, _)| distance < d) {
+                    nearest = Some((distance, Pick { object: object_idx, face: face_idx }));
+                }
+            }
+        }
+    }
+    nearest.map(|(_, pick)| pick)
+}
+
+// The face seen through cell (x, y) of the view
+pub fn pick_cell(scene: &Scene, proj: &Projection, t: f32, x: i32, y: i32) -> Option<Pick> {
+    pick_ray(scene, t, proj.eye(), proj.cell_ray(x, y))
+}
+
+// Every cell of `fb` where the view sees `pick`, with the face's color.
+// Only the screen rectangle around the face is searched
+fn cells_showing(fb: &mut FrameBuffer, scene: &Scene, proj: &Projection, t: f32, pick: Pick, mut paint: impl FnMut(&mut Cell, Color)) {
+    let Some(object) = scene.objects.get(pick.object) else { return; };
+    let Some(face) = object.mesh.faces.get(pick.face) else { return; };
+    let verts = object.transformed(t);
+    let corners: Option<Vec<(f32, f32, f32)>> = face.indices.iter().map(|i| proj.project_f(verts[*i])).collect();
+    // A corner behind the eye leaves no bounds to go by
+    let (x0, y0, x1, y1) = match corners {
+        Some(corners) => corners.iter().fold((f32::MAX, f32::MAX, f32::MIN, f32::MIN), |(x0, y0, x1, y1), (x, y, _)| {
+            (x0.min(*x), y0.min(*y), x1.max(*x), y1.max(*y))
+        }),
+        None => (0.0, 0.0, fb.width as f32, fb.height as f32),
+    };
+    let xs = (x0.floor().max(0.0) as usize)..(x1.ceil().max(0.0) as usize).min(fb.width);
+    let ys = (y0.floor().max(0.0) as usize)..(y1.ceil().max(0.0) as usize).min(fb.height);
+
+    for y in ys {
+        for x in xs.clone() {
+            if pick_cell(scene, proj, t, x as i32, y as i32) == Some(pick) {
+                paint(&mut fb.cells[y * fb.width + x], face.color);
+            }
+        }
+    }
+}
+
+// Draw `pick` in the highlight color
+pub fn highlight(fb: &mut FrameBuffer, scene: &Scene, proj: &Projection, t: f32, pick: Pick) {
+    cells_showing(fb, scene, proj, t, pick, |cell, _| {
+        // Wireframe and point views leave the inside of faces blank
+        if cell.is_empty() {
+            *cell = Cell::shaded(HIGHLIGHT_INTENSITY, HIGHLIGHT_COLOR);
+            return;
+        }
+        cell.color = HIGHLIGHT_COLOR;
+        // Solid blocks and fixed characters keep their shape
+        if matches!(cell.glyph, Glyph::Ramp | Glyph::Hatch(_)) {
+            cell.intensity = cell.intensity.max(HIGHLIGHT_INTENSITY);
+        }
+    });
+}
+
+// Draw `pick` brighter in its own color, lighter than a highlight
+pub fn hover(fb: &mut FrameBuffer, scene: &Scene, proj: &Projection, t: f32, pick: Pick) {
+    cells_showing(fb, scene, proj, t, pick, |cell, color| {
+        if cell.is_empty() {
+            *cell = Cell::shaded(HOVER_BOOST, color);
+        } else if matches!(cell.glyph, Glyph::Ramp | Glyph::Hatch(_)) {
+            cell.intensity = (cell.intensity + HOVER_BOOST).min(1.0);
+        }
+    });
+}
@@ -0,0 +1,48 @@
+// Rendering to an actual bitmap, for the terminal graphics protocols. The
+// frame is rendered with one sample per pixel and each sample turned into
+// a color, the char backend's glyphs play no part
+
+use crate::color::{color_to_rgb, BACKGROUND_RGB};
+use crate::framebuffer::{FrameBuffer, Glyph};
+use crate::render::{render_frame, Projection, RenderSettings};
+use crate::scene::Scene;
+use crate::subcell::SubCell;
+
+// Assumed when the terminal doesn't report its size in pixels
+pub const DEFAULT_CELL_PIXELS: (usize, usize) = (8, 16);
+
+// An RGB image, row by row
+pub struct Bitmap {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<[u8; 3]>,
+}
+
+impl Bitmap {
+    // One pixel per cell of `fb`, empty cells taking the background color
+    pub fn from_framebuffer(fb: &FrameBuffer) -> Bitmap {
+        let pixels = fb.cells.iter()
+            .map(|cell| match (cell.glyph, cell.background) {
+                (Glyph::Fixed(' '), Some(bg)) => color_to_rgb(bg),
+                (Glyph::Fixed(' '), None) => BACKGROUND_RGB,
+                _ => color_to_rgb(cell.shown_color(true)),
+            })
+            .collect();
+        Bitmap { width: fb.width, height: fb.height, pixels }
+    }
+}
+
+// Render the area `proj` covers in cells at `cell` pixels per cell. Options
+// that only exist to get past the cell grid are left out
+pub fn render_bitmap(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings, cell: (usize, usize)) -> Bitmap {
+    let settings = RenderSettings {
+        supersample: 1,
+        antialias: false,
+        subcell: SubCell::Off,
+        solid_fill: false,
+        truecolor: true,
+        ..settings.clone()
+    };
+    let fb = render_frame(scene, &proj.subdivided(cell.0, cell.1), t, &settings);
+    Bitmap::from_framebuffer(&fb)
+}
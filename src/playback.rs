@@ -0,0 +1,97 @@
+// Animation clock the scene is posed with. It only runs while playing and
+// at an adjustable rate, unlike the wall clock it started from
+pub struct Playback {
+    // Seconds of animation shown so far
+    pub time: f32,
+    // Animation seconds per real second, below zero playing backwards
+    pub speed: f32,
+    pub paused: bool,
+}
+
+// Real time handed out in steps of the same length, so what is simulated
+// with them comes out the same however fast or slow frames are drawn. The
+// time left over waits for the next frame
+pub struct FixedStep {
+    // Steps per second
+    pub rate: f32,
+    left: f32,
+}
+
+// Real seconds one frame catches up on at most, so a long stall, like the
+// terminal being suspended, doesn't run thousands of steps at once
+const MAX_CATCH_UP: f32 = 0.25;
+pub const DEFAULT_SIM_RATE: f32 = 60.0;
+
+impl FixedStep {
+    pub fn new(rate: f32) -> FixedStep {
+        FixedStep { rate, left: 0.0 }
+    }
+
+    // Seconds a step takes
+    pub fn step(&self) -> f32 {
+        1.0 / self.rate
+    }
+
+    // How many whole steps `dt` more real seconds make
+    pub fn steps(&mut self, dt: f32) -> usize {
+        self.left = (self.left + dt).min(MAX_CATCH_UP);
+        let steps = (self.left * self.rate).floor();
+        self.left -= steps / self.rate;
+        steps as usize
+    }
+
+    // How far into the next step the time left over has got, from 0 to 1
+    pub fn fraction(&self) -> f32 {
+        (self.left * self.rate).clamp(0.0, 1.0)
+    }
+}
+
+// Factor one speed step changes the rate by, and how far it can go either
+// way
+const SPEED_STEP: f32 = 1.25;
+pub const MIN_SPEED: f32 = 0.1;
+pub const MAX_SPEED: f32 = 10.0;
+// Animation seconds one step moves on by at normal speed, a frame at 30 fps
+const STEP: f32 = 1.0 / 30.0;
+// Animation seconds one scrub moves by at normal speed
+const SCRUB: f32 = 0.25;
+
+impl Playback {
+    pub fn new() -> Playback {
+        Playback { time: 0.0, speed: 1.0, paused: false }
+    }
+
+    // Move on by `dt` real seconds
+    pub fn advance(&mut self, dt: f32) {
+        if !self.paused {
+            self.time += dt * self.speed;
+        }
+    }
+
+    // Stop, if playing, and move on by exactly one step at the current speed,
+    // back in time when playing backwards
+    pub fn step(&mut self) {
+        self.paused = true;
+        self.time += STEP * self.speed;
+    }
+
+    // Stop, if playing, and jump `steps` scrubs on, or back when negative,
+    // whichever way it plays
+    pub fn scrub(&mut self, steps: f32) {
+        self.paused = true;
+        self.time += SCRUB * self.speed.abs() * steps;
+    }
+
+    // Faster and slower keep the direction
+    pub fn faster(&mut self) {
+        self.speed = self.speed.signum() * (self.speed.abs() * SPEED_STEP).min(MAX_SPEED);
+    }
+
+    pub fn slower(&mut self) {
+        self.speed = self.speed.signum() * (self.speed.abs() / SPEED_STEP).max(MIN_SPEED);
+    }
+
+    pub fn reverse(&mut self) {
+        self.speed = -self.speed;
+    }
+}
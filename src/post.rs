@@ -0,0 +1,298 @@
+// Post-processing passes run on the finished frame buffer
+
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+use crate::color::{color_to_rgb, rgb_color, shade_rgb};
+use crate::framebuffer::{Cell, FrameBuffer, Glyph};
+
+// Depth fog, distances are measured from the camera
+#[derive(Clone, Copy, PartialEq)]
+pub enum Fog {
+    Off,
+    // No fog before `start`, fully fogged past `end`
+    Linear { start: f32, end: f32 },
+    // 1 - e^(-density * depth)
+    Exponential { density: f32 },
+}
+
+impl Fog {
+    // `off`, `linear[:START,END]` or `exp[:DENSITY]`
+    pub fn parse(s: &str) -> Option<Fog> {
+        let (kind, params) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "off" if params.is_empty() => Some(Fog::Off),
+            "linear" if params.is_empty() => Some(Fog::Linear { start: 4.0, end: 10.0 }),
+            "linear" => {
+                let (start, end) = params.split_once(',')?;
+                let (start, end) = (start.trim().parse().ok()?, end.trim().parse().ok()?);
+                if end > start { Some(Fog::Linear { start, end }) } else { None }
+            }
+            "exp" if params.is_empty() => Some(Fog::Exponential { density: 0.12 }),
+            "exp" => params.trim().parse().ok().filter(|d: &f32| *d >= 0.0).map(|density| Fog::Exponential { density }),
+            _ => None,
+        }
+    }
+
+    // 0.0 leaves the sample untouched, 1.0 replaces it with the background
+    fn factor(&self, depth: f32) -> f32 {
+        match *self {
+            Fog::Off => 0.0,
+            Fog::Linear { start, end } => ((depth - start) / (end - start)).clamp(0.0, 1.0),
+            Fog::Exponential { density } => 1.0 - (-density * depth.max(0.0)).exp(),
+        }
+    }
+}
+
+// Fade distant cells towards the background: shaded cells move down the ramp
+// and every color is mixed with the background color
+pub fn apply_fog(fb: &mut FrameBuffer, fog: Fog, background: [u8; 3], truecolor: bool) {
+    if fog == Fog::Off { return; }
+
+    for (cell, depth) in fb.cells.iter_mut().zip(&fb.depth) {
+        if cell.is_empty() || *depth == f32::MAX { continue; }
+
+        let f = fog.factor(*depth);
+        if f <= 0.0 { continue; }
+        if f >= 1.0 || (matches!(cell.glyph, Glyph::Fixed(_)) && f >= 0.9) {
+            *cell = Cell::EMPTY;
+            continue;
+        }
+
+        let rgb = color_to_rgb(cell.color);
+        let mixed = [0, 1, 2].map(|i| (rgb[i] as f32 * (1.0 - f) + background[i] as f32 * f) / 255.0);
+        cell.color = rgb_color(mixed, truecolor);
+        cell.intensity *= 1.0 - f;
+    }
+}
+
+// Replace the frame with its depth buffer: nearer cells get brighter ramp
+// characters and grays, over the range of depths actually present
+pub fn depth_view(fb: &mut FrameBuffer, truecolor: bool) {
+    let written = || fb.depth.iter().copied().filter(|d| *d != f32::MAX);
+    let near = written().fold(f32::MAX, f32::min);
+    let far = written().fold(f32::MIN, f32::max);
+    let range = (far - near).max(1e-6);
+
+    for (cell, depth) in fb.cells.iter_mut().zip(&fb.depth) {
+        if *depth == f32::MAX {
+            *cell = Cell::EMPTY;
+            continue;
+        }
+        let closeness = 1.0 - (depth - near) / range;
+        let intensity = 0.1 + 0.9 * closeness;
+        *cell = Cell::shaded(intensity, rgb_color([intensity; 3], truecolor));
+    }
+}
+
+// Phosphor-style persistence: cells of the previous (already blurred) frame
+// fade by `decay` and show through wherever they are brighter than what the
+// current frame has there, leaving trails behind moving objects
+pub fn apply_motion_blur(fb: &mut FrameBuffer, previous: &FrameBuffer, decay: f32, truecolor: bool) {
+    if decay <= 0.0 || previous.width != fb.width || previous.height != fb.height { return; }
+
+    for (i, old) in previous.cells.iter().enumerate() {
+        if old.is_empty() { continue; }
+        let trail = old.intensity * decay;
+        if trail < 0.05 { continue; }
+
+        let cell = &mut fb.cells[i];
+        let current = if cell.is_empty() { 0.0 } else { cell.intensity };
+        if matches!(cell.glyph, Glyph::Fixed(_)) && !cell.is_empty() { continue; }
+        if trail <= current { continue; }
+
+        let color = if truecolor {
+            rgb_color(color_to_rgb(old.color).map(|c| c as f32 / 255.0 * decay), true)
+        } else {
+            old.color
+        };
+        if cell.is_empty() {
+            fb.depth[i] = previous.depth[i];
+            *cell = Cell::shaded(trail, color);
+        } else {
+            cell.intensity = trail;
+        }
+    }
+}
+
+// Shaded cells brighter than this spill light onto their neighbours
+const BLOOM_THRESHOLD: f32 = 0.7;
+// Glow kernel, wider than tall because cells are about twice as tall as wide
+const BLOOM_KERNEL: [(i32, i32, f32); 10] = [
+    (-1, 0, 0.6), (1, 0, 0.6), (-2, 0, 0.3), (2, 0, 0.3),
+    (0, -1, 0.5), (0, 1, 0.5),
+    (-1, -1, 0.25), (1, -1, 0.25), (-1, 1, 0.25), (1, 1, 0.25),
+];
+
+// Let bright cells bleed into their neighbours: the excess above the
+// threshold is spread with a small blur and added on top, lighting up empty
+// cells around bright surfaces with the color of the strongest source
+pub fn apply_bloom(fb: &mut FrameBuffer, truecolor: bool) {
+    let (width, height) = (fb.width as i32, fb.height as i32);
+    let mut glow = vec![0.0f32; fb.cells.len()];
+    let mut rgb = vec![[0.0f32; 3]; fb.cells.len()];
+    let mut strongest: Vec<Option<(f32, usize)>> = vec![None; fb.cells.len()];
+
+    for (i, cell) in fb.cells.iter().enumerate() {
+        if matches!(cell.glyph, Glyph::Fixed(_)) || cell.intensity <= BLOOM_THRESHOLD { continue; }
+        let excess = (cell.intensity - BLOOM_THRESHOLD) / (1.0 - BLOOM_THRESHOLD);
+        let source = color_to_rgb(cell.color).map(|c| c as f32 / 255.0);
+        let (x, y) = ((i % fb.width) as i32, (i / fb.width) as i32);
+        for (dx, dy, weight) in BLOOM_KERNEL {
+            let (nx, ny) = (x + dx, y + dy);
+            if nx < 0 || ny < 0 || nx >= width || ny >= height { continue; }
+            let n = (ny * width + nx) as usize;
+            let amount = excess * weight;
+            glow[n] += amount;
+            for (acc, c) in rgb[n].iter_mut().zip(source) {
+                *acc += c * amount;
+            }
+            if strongest[n].is_none_or(|(best, _)| amount > best) {
+                strongest[n] = Some((amount, i));
+            }
+        }
+    }
+
+    for i in 0..fb.cells.len() {
+        let amount = glow[i].min(1.0);
+        if amount < 0.1 { continue; }
+        let Some((_, source)) = strongest[i] else { continue; };
+        let color = if truecolor {
+            rgb_color(rgb[i].map(|c| (c / glow[i]).min(1.0)), true)
+        } else {
+            fb.cells[source].color
+        };
+
+        let cell = &mut fb.cells[i];
+        if cell.is_empty() {
+            *cell = Cell::shaded(amount * 0.5, color);
+        } else if !matches!(cell.glyph, Glyph::Fixed(_)) {
+            cell.intensity = (cell.intensity + amount * 0.5).min(1.0);
+        }
+    }
+}
+
+// Retro monitor look
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Crt {
+    Off,
+    // Dimmed alternate rows and an occasional horizontal jitter
+    Flat,
+    // The same on a bulging screen
+    Curved,
+}
+
+// Intensity kept on the dimmed scanlines
+const SCANLINE_LEVEL: f32 = 0.6;
+// Barrel distortion strength, 0.0 is flat
+const CURVATURE: f32 = 0.12;
+// Chance per row and per jitter step that the row slips by one cell
+const JITTER_CHANCE: u32 = 6;
+const JITTER_RATE: f32 = 20.0;
+
+// Cheap integer hash, stable for a given row, time step and seed
+fn jitter_hash(row: u32, step: u32, seed: u32) -> u32 {
+    let mut h = row.wrapping_mul(0x9e37_79b9) ^ step.wrapping_mul(0x85eb_ca6b) ^ seed.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x2c1b_3c6d);
+    h ^ (h >> 12)
+}
+
+// Warp the frame through the screen curvature, shift rows that jitter at
+// time `t` for `seed` and dim every other row
+pub fn apply_crt(fb: &mut FrameBuffer, crt: Crt, t: f32, seed: u32, truecolor: bool) {
+    if crt == Crt::Off { return; }
+
+    let (width, height) = (fb.width, fb.height);
+    let step = (t * JITTER_RATE) as u32;
+    let source = |x: usize, y: usize| -> Option<usize> {
+        let shift = match jitter_hash(y as u32, step, seed) % 100 {
+            h if h < JITTER_CHANCE / 2 => -1.0,
+            h if h < JITTER_CHANCE => 1.0,
+            _ => 0.0,
+        };
+        let (mut sx, mut sy) = (x as f32 + 0.5 + shift, y as f32 + 0.5);
+        if crt == Crt::Curved {
+            let u = sx / width as f32 * 2.0 - 1.0;
+            let v = sy / height as f32 * 2.0 - 1.0;
+            let warp = 1.0 + CURVATURE * (u * u + v * v);
+            sx = (u * warp + 1.0) / 2.0 * width as f32;
+            sy = (v * warp + 1.0) / 2.0 * height as f32;
+        }
+        if sx < 0.0 || sy < 0.0 || sx >= width as f32 || sy >= height as f32 { return None; }
+        Some(sy as usize * width + sx as usize)
+    };
+
+    let mut cells = vec![Cell::EMPTY; width * height];
+    let mut depth = vec![f32::MAX; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let Some(src) = source(x, y) else { continue; };
+            let (i, mut cell) = (y * width + x, fb.cells[src]);
+            if y % 2 == 1 && !cell.is_empty() {
+                cell.intensity *= SCANLINE_LEVEL;
+                if truecolor {
+                    let rgb = color_to_rgb(cell.color).map(|c| c as f32 / 255.0 * SCANLINE_LEVEL);
+                    cell.color = rgb_color(rgb, true);
+                }
+            }
+            cells[i] = cell;
+            depth[i] = fb.depth[src];
+        }
+    }
+    fb.cells = cells;
+    fb.depth = depth;
+}
+
+// Turn shaded cells into blank cells with a background of their shaded
+// color, so surfaces read as solid blocks. Applied last since it bakes the
+// intensity into the color
+pub fn fill_backgrounds(fb: &mut FrameBuffer, truecolor: bool) {
+    for cell in fb.cells.iter_mut() {
+        if matches!(cell.glyph, Glyph::Fixed(_)) { continue; }
+        let shaded = color_to_rgb(shade_rgb(cell.color, cell.intensity)).map(|c| c as f32 / 255.0);
+        cell.background = Some(rgb_color(shaded, truecolor));
+        cell.glyph = Glyph::Fixed(' ');
+    }
+}
+
+// Give every cell nothing was drawn in a background color. Only with RGB
+// colors: the nearest named color would come out in the terminal's theme
+pub fn paint_background(fb: &mut FrameBuffer, background: [u8; 3], truecolor: bool) {
+    if !truecolor { return; }
+    let color = rgb_color(background.map(|c| c as f32 / 255.0), true);
+    for cell in fb.cells.iter_mut().filter(|c| c.is_empty()) {
+        cell.background = Some(color);
+    }
+}
+
+// Place `fb` at `origin` in a `width` x `height` frame, with every cell
+// around it given the `fill` background
+pub fn letterbox(fb: &FrameBuffer, width: usize, height: usize, origin: (usize, usize), fill: Color) -> FrameBuffer {
+    let mut out = FrameBuffer::new(width, height);
+    for cell in out.cells.iter_mut() {
+        cell.background = Some(fill);
+    }
+    for y in 0..fb.height.min(height.saturating_sub(origin.1)) {
+        for x in 0..fb.width.min(width.saturating_sub(origin.0)) {
+            let (from, to) = (y * fb.width + x, (y + origin.1) * width + x + origin.0);
+            out.cells[to] = fb.cells[from];
+            out.depth[to] = fb.depth[from];
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_a_frame_bigger_than_the_screen() {
+        let fb = FrameBuffer::new(400, 40);
+        let out = letterbox(&fb, 80, 24, (100, 30), Color::Black);
+        assert_eq!((out.width, out.height), (80, 24));
+        let out = letterbox(&fb, 80, 24, (2, 1), Color::Black);
+        assert_eq!(out.cells.len(), 80 * 24);
+    }
+}
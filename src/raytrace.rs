@@ -0,0 +1,251 @@
+use std::sync::Arc;
+
+use crossterm::style::Color;
+
+use crate::color::{color_to_rgb, rgb_color};
+use crate::math::{add, cross, dot, get_face_normal, normalize, reflect, scale, sub};
+use crate::framebuffer::{Cell, FrameBuffer, Glyph};
+use crate::render::{ground_corners, white_point, Projection, RenderSettings, GROUND_COLOR};
+use crate::scene::{Environment, Scene};
+use crate::texture::{luminance, TextArt, Texture};
+
+// Reflection bounces plus transparent layers a single primary ray may visit
+const MAX_BOUNCES: u32 = 6;
+const EPSILON: f32 = 1e-3;
+// The ground only reflects a little light so it stays in the background
+const GROUND_ALBEDO: f32 = 0.5;
+
+struct Triangle {
+    v: [[f32; 3]; 3],
+    normal: [f32; 3],
+    color: [f32; 3],
+    albedo: f32,
+    reflectivity: f32,
+    opacity: f32,
+    emission: f32,
+    uv: [[f32; 2]; 3],
+    texture: Option<Arc<Texture>>,
+    art: Option<Arc<TextArt>>,
+}
+
+// Light arriving along a ray: the scalar intensity drives the shade ramp,
+// the color is what ends up as the cell color. `glyph` carries text art from
+// the surface that dominates the ray
+struct Radiance {
+    intensity: f32,
+    color: [f32; 3],
+    glyph: Option<char>,
+}
+
+const BACKGROUND: Radiance = Radiance { intensity: 0.0, color: [0.0; 3], glyph: None };
+
+fn sky_radiance(env: &Environment, dir: [f32; 3]) -> Radiance {
+    let color = env.sample(dir);
+    Radiance { intensity: color[0].max(color[1]).max(color[2]), color, glyph: None }
+}
+
+fn to_linear(color: Color) -> [f32; 3] {
+    color_to_rgb(color).map(|c| c as f32 / 255.0)
+}
+
+// Surface description shared by all triangles of one polygon
+struct Surface<'a> {
+    color: [f32; 3],
+    albedo: f32,
+    reflectivity: f32,
+    opacity: f32,
+    emission: f32,
+    uv: &'a [[f32; 2]],
+    texture: Option<Arc<Texture>>,
+    art: Option<Arc<TextArt>>,
+}
+
+// Fan-triangulate every face of the scene (plus the ground) in world space
+fn collect_triangles(scene: &Scene, t: f32) -> Vec<Triangle> {
+    let mut triangles = Vec::new();
+    let mut push_polygon = |verts: &[[f32; 3]], surface: Surface| {
+        if verts.len() < 3 { return; }
+        let normal = normalize(get_face_normal(verts[0], verts[1], verts[2]));
+        let has_uv = surface.uv.len() == verts.len();
+        let uv_at = |i: usize| if has_uv { surface.uv[i] } else { [0.0, 0.0] };
+        for i in 1..verts.len() - 1 {
+            triangles.push(Triangle {
+                v: [verts[0], verts[i], verts[i + 1]],
+                normal,
+                color: surface.color,
+                albedo: surface.albedo,
+                reflectivity: surface.reflectivity,
+                opacity: surface.opacity,
+                emission: surface.emission,
+                uv: [uv_at(0), uv_at(i), uv_at(i + 1)],
+                texture: surface.texture.clone().filter(|_| has_uv),
+                art: surface.art.clone().filter(|_| has_uv),
+            });
+        }
+    };
+
+    for object in &scene.objects {
+        let verts = object.transformed(t);
+        for face in &object.mesh.faces {
+            let face_verts: Vec<[f32; 3]> = face.indices.iter().map(|i| verts[*i]).collect();
+            push_polygon(&face_verts, Surface {
+                color: to_linear(face.color),
+                albedo: 1.0,
+                reflectivity: object.material.reflectivity,
+                opacity: object.material.opacity,
+                emission: object.material.emission,
+                uv: &face.uv,
+                texture: object.material.texture.clone(),
+                art: face.art.clone(),
+            });
+        }
+    }
+    push_polygon(&ground_corners(), Surface {
+        color: to_linear(GROUND_COLOR),
+        albedo: GROUND_ALBEDO,
+        reflectivity: 0.0,
+        opacity: 1.0,
+        emission: 0.0,
+        uv: &[],
+        texture: None,
+        art: None,
+    });
+
+    triangles
+}
+
+fn intersect(origin: [f32; 3], dir: [f32; 3], tri: &Triangle) -> Option<(f32, f32, f32)> {
+    intersect_triangle(origin, dir, &tri.v)
+}
+
+// Möller–Trumbore ray/triangle intersection, returns the ray parameter and
+// the barycentric weights of the second and third vertex
+pub fn intersect_triangle(origin: [f32; 3], dir: [f32; 3], tri: &[[f32; 3]; 3]) -> Option<(f32, f32, f32)> {
+    let e1 = sub(tri[1], tri[0]);
+    let e2 = sub(tri[2], tri[0]);
+    let p = cross(dir, e2);
+    let det = dot(e1, p);
+    if det.abs() < 1e-8 { return None; }
+
+    let inv = 1.0 / det;
+    let s = sub(origin, tri[0]);
+    let u = dot(s, p) * inv;
+    if !(0.0..=1.0).contains(&u) { return None; }
+
+    let q = cross(s, e1);
+    let v = dot(dir, q) * inv;
+    if v < 0.0 || u + v > 1.0 { return None; }
+
+    let t = dot(e2, q) * inv;
+    if t > EPSILON { Some((t, u, v)) } else { None }
+}
+
+fn closest_hit(origin: [f32; 3], dir: [f32; 3], triangles: &[Triangle]) -> Option<((f32, f32, f32), &Triangle)> {
+    triangles.iter()
+        .filter_map(|tri| intersect(origin, dir, tri).map(|hit| (hit, tri)))
+        .min_by(|a, b| a.0.0.partial_cmp(&b.0.0).unwrap_or(std::cmp::Ordering::Equal))
+}
+
+// Fraction of light that makes it from `origin` along `dir` past every
+// surface, transparent ones only dimming it
+fn transmittance(origin: [f32; 3], dir: [f32; 3], triangles: &[Triangle]) -> f32 {
+    let mut through = 1.0;
+    for tri in triangles {
+        if intersect(origin, dir, tri).is_some() {
+            through *= 1.0 - tri.opacity;
+            if through <= 0.0 { return 0.0; }
+        }
+    }
+    through
+}
+
+fn trace(origin: [f32; 3], dir: [f32; 3], triangles: &[Triangle], scene: &Scene, depth: u32) -> Option<(f32, Radiance)> {
+    let ((t, bu, bv), tri) = closest_hit(origin, dir, triangles)?;
+    let point = add(origin, scale(dir, t));
+    // Triangles are two-sided, light whichever side the ray hit
+    let normal = if dot(tri.normal, dir) > 0.0 { scale(tri.normal, -1.0) } else { tri.normal };
+    let lift = add(point, scale(normal, EPSILON));
+
+    // Direct lighting, each light tested with a shadow ray
+    let mut intensity = 0.0;
+    let mut tint = [0.0f32; 3];
+    for light in &scene.lights {
+        let l = normalize(light.dir);
+        let lambert = dot(normal, l) * transmittance(lift, l, triangles);
+        if lambert <= 0.0 { continue; }
+
+        let strength = light.color[0].max(light.color[1]).max(light.color[2]);
+        intensity += lambert * strength;
+        for (acc, c) in tint.iter_mut().zip(light.color) {
+            *acc += lambert * c;
+        }
+    }
+    let peak = tint[0].max(tint[1]).max(tint[2]);
+    let tint = if peak > 0.0 { tint.map(|c| c / peak) } else { [1.0; 3] };
+    let w = [1.0 - bu - bv, bu, bv];
+    let uv = [0, 1].map(|k| w[0] * tri.uv[0][k] + w[1] * tri.uv[1][k] + w[2] * tri.uv[2][k]);
+    let (base, albedo) = match &tri.texture {
+        Some(tex) => {
+            let texel = tex.sample(uv);
+            (texel.map(|c| c as f32 / 255.0), tri.albedo * luminance(texel))
+        }
+        None => (tri.color, tri.albedo),
+    };
+    let mut local = Radiance {
+        intensity: (intensity * albedo).max(tri.emission),
+        color: [base[0] * tint[0], base[1] * tint[1], base[2] * tint[2]],
+        glyph: tri.art.as_ref().and_then(|art| art.sample(uv)),
+    };
+
+    if tri.reflectivity > 0.0 && depth < MAX_BOUNCES {
+        let reflected_dir = reflect(dir, normal);
+        let reflected = trace(lift, reflected_dir, triangles, scene, depth + 1)
+            .map(|(_, r)| r)
+            .unwrap_or_else(|| match &scene.environment {
+                Some(env) => sky_radiance(env, reflected_dir),
+                None => BACKGROUND,
+            });
+        let r = tri.reflectivity;
+        local.intensity = local.intensity * (1.0 - r) + reflected.intensity * r;
+        for (c, rc) in local.color.iter_mut().zip(reflected.color) {
+            *c = *c * (1.0 - r) + rc * r;
+        }
+        if r > 0.5 { local.glyph = local.glyph.or(reflected.glyph); }
+    }
+
+    if tri.opacity < 1.0 && depth < MAX_BOUNCES {
+        let behind = trace(add(point, scale(dir, EPSILON)), dir, triangles, scene, depth + 1)
+            .map(|(_, r)| r)
+            .unwrap_or(BACKGROUND);
+        let a = tri.opacity;
+        local.intensity = local.intensity * a + behind.intensity * (1.0 - a);
+        for (c, bc) in local.color.iter_mut().zip(behind.color) {
+            *c = *c * a + bc * (1.0 - a);
+        }
+        if a < 0.5 { local.glyph = local.glyph.or(behind.glyph); }
+    }
+
+    Some((t, local))
+}
+
+// Shoot one primary ray per terminal cell
+pub fn render_raytrace(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings) -> FrameBuffer {
+    let mut fb = FrameBuffer::new(proj.width, proj.height);
+    let triangles = collect_triangles(scene, t);
+    let eye = proj.eye();
+    let white = white_point(&scene.lights);
+
+    for y in 0..proj.height as i32 {
+        for x in 0..proj.width as i32 {
+            let dir = normalize(proj.cell_ray(x, y));
+            if let Some((depth, radiance)) = trace(eye, dir, &triangles, scene, 0) {
+                let intensity = settings.shading.intensity(settings.tone.apply(radiance.intensity, white));
+                let mut cell = Cell::shaded(intensity, rgb_color(radiance.color, settings.truecolor));
+                if let Some(c) = radiance.glyph { cell.glyph = Glyph::Fixed(c); }
+                fb.plot(x, y, depth, cell);
+            }
+        }
+    }
+
+    fb
+}
@@ -0,0 +1,805 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+use crate::color::{color_to_rgb, nearest_ansi, rgb_color, tint_color, BACKGROUND_RGB};
+use crate::framebuffer::{Cell, FrameBuffer, Glyph, Ramp};
+use crate::camera::Camera;
+use crate::gizmo::draw_handles;
+use crate::math::{add, dot, get_face_normal, normalize, polygon_uv, reflect, scale, sub};
+use crate::picking::{highlight, hover, Pick};
+use crate::scene::{shadow_light, Light, Scene};
+use crate::post::{apply_bloom, apply_crt, apply_fog, depth_view, fill_backgrounds, paint_background, Crt, Fog};
+use crate::raytrace::render_raytrace;
+use crate::shading::{hatch_stroke, silhouette_edges, Shading, ToneMap, OUTLINE_CHAR};
+use crate::shadow::{ShadowMap, ShadowQuality};
+use crate::subcell::{pack_subcells, SubCell};
+use crate::texture::luminance;
+use crate::wireframe::{render_points, render_wireframe, HiddenLines};
+
+// How the frame is produced
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum RenderMode {
+    // Scanline rasterization of faces and edges
+    Solid,
+    // One ray per cell, with shadows and mirror reflections
+    Raytrace,
+    // Edges only, see `HiddenLines` for those behind faces
+    Wireframe,
+    // Just the vertices
+    Points,
+}
+
+impl RenderMode {
+    pub fn parse(s: &str) -> Option<RenderMode> {
+        RenderMode::from_str(s, false).ok()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            RenderMode::Solid => "solid",
+            RenderMode::Raytrace => "raytrace",
+            RenderMode::Wireframe => "wireframe",
+            RenderMode::Points => "points",
+        }
+    }
+
+    // The mode after this one when cycling at runtime
+    pub fn next(self) -> RenderMode {
+        match self {
+            RenderMode::Solid => RenderMode::Wireframe,
+            RenderMode::Wireframe => RenderMode::Points,
+            RenderMode::Points => RenderMode::Raytrace,
+            RenderMode::Raytrace => RenderMode::Solid,
+        }
+    }
+}
+
+// Everything that changes how a frame looks, independent of the scene
+#[derive(Clone)]
+pub struct RenderSettings {
+    pub mode: RenderMode,
+    pub shadows: ShadowQuality,
+    pub shading: Shading,
+    pub fog: Fog,
+    pub tone: ToneMap,
+    pub ramp: Ramp,
+    // Samples per cell along each axis, 1 disables supersampling
+    pub supersample: usize,
+    // Partial block glyphs along silhouettes, skipped when supersampling or
+    // packing sub-cells
+    pub antialias: bool,
+    // Characters packing several samples per cell, replaces supersampling
+    pub subcell: SubCell,
+    pub hidden_lines: HiddenLines,
+    // Skip faces pointing away from the eye in solid mode
+    pub cull: bool,
+    // Draw the edges of faces over them in solid mode
+    pub edges: bool,
+    // Show the depth buffer instead of the shaded frame
+    pub depth_view: bool,
+    // How much of the previous frame survives into the next, 0.0 disables
+    // motion blur
+    pub motion_blur: f32,
+    pub bloom: bool,
+    pub crt: Crt,
+    // Varies everything drawn at random, the rows the CRT effect jitters.
+    // The same seed draws the same frames
+    pub seed: u32,
+    // Paint shaded surfaces as background-colored blocks instead of ramp
+    // characters
+    pub solid_fill: bool,
+    // Painted where nothing is drawn and faded towards by fog, `None` keeps
+    // the terminal's own
+    pub background: Option<[u8; 3]>,
+    // Face picked with the mouse, drawn brighter
+    pub highlight: Option<Pick>,
+    // Face under the mouse pointer, brightened a little
+    pub hover: Option<Pick>,
+    // Object whose transform handles are drawn
+    pub gizmo: Option<usize>,
+    pub truecolor: bool,
+}
+
+impl Default for RenderSettings {
+    fn default() -> RenderSettings {
+        RenderSettings {
+            mode: RenderMode::Solid,
+            shadows: ShadowQuality::Ground,
+            shading: Shading::Smooth,
+            fog: Fog::Off,
+            tone: ToneMap::default(),
+            ramp: Ramp::default(),
+            supersample: 1,
+            antialias: false,
+            subcell: SubCell::Off,
+            hidden_lines: HiddenLines::Faint,
+            cull: true,
+            edges: true,
+            depth_view: false,
+            motion_blur: 0.0,
+            bloom: false,
+            crt: Crt::Off,
+            seed: 0,
+            solid_fill: false,
+            background: None,
+            highlight: None,
+            hover: None,
+            gizmo: None,
+            truecolor: false,
+        }
+    }
+}
+
+pub const FOV: f32 = 40.0;
+pub const DISTANCE: f32 = 5.0;
+// Height of a terminal cell over its width on most fonts
+pub const DEFAULT_ASPECT: f32 = 2.0;
+// Viewport height the view is framed for when letterboxed. The picture
+// scales with the viewport from there, so it looks the same at any size
+const LETTERBOX_ROWS: f32 = 23.0;
+
+// Perspective view through `camera`, by default at (0, 0, -DISTANCE)
+// looking down +z
+#[derive(Clone, Copy)]
+pub struct Projection {
+    pub width: usize,
+    pub height: usize,
+    pub fov: f32,
+    pub camera: Camera,
+    // Horizontal stretch making up for grid cells that are taller than wide
+    pub aspect: f32,
+}
+
+impl Projection {
+    // `aspect` is the height of a cell over its width
+    pub fn new(width: usize, height: usize, aspect: f32) -> Projection {
+        Projection { width, height, fov: FOV, camera: Camera::orbit(DISTANCE), aspect }
+    }
+
+    pub fn eye(&self) -> [f32; 3] {
+        self.camera.position
+    }
+
+    pub fn project(&self, point: [f32; 3]) -> Option<(i32, i32, f32)> {
+        self.project_f(point).map(|(x, y, z)| (x as i32, y as i32, z))
+    }
+
+    // Projection to fractional cell coordinates
+    pub fn project_f(&self, point: [f32; 3]) -> Option<(f32, f32, f32)> {
+        let view = self.camera.view_space(point);
+        let z = view[2];
+        if z <= 0.1 { return None; }
+
+        let factor = self.fov / z;
+        let x = (view[0] * factor * self.aspect) + (self.width as f32 / 2.0);
+        let y = (-view[1] * factor) + (self.height as f32 / 2.0);
+
+        Some((x, y, z))
+    }
+
+    // The largest region of a `width` x `height` cell area that is `ratio`
+    // times as wide as tall on screen, centered, with the view scaled to it.
+    // Also returns the region's top-left cell
+    pub fn letterboxed(width: usize, height: usize, aspect: f32, ratio: f32) -> (Projection, (usize, usize)) {
+        let (w, h) = if width as f32 > height as f32 * aspect * ratio {
+            ((height as f32 * aspect * ratio).round() as usize, height)
+        } else {
+            (width, (width as f32 / (aspect * ratio)).round() as usize)
+        };
+        let (w, h) = (w.clamp(1, width.max(1)), h.clamp(1, height.max(1)));
+        let proj = Projection { fov: FOV * h as f32 / LETTERBOX_ROWS, ..Projection::new(w, h, aspect) };
+        (proj, (width.saturating_sub(w) / 2, height.saturating_sub(h) / 2))
+    }
+
+    // The same view with every cell split into a `cols` x `rows` grid
+    pub fn subdivided(&self, cols: usize, rows: usize) -> Projection {
+        Projection {
+            width: self.width * cols,
+            height: self.height * rows,
+            fov: self.fov * rows as f32,
+            aspect: self.aspect * cols as f32 / rows as f32,
+            ..*self
+        }
+    }
+
+    // Direction towards the point of the hemisphere facing the eye that shows
+    // at fractional cell (x, y), for placing a light with the pointer. The
+    // hemisphere's rim touches the view's shorter sides and points past it
+    // are on the rim
+    pub fn hemisphere_dir(&self, x: f32, y: f32) -> [f32; 3] {
+        // In rows, as cells are `aspect` times as tall as wide
+        let radius = (self.width as f32 / self.aspect).min(self.height as f32) / 2.0;
+        let dx = (x - self.width as f32 / 2.0) / self.aspect / radius;
+        let dy = (self.height as f32 / 2.0 - y) / radius;
+        let out = dx.hypot(dy).max(1.0);
+        let (dx, dy) = (dx / out, dy / out);
+        self.camera.world_dir([dx, dy, -(1.0 - dx * dx - dy * dy).max(0.0).sqrt()])
+    }
+
+    // World-space direction through the center of a cell, scaled so its
+    // depth along the view is 1
+    pub fn cell_ray(&self, x: i32, y: i32) -> [f32; 3] {
+        let sx = x as f32 + 0.5 - self.width as f32 / 2.0;
+        let sy = y as f32 + 0.5 - self.height as f32 / 2.0;
+        self.camera.world_dir([sx / (self.fov * self.aspect), -sy / self.fov, 1.0])
+    }
+}
+
+// Draw a line with depth testing. `glyph` picks the character for each cell
+pub fn draw_line(fb: &mut FrameBuffer, p0: (i32, i32, f32), p1: (i32, i32, f32), bias: f32,
+                 mut glyph: impl FnMut(i32, i32) -> char, color: Color) {
+    for_each_line_cell(p0, p1, |x, y, z| fb.plot(x, y, z + bias, Cell::glyph(glyph(x, y), color)));
+}
+
+// Bresenham's line algorithm, with depth interpolated perspective-correctly
+// between the two endpoints
+pub fn for_each_line_cell(p0: (i32, i32, f32), p1: (i32, i32, f32), mut plot: impl FnMut(i32, i32, f32)) {
+    let (x0, y0, x1, y1) = (p0.0, p0.1, p1.0, p1.1);
+    let dx = (x1 - x0).abs();
+    let dy = -(y1 - y0).abs();
+    let sx = if x0 < x1 { 1 } else { -1 };
+    let sy = if y0 < y1 { 1 } else { -1 };
+    let steps = dx.max(-dy).max(1) as f32;
+    let mut err = dx + dy;
+    let mut x = x0;
+    let mut y = y0;
+    let mut step = 0;
+
+    loop {
+        let t = step as f32 / steps;
+        let z = 1.0 / ((1.0 - t) / p0.2 + t / p1.2);
+        plot(x, y, z);
+
+        if x == x1 && y == y1 { break; }
+
+        let e2 = 2 * err;
+        if e2 >= dy {
+            if x == x1 { break; }
+            err += dy;
+            x += sx;
+        }
+        if e2 <= dx {
+            if y == y1 { break; }
+            err += dx;
+            y += sy;
+        }
+        step += 1;
+    }
+}
+
+// Fill a planar convex polygon. Each covered cell gets its true depth by
+// intersecting the cell's view ray with the polygon's plane, and `shade` is
+// asked for the character and color at that world-space point.
+pub fn fill_face(fb: &mut FrameBuffer, proj: &Projection, vertices: &[[f32; 3]],
+                 mut shade: impl FnMut([f32; 3]) -> Cell) {
+    let (width, height) = (fb.width, fb.height);
+    raster_face(proj, vertices, width, height, |x, y, z, world| {
+        fb.plot(x, y, z, shade(world));
+    });
+}
+
+// Like fill_face, but composites the polygon over what is already in the
+// buffer with the given opacity.
+pub fn fill_face_blended(fb: &mut FrameBuffer, proj: &Projection, vertices: &[[f32; 3]], alpha: f32,
+                         truecolor: bool, mut shade: impl FnMut([f32; 3]) -> Cell) {
+    let (width, height) = (fb.width, fb.height);
+    raster_face(proj, vertices, width, height, |x, y, z, world| {
+        fb.blend(x, y, z, shade(world), alpha, truecolor);
+    });
+}
+
+// Like fill_face, but coverage is sampled at the four quadrant centers of
+// each cell. Cells the face only partly covers end up as the matching
+// quadrant block glyph, unless other faces fill in the rest.
+pub fn fill_face_aa(fb: &mut FrameBuffer, proj: &Projection, vertices: &[[f32; 3]],
+                    mut shade: impl FnMut([f32; 3]) -> Cell) {
+    let projected: Vec<(f32, f32)> = vertices.iter()
+        .filter_map(|v| proj.project_f(*v))
+        .map(|(x, y, _)| (x, y))
+        .collect();
+    if projected.len() < 3 || projected.len() < vertices.len() {
+        fill_face(fb, proj, vertices, shade);
+        return;
+    }
+
+    let normal = get_face_normal(vertices[0], vertices[1], vertices[2]);
+    let plane_d = dot(normal, sub(vertices[0], proj.eye()));
+
+    let min_x = projected.iter().map(|p| p.0).fold(f32::MAX, f32::min).floor().max(0.0) as i32;
+    let max_x = projected.iter().map(|p| p.0).fold(f32::MIN, f32::max).ceil().min(fb.width as f32 - 1.0) as i32;
+    let min_y = projected.iter().map(|p| p.1).fold(f32::MAX, f32::min).floor().max(0.0) as i32;
+    let max_y = projected.iter().map(|p| p.1).fold(f32::MIN, f32::max).ceil().min(fb.height as f32 - 1.0) as i32;
+
+    // Quadrant sample offsets in QUADRANT_CHARS bit order
+    const SAMPLES: [(f32, f32); 4] = [(0.25, 0.25), (0.75, 0.25), (0.25, 0.75), (0.75, 0.75)];
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let mask = SAMPLES.iter().enumerate()
+                .filter(|(_, (dx, dy))| point_in_polygon(x as f32 + dx, y as f32 + dy, &projected))
+                .fold(0u8, |m, (bit, _)| m | (1 << bit));
+            if mask == 0 { continue; }
+
+            let ray = proj.cell_ray(x, y);
+            let denom = dot(normal, ray);
+            if denom.abs() < 1e-6 { continue; }
+            let z = plane_d / denom;
+            let cell = shade(add(proj.eye(), scale(ray, z)));
+            if mask == 0b1111 {
+                fb.plot(x, y, z, cell);
+            } else {
+                fb.plot_partial(x, y, z, cell, mask);
+            }
+        }
+    }
+}
+
+// Convex point-in-polygon test on fractional coordinates, either winding
+fn point_in_polygon(px: f32, py: f32, vertices: &[(f32, f32)]) -> bool {
+    let n = vertices.len();
+    let side = |i: usize| {
+        let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+        (b.0 - a.0) * (py - a.1) - (b.1 - a.1) * (px - a.0)
+    };
+    (0..n).all(|i| side(i) >= 0.0) || (0..n).all(|i| side(i) <= 0.0)
+}
+
+// Visit every covered cell of a face with its depth and world-space position
+fn raster_face(proj: &Projection, vertices: &[[f32; 3]], width: usize, height: usize,
+               mut f: impl FnMut(i32, i32, f32, [f32; 3])) {
+    let projected: Vec<(i32, i32, f32)> = vertices.iter().filter_map(|v| proj.project(*v)).collect();
+    if projected.len() < 3 || vertices.len() < 3 { return; }
+
+    let normal = get_face_normal(vertices[0], vertices[1], vertices[2]);
+    let anchor = sub(vertices[0], proj.eye());
+    let plane_d = dot(normal, anchor);
+    let avg_z: f32 = projected.iter().map(|p| p.2).sum::<f32>() / projected.len() as f32;
+
+    for_each_covered_cell(&projected, width, height, |x, y| {
+        let ray = proj.cell_ray(x, y);
+        let denom = dot(normal, ray);
+        let z = if denom.abs() > 1e-6 { plane_d / denom } else { avg_z };
+        let world = add(proj.eye(), scale(ray, z));
+        f(x, y, z, world);
+    });
+}
+
+// Visit every on-screen cell inside an already projected convex polygon
+fn for_each_covered_cell(projected: &[(i32, i32, f32)], width: usize, height: usize, mut f: impl FnMut(i32, i32)) {
+    if projected.len() < 3 { return; }
+
+    // Get bounding box, clipped to the screen
+    let min_x = projected.iter().map(|p| p.0).min().unwrap_or(0).max(0);
+    let max_x = projected.iter().map(|p| p.0).max().unwrap_or(0).min(width as i32 - 1);
+    let min_y = projected.iter().map(|p| p.1).min().unwrap_or(0).max(0);
+    let max_y = projected.iter().map(|p| p.1).max().unwrap_or(0).min(height as i32 - 1);
+
+    // Simple point-in-polygon for convex shapes
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            if point_in_quad(x, y, projected) {
+                f(x, y);
+            }
+        }
+    }
+}
+
+fn point_in_quad(px: i32, py: i32, vertices: &[(i32, i32, f32)]) -> bool {
+    if vertices.len() < 3 { return false; }
+
+    let mut inside = true;
+    let n = vertices.len();
+
+    for i in 0..n {
+        let j = (i + 1) % n;
+        let edge_x = vertices[j].0 - vertices[i].0;
+        let edge_y = vertices[j].1 - vertices[i].1;
+        let point_x = px - vertices[i].0;
+        let point_y = py - vertices[i].1;
+
+        let cross = edge_x * point_y - edge_y * point_x;
+        if cross < 0 {
+            inside = false;
+            break;
+        }
+    }
+
+    if inside { return true; }
+
+    // Try other winding
+    inside = true;
+    for i in 0..n {
+        let j = (i + 1) % n;
+        let edge_x = vertices[j].0 - vertices[i].0;
+        let edge_y = vertices[j].1 - vertices[i].1;
+        let point_x = px - vertices[i].0;
+        let point_y = py - vertices[i].1;
+
+        let cross = edge_x * point_y - edge_y * point_x;
+        if cross > 0 {
+            inside = false;
+            break;
+        }
+    }
+
+    inside
+}
+
+// Sum the lambert contribution of every light. Returns the scalar intensity
+// used for the shade ramp, which `ToneMap` brings back under 1.0, and the
+// normalized light tint (max component 1.0).
+pub fn accumulate_lights(normal: [f32; 3], lights: &[Light]) -> (f32, [f32; 3]) {
+    let normal = normalize(normal);
+    let mut intensity = 0.0;
+    let mut rgb = [0.0f32; 3];
+    for light in lights {
+        let dot = dot(normal, normalize(light.dir)).max(0.0);
+        let strength = light.color[0].max(light.color[1]).max(light.color[2]);
+        intensity += dot * strength;
+        for (acc, c) in rgb.iter_mut().zip(light.color) {
+            *acc += dot * c;
+        }
+    }
+
+    let peak = rgb[0].max(rgb[1]).max(rgb[2]);
+    let tint = if peak > 0.0 { [rgb[0] / peak, rgb[1] / peak, rgb[2] / peak] } else { [1.0, 1.0, 1.0] };
+    (intensity, tint)
+}
+
+// Intensity of a surface facing every light at once, the most they can add
+// up to
+pub fn white_point(lights: &[Light]) -> f32 {
+    lights.iter().map(|l| l.color[0].max(l.color[1]).max(l.color[2])).sum()
+}
+
+// Ground plane below the objects, in world units
+pub const GROUND_Y: f32 = -1.8;
+pub const GROUND_HALF_SIZE: f32 = 3.5;
+pub const GROUND_COLOR: Color = Color::DarkGrey;
+// How bright the ground is, and the share of that left in shadow, where only
+// the ambient light reaches
+const GROUND_INTENSITY: f32 = 0.5;
+const SHADOW_AMBIENT: f32 = 0.5;
+
+// Ground corners, wound so the normal points up
+pub fn ground_corners() -> [[f32; 3]; 4] {
+    let h = GROUND_HALF_SIZE;
+    [[-h, GROUND_Y, -h], [-h, GROUND_Y, h], [h, GROUND_Y, h], [h, GROUND_Y, -h]]
+}
+
+// Convex hull of 2D points (monotone chain), counter-clockwise
+fn convex_hull(mut points: Vec<[f32; 2]>) -> Vec<[f32; 2]> {
+    points.sort_by(|a, b| a[0].partial_cmp(&b[0]).unwrap_or(std::cmp::Ordering::Equal)
+        .then(a[1].partial_cmp(&b[1]).unwrap_or(std::cmp::Ordering::Equal)));
+    if points.len() < 3 { return points; }
+
+    let cross = |o: [f32; 2], a: [f32; 2], b: [f32; 2]| (a[0] - o[0]) * (b[1] - o[1]) - (a[1] - o[1]) * (b[0] - o[0]);
+    let mut lower: Vec<[f32; 2]> = Vec::new();
+    for p in &points {
+        while lower.len() >= 2 && cross(lower[lower.len() - 2], lower[lower.len() - 1], *p) <= 0.0 {
+            lower.pop();
+        }
+        lower.push(*p);
+    }
+    let mut upper: Vec<[f32; 2]> = Vec::new();
+    for p in points.iter().rev() {
+        while upper.len() >= 2 && cross(upper[upper.len() - 2], upper[upper.len() - 1], *p) <= 0.0 {
+            upper.pop();
+        }
+        upper.push(*p);
+    }
+
+    lower.pop();
+    upper.pop();
+    lower.extend(upper);
+    lower
+}
+
+// Draw the ground plane. With a shadow map every ground cell is looked up in
+// it; with `ShadowQuality::Ground` each object's silhouette is projected onto
+// the plane along the shadow light instead.
+fn draw_ground(fb: &mut FrameBuffer, proj: &Projection, objects: &[Vec<[f32; 3]>], light: Option<&Light>,
+               quality: ShadowQuality, shadow_map: Option<&ShadowMap>) {
+    let h = GROUND_HALF_SIZE;
+    let up = [0.0, 1.0, 0.0];
+    let lit = Cell::shaded(GROUND_INTENSITY, GROUND_COLOR);
+    let shadowed = Cell::shaded(GROUND_INTENSITY * SHADOW_AMBIENT, GROUND_COLOR);
+    fill_face(fb, proj, &ground_corners(), |p| match shadow_map {
+        Some(map) if map.occluded(p, up) => shadowed,
+        _ => lit,
+    });
+
+    let Some(light) = light else { return; };
+    if quality != ShadowQuality::Ground { return; }
+
+    // Slide every vertex along the light direction until it hits the plane,
+    // slightly above the ground so the shadow wins the depth test
+    let d = light.dir;
+    let shadow_y = GROUND_Y + 0.01;
+    for vertices in objects {
+        let footprint: Vec<[f32; 2]> = vertices.iter()
+            .map(|p| {
+                let t = (p[1] - shadow_y) / d[1];
+                [p[0] - d[0] * t, p[2] - d[2] * t]
+            })
+            .collect();
+
+        let hull: Vec<[f32; 3]> = convex_hull(footprint).iter()
+            .map(|p| [p[0].clamp(-h, h), shadow_y, p[1].clamp(-h, h)])
+            .collect();
+        fill_face(fb, proj, &hull, |_| shadowed);
+    }
+}
+
+// Render one frame with whichever mode is selected, post-processing included
+pub fn render_frame(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings) -> FrameBuffer {
+    let n = settings.supersample.max(1);
+    let subcell = settings.subcell.grid();
+    let (cols, rows) = subcell.unwrap_or((n, n));
+    let target = proj.subdivided(cols, rows);
+    let fog_color = settings.background.unwrap_or(BACKGROUND_RGB);
+    let mut fb = match settings.mode {
+        RenderMode::Solid => render_scene(scene, &target, t, settings),
+        RenderMode::Raytrace => render_raytrace(scene, &target, t, settings),
+        RenderMode::Wireframe => render_wireframe(scene, &target, t, settings),
+        RenderMode::Points => render_points(scene, &target, t),
+    };
+    if subcell.is_some() {
+        // Packed cells are fixed glyphs, so depth and fog work on the samples
+        if settings.depth_view {
+            depth_view(&mut fb, settings.truecolor);
+        } else {
+            apply_fog(&mut fb, settings.fog, fog_color, settings.truecolor);
+        }
+        fb = pack_subcells(&fb, settings.subcell, settings.truecolor);
+        if settings.depth_view { return fb; }
+    } else {
+        if n > 1 {
+            fb = fb.downsample(n, settings.truecolor);
+        }
+        if settings.depth_view {
+            depth_view(&mut fb, settings.truecolor);
+            return fb;
+        }
+        apply_fog(&mut fb, settings.fog, fog_color, settings.truecolor);
+    }
+    if settings.bloom {
+        apply_bloom(&mut fb, settings.truecolor);
+    }
+    apply_crt(&mut fb, settings.crt, t, settings.seed, settings.truecolor);
+    if settings.solid_fill {
+        fill_backgrounds(&mut fb, settings.truecolor);
+    }
+    if let Some(pick) = settings.hover.filter(|pick| settings.highlight != Some(*pick)) {
+        hover(&mut fb, scene, proj, t, pick);
+    }
+    if let Some(pick) = settings.highlight {
+        highlight(&mut fb, scene, proj, t, pick);
+    }
+    if let Some(object) = settings.gizmo.and_then(|i| scene.objects.get(i)) {
+        draw_handles(&mut fb, object, proj);
+    }
+    if let Some(background) = settings.background {
+        paint_background(&mut fb, background, settings.truecolor);
+    }
+    fb
+}
+
+pub fn render_scene(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings) -> FrameBuffer {
+    let (shadows, truecolor) = (settings.shadows, settings.truecolor);
+    let mut fb = FrameBuffer::new(proj.width, proj.height);
+
+    // Transform vertices
+    let transformed: Vec<Vec<[f32; 3]>> = scene.objects.iter().map(|o| o.transformed(t)).collect();
+
+    let shadow_idx = if shadows == ShadowQuality::Off { None } else { shadow_light(&scene.lights) };
+    let light = shadow_idx.map(|i| &scene.lights[i]);
+    let unshadowed: Vec<Light> = scene.lights.iter()
+        .enumerate()
+        .filter(|(i, _)| Some(*i) != shadow_idx)
+        .map(|(_, l)| *l)
+        .collect();
+
+    // Transparent objects neither cast shadows nor hide what is behind them
+    let opaque: Vec<Vec<[f32; 3]>> = scene.objects.iter().zip(&transformed)
+        .filter(|(o, _)| o.material.opacity >= 1.0)
+        .map(|(_, verts)| verts.clone())
+        .collect();
+
+    let shadow_map = match (shadows, light) {
+        (ShadowQuality::Map, Some(light)) => {
+            let polygons: Vec<Vec<[f32; 3]>> = scene.objects.iter().zip(&transformed)
+                .filter(|(o, _)| o.material.opacity >= 1.0)
+                .flat_map(|(o, verts)| o.mesh.faces.iter().map(move |f| f.indices.iter().map(|i| verts[*i]).collect()))
+                .collect();
+            Some(ShadowMap::build(light.dir, &polygons))
+        }
+        _ => None,
+    };
+
+    draw_ground(&mut fb, proj, &opaque, light, shadows, shadow_map.as_ref());
+
+    // Opaque geometry first, then transparent faces back to front
+    let mut translucent: Vec<TranslucentFace> = Vec::new();
+    for (object, verts) in scene.objects.iter().zip(&transformed) {
+        let alpha = object.material.opacity;
+        let white = white_point(&scene.lights);
+        // Render faces
+        for face in &object.mesh.faces {
+            let face_verts: Vec<[f32; 3]> = face.indices.iter().map(|i| verts[*i]).collect();
+            let normal = get_face_normal(face_verts[0], face_verts[1], face_verts[2]);
+
+            // Backface culling - skip faces pointing away from the eye,
+            // unless the far side can be seen through the object
+            if settings.cull && alpha >= 1.0 && dot(normal, sub(proj.eye(), face_verts[0])) <= 0.0 { continue; }
+
+            // Calculate shading based on normal, with and without the shadow light
+            let (intensity, tint) = accumulate_lights(normal, &scene.lights);
+            let (dark_intensity, dark_tint) = accumulate_lights(normal, &unshadowed);
+            let emission = object.material.emission;
+            let (intensity, dark_intensity) = (intensity.max(emission), dark_intensity.max(emission));
+            let [intensity, dark_intensity] = [intensity, dark_intensity]
+                .map(|i| settings.shading.intensity(settings.tone.apply(i, white)));
+            let glyph = match settings.shading {
+                Shading::Hatch => Glyph::Hatch(hatch_stroke(normal)),
+                _ => Glyph::Ramp,
+            };
+            let lit = Cell { glyph, intensity, color: if truecolor { tint_color(face.color, tint) } else { face.color }, background: None };
+            let dark = Cell { glyph, intensity: dark_intensity, color: if truecolor { tint_color(face.color, dark_tint) } else { face.color }, background: None };
+
+            if alpha < 1.0 {
+                let depth = view_depth(&proj.camera, &face_verts);
+                translucent.push(TranslucentFace { depth, vertices: face_verts, lit, dark, alpha });
+                continue;
+            }
+
+            let unit_normal = normalize(normal);
+            let texture = object.material.texture.as_deref().filter(|_| face.uv.len() == face.indices.len());
+            let art = face.art.as_deref().filter(|_| face.uv.len() == face.indices.len());
+            let mirror = scene.environment.as_ref().filter(|_| object.material.reflectivity > 0.0);
+            let shade = |p| {
+                let (base, tint) = match &shadow_map {
+                    Some(map) if map.occluded(p, unit_normal) => (dark, dark_tint),
+                    _ => (lit, tint),
+                };
+                if texture.is_none() && art.is_none() && mirror.is_none() { return base; }
+                let uv = polygon_uv(p, &face_verts, &face.uv);
+                let mut cell = match texture {
+                    Some(tex) => textured(base, tex.sample(uv), tint, truecolor),
+                    None => base,
+                };
+                if let Some(env) = mirror {
+                    let sky = env.sample(reflect(normalize(sub(p, proj.eye())), unit_normal));
+                    cell = mirrored(cell, sky, object.material.reflectivity, truecolor);
+                }
+                match art.and_then(|art| art.sample(uv)) {
+                    Some(c) => Cell { glyph: Glyph::Fixed(c), ..cell },
+                    None => cell,
+                }
+            };
+            if settings.antialias && settings.supersample <= 1 && settings.subcell == SubCell::Off {
+                fill_face_aa(&mut fb, proj, &face_verts, shade);
+            } else {
+                fill_face(&mut fb, proj, &face_verts, shade);
+            }
+        }
+
+        // Draw edges on top; toon shading only outlines the silhouette
+        if alpha >= 1.0 && settings.edges {
+            if settings.shading == Shading::Toon {
+                let outline = silhouette_edges(&object.mesh.faces, verts, proj.eye());
+                draw_edges(&mut fb, proj, &outline, verts, Some(OUTLINE_CHAR));
+            } else {
+                draw_edges(&mut fb, proj, &object.mesh.edges, verts, None);
+            }
+        }
+    }
+
+    translucent.sort_by(|a, b| b.depth.partial_cmp(&a.depth).unwrap_or(std::cmp::Ordering::Equal));
+    for face in &translucent {
+        let v = &face.vertices;
+        let unit_normal = normalize(get_face_normal(v[0], v[1], v[2]));
+        fill_face_blended(&mut fb, proj, v, face.alpha, truecolor, |p| match &shadow_map {
+            Some(map) if map.occluded(p, unit_normal) => face.dark,
+            _ => face.lit,
+        });
+    }
+
+    // Edges of transparent objects stay visible through their own faces
+    for (object, verts) in scene.objects.iter().zip(&transformed) {
+        if object.material.opacity < 1.0 && settings.edges {
+            draw_edges(&mut fb, proj, &object.mesh.edges, verts, None);
+        }
+    }
+
+    fb.resolve_partial();
+    fb
+}
+
+// Apply a texel to a shaded cell: its luminance scales the intensity and its
+// color replaces the face color
+pub fn textured(cell: Cell, texel: [u8; 3], tint: [f32; 3], truecolor: bool) -> Cell {
+    let [r, g, b] = texel;
+    let color = if truecolor { tint_color(Color::Rgb { r, g, b }, tint) } else { nearest_ansi(texel) };
+    Cell { intensity: cell.intensity * luminance(texel), color, ..cell }
+}
+
+// Mix the environment seen in a mirror direction into a shaded cell, `sky`
+// being linear RGB
+pub fn mirrored(cell: Cell, sky: [f32; 3], reflectivity: f32, truecolor: bool) -> Cell {
+    let r = reflectivity;
+    let base = color_to_rgb(cell.color).map(|c| c as f32 / 255.0);
+    let color = [0, 1, 2].map(|i| base[i] * (1.0 - r) + sky[i] * r);
+    let sky_intensity = sky[0].max(sky[1]).max(sky[2]);
+    Cell { intensity: cell.intensity * (1.0 - r) + sky_intensity * r, color: rgb_color(color, truecolor), ..cell }
+}
+
+// How far in front of `camera` the middle of a face is, for drawing
+// transparent faces back to front from wherever it looks
+fn view_depth(camera: &Camera, verts: &[[f32; 3]]) -> f32 {
+    verts.iter().map(|v| camera.view_space(*v)[2]).sum::<f32>() / verts.len() as f32
+}
+
+// A transparent face waiting to be composited once all opaque geometry is in
+struct TranslucentFace {
+    depth: f32,
+    vertices: Vec<[f32; 3]>,
+    lit: Cell,
+    dark: Cell,
+    alpha: f32,
+}
+
+// Draw mesh edges with `c`, or with slope glyphs when it is `None`
+fn draw_edges(fb: &mut FrameBuffer, proj: &Projection, edges: &[(usize, usize)], verts: &[[f32; 3]], c: Option<char>) {
+    for (i, j) in edges {
+        if let (Some(p1), Some(p2)) = (proj.project_f(verts[*i]), proj.project_f(verts[*j])) {
+            let (a, b) = ((p1.0 as i32, p1.1 as i32, p1.2), (p2.0 as i32, p2.1 as i32, p2.2));
+            match c {
+                Some(c) => draw_line(fb, a, b, -0.1, |_, _| c, Color::White),
+                None => draw_line(fb, a, b, -0.1, |x, y| slope_char(p1, p2, x, y, proj.aspect), Color::White),
+            }
+        }
+    }
+}
+
+// Character following the on-screen direction of the line from `p0` to `p1`
+// (fractional cell coordinates). Cells are `aspect` times as tall as wide,
+// so vertical steps count that much more. Shallow lines switch between '-'
+// and '_' depending on where they cross the cell, which smooths their
+// staircase
+pub fn slope_char(p0: (f32, f32, f32), p1: (f32, f32, f32), x: i32, y: i32, aspect: f32) -> char {
+    let (dx, dy) = (p1.0 - p0.0, p1.1 - p0.1);
+    let angle = (-aspect * dy).atan2(dx).to_degrees().rem_euclid(180.0);
+    match angle {
+        a if !(22.5..157.5).contains(&a) => {
+            let cy = p0.1 + (x as f32 + 0.5 - p0.0) * dy / dx;
+            if cy - y as f32 > 0.66 { '_' } else { '-' }
+        }
+        a if a < 67.5 => '/',
+        a if a < 112.5 => '|',
+        _ => '\\',
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn letterbox_in_an_empty_area() {
+        let (proj, origin) = Projection::letterboxed(0, 0, DEFAULT_ASPECT, 16.0 / 9.0);
+        assert_eq!((proj.width, proj.height), (1, 1));
+        assert_eq!(origin, (0, 0));
+        let (_, origin) = Projection::letterboxed(80, 0, DEFAULT_ASPECT, 16.0 / 9.0);
+        assert_eq!(origin.1, 0);
+    }
+
+    #[test]
+    fn translucent_depth_follows_the_camera() {
+        // Looking down -x from +x, where world z says nothing about depth
+        let camera = Camera { position: [5.0, 0.0, 0.0], yaw: -std::f32::consts::FRAC_PI_2, pitch: 0.0 };
+        let near = [[2.0, 1.0, 0.0], [2.0, -1.0, 1.0], [2.0, -1.0, -1.0]];
+        let far = [[-2.0, 1.0, 0.0], [-2.0, -1.0, 1.0], [-2.0, -1.0, -1.0]];
+        assert!(view_depth(&camera, &near) < view_depth(&camera, &far));
+        assert!((view_depth(&camera, &near) - 3.0).abs() < 1e-4);
+    }
+}
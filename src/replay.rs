@@ -0,0 +1,93 @@
+// Frames recorded as the text and escape sequences that draw them, with
+// when each is shown, for `record --format ansi` and `play`. Other programs
+// can show them too: the file is a header line and then each frame as a
+// line with its time in seconds and its length in bytes, followed by that
+// many bytes drawing it from the top left corner
+//
+//   teruminator-ansi 80 24
+//   frame 0.000000 1934
+//   ...the frame...
+//   frame 0.033333 1911
+//   ...
+
+use std::io::{stdout, Write};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::execute;
+use crossterm::terminal::{Clear, ClearType};
+
+use crate::terminal::quit_requested;
+
+const MAGIC: &str = "teruminator-ansi";
+
+pub fn write_header(out: &mut impl Write, width: usize, height: usize) -> std::io::Result<()> {
+    writeln!(out, "{MAGIC} {width} {height}")
+}
+
+// `frame` to be shown `t` seconds in, its lines ending in \r\n but the last
+pub fn write_frame(out: &mut impl Write, t: f32, frame: &[u8]) -> std::io::Result<()> {
+    writeln!(out, "frame {t:.6} {}", frame.len())?;
+    out.write_all(frame)?;
+    writeln!(out)
+}
+
+struct Recorded {
+    height: usize,
+    frames: Vec<(f32, Vec<u8>)>,
+}
+
+fn read(bytes: &[u8]) -> Result<Recorded, String> {
+    let (header, mut rest) = split_line(bytes).unwrap_or_default();
+    let height = match header.split(' ').collect::<Vec<_>>().as_slice() {
+        [MAGIC, width, height] if width.parse::<usize>().is_ok() => height.parse().ok(),
+        _ => None,
+    };
+    let height = height.ok_or("not a recording made with record --format ansi")?;
+    let mut frames = Vec::new();
+    while !rest.is_empty() {
+        let at = |e: &str| format!("frame {}: {e}", frames.len() + 1);
+        let (heading, after) = split_line(rest).ok_or_else(|| at("cut short"))?;
+        let (t, length) = match heading.split(' ').collect::<Vec<_>>().as_slice() {
+            ["frame", t, length] => (t.parse::<f32>().ok().filter(|t| t.is_finite()), length.parse::<usize>().ok()),
+            _ => (None, None),
+        };
+        let (Some(t), Some(length)) = (t, length) else { return Err(at("expected frame TIME LENGTH")); };
+        let frame = after.get(..length).ok_or_else(|| at("cut short"))?;
+        frames.push((t, frame.to_vec()));
+        // Past the frame and the newline after it
+        rest = after.get(length + 1..).unwrap_or_default();
+    }
+    Ok(Recorded { height, frames })
+}
+
+// The first line of `bytes` and what comes after it
+fn split_line(bytes: &[u8]) -> Option<(String, &[u8])> {
+    let end = bytes.iter().position(|&b| b == b'\n')?;
+    Some((String::from_utf8_lossy(&bytes[..end]).into_owned(), &bytes[end + 1..]))
+}
+
+// Show the recording at `path` on the terminal at the pace it was made,
+// until it ends or Ctrl-C
+pub fn play(path: &Path) -> Result<(), String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let recorded = read(&bytes).map_err(|e| format!("{}: {e}", path.display()))?;
+    let mut out = stdout().lock();
+    let played = (|| -> std::io::Result<()> {
+        execute!(out, Hide, Clear(ClearType::All))?;
+        let started = Instant::now();
+        for (t, frame) in &recorded.frames {
+            if quit_requested() { break; }
+            let due = Duration::from_secs_f32(t.max(0.0));
+            std::thread::sleep(due.saturating_sub(started.elapsed()));
+            execute!(out, MoveTo(0, 0))?;
+            out.write_all(frame)?;
+            out.flush()?;
+        }
+        Ok(())
+    })();
+    // Under the last frame, with the cursor back
+    let restored = write!(out, "\x1b[{};1H\r\n", recorded.height).and_then(|_| execute!(out, Show));
+    played.and(restored).map_err(|e| e.to_string())
+}
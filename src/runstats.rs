@@ -0,0 +1,122 @@
+// Statistics about a whole run that --stats-json writes when it ends, for
+// scripts keeping track of how fast the renderer is. The view, render,
+// record and bench all keep them
+
+#[cfg(unix)]
+use std::fs::File;
+use std::io::Write;
+#[cfg(unix)]
+use std::mem::ManuallyDrop;
+#[cfg(unix)]
+use std::os::fd::{FromRawFd, RawFd};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+// Passes everything on to `inner`, counting the bytes
+pub struct Counted<W> {
+    inner: W,
+    pub bytes: u64,
+}
+
+impl<W> Counted<W> {
+    pub fn new(inner: W) -> Counted<W> {
+        Counted { inner, bytes: 0 }
+    }
+}
+
+impl<W: Write> Write for Counted<W> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.bytes += written as u64;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub struct RunStats {
+    started: Instant,
+    // Milliseconds each frame took, without waiting for the next one
+    frame_times: Vec<f32>,
+    // Terminal cells drawn, only the changed ones of text frames
+    cells: u64,
+    // Bytes written to the terminal or the files saved
+    bytes: u64,
+}
+
+impl RunStats {
+    pub fn new() -> RunStats {
+        RunStats { started: Instant::now(), frame_times: Vec::new(), cells: 0, bytes: 0 }
+    }
+
+    pub fn frame_time(&mut self, time: Duration) {
+        self.frame_times.push(time.as_secs_f32() * 1000.0);
+    }
+
+    pub fn frame_cells(&mut self, cells: usize) {
+        self.cells += cells as u64;
+    }
+
+    pub fn bytes_written(&mut self, bytes: u64) {
+        self.bytes += bytes;
+    }
+
+    // A single line of JSON
+    pub fn json(&self) -> String {
+        let mut times = self.frame_times.clone();
+        times.sort_by(f32::total_cmp);
+        let mean = if times.is_empty() { 0.0 } else { times.iter().sum::<f32>() / times.len() as f32 };
+        let percentile = |p: f32| if times.is_empty() { 0.0 } else { times[((times.len() - 1) as f32 * p).round() as usize] };
+        format!("{{\"frames\": {}, \"seconds\": {:.3}, \"frame_ms\": {{\"mean\": {mean:.3}, \"median\": {:.3}, \
+            \"p95\": {:.3}, \"p99\": {:.3}, \"max\": {:.3}}}, \"cells\": {}, \"bytes\": {}}}",
+            times.len(), self.started.elapsed().as_secs_f32(), percentile(0.5), percentile(0.95), percentile(0.99),
+            percentile(1.0), self.cells, self.bytes)
+    }
+}
+
+// Where --stats-json writes: a file descriptor already open, like 3 with
+// 3>stats.json, or a file, the only choice on systems without descriptors
+#[derive(Clone)]
+pub enum StatsTarget {
+    #[cfg(unix)]
+    Fd(i32),
+    File(PathBuf),
+}
+
+impl StatsTarget {
+    pub fn parse(s: &str) -> Result<StatsTarget, String> {
+        #[cfg(unix)]
+        if let Ok(fd) = s.parse() {
+            if !fd_open(fd) { return Err(format!("file descriptor {fd} for --stats-json is not open")); }
+            return Ok(StatsTarget::Fd(fd));
+        }
+        Ok(StatsTarget::File(s.into()))
+    }
+
+    pub fn write(&self, stats: &RunStats) -> Result<(), String> {
+        let line = stats.json();
+        match self {
+            #[cfg(unix)]
+            StatsTarget::Fd(fd) => write_fd(*fd, &line).map_err(|e| format!("cannot write statistics to descriptor {fd}: {e}")),
+            StatsTarget::File(path) => std::fs::write(path, format!("{line}\n")).map_err(|e| format!("cannot save {}: {e}", path.display())),
+        }
+    }
+}
+
+// Whether `fd` is an open file descriptor
+#[cfg(unix)]
+fn fd_open(fd: RawFd) -> bool {
+    // SAFETY: F_GETFD only reads the descriptor's flags, on any number
+    unsafe { libc::fcntl(fd, libc::F_GETFD) != -1 }
+}
+
+// Write `line` to the file descriptor `fd`, leaving it open
+#[cfg(unix)]
+fn write_fd(fd: RawFd, line: &str) -> std::io::Result<()> {
+    // SAFETY: the caller checked `fd` is open, and the File is never
+    // dropped so it doesn't close it
+    let mut file = ManuallyDrop::new(unsafe { File::from_raw_fd(fd) });
+    writeln!(file, "{line}").and_then(|_| file.flush())
+}
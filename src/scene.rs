@@ -0,0 +1,472 @@
+use std::collections::BTreeSet;
+use std::f32::consts::{PI, TAU};
+use std::path::Path;
+use std::sync::{Arc, LazyLock, RwLock, RwLockReadGuard};
+
+use crossterm::style::Color;
+
+use crate::camera::Aim;
+use crate::obj::load_obj;
+use crate::texture::{TextArt, Texture};
+
+use crate::math::{add, length, normalize, rotate_x, rotate_y, rotate_z, scale};
+
+// Cube vertices (unit cube centered at origin)
+const CUBE_VERTICES: [[f32; 3]; 8] = [
+    [-1.0, -1.0, -1.0],
+    [ 1.0, -1.0, -1.0],
+    [ 1.0,  1.0, -1.0],
+    [-1.0,  1.0, -1.0],
+    [-1.0, -1.0,  1.0],
+    [ 1.0, -1.0,  1.0],
+    [ 1.0,  1.0,  1.0],
+    [-1.0,  1.0,  1.0],
+];
+
+// Cube edges (pairs of vertex indices)
+const CUBE_EDGES: [(usize, usize); 12] = [
+    (0, 1), (1, 2), (2, 3), (3, 0), // back face
+    (4, 5), (5, 6), (6, 7), (7, 4), // front face
+    (0, 4), (1, 5), (2, 6), (3, 7), // connecting edges
+];
+
+// Face definitions for coloring (4 vertices per face, with color). Vertices
+// are wound so that get_face_normal points out of the cube.
+const CUBE_FACES: [([usize; 4], Color); 6] = [
+    ([0, 3, 2, 1], Color::Red),      // back
+    ([4, 5, 6, 7], Color::Green),    // front
+    ([0, 4, 7, 3], Color::Blue),     // left
+    ([1, 2, 6, 5], Color::Yellow),   // right
+    ([3, 7, 6, 2], Color::Magenta),  // top
+    ([0, 1, 5, 4], Color::Cyan),     // bottom
+];
+
+// Directional light, `dir` points from the surface towards the light and
+// `color` is linear RGB in 0.0..=1.0
+#[derive(Clone, Copy)]
+pub struct Light {
+    pub dir: [f32; 3],
+    pub color: [f32; 3],
+}
+
+// Warm key light from the viewer plus a cool fill from the upper left
+pub const LIGHTS: [Light; 2] = [
+    Light { dir: [0.0, 0.0, -1.0], color: [1.0, 0.9, 0.75] },
+    Light { dir: [-1.0, 1.0, -0.5], color: [0.25, 0.35, 0.6] },
+];
+
+// Procedural sky seen in reflections: a blue gradient above the horizon and
+// dark ground below it. The hard step at the horizon is what sells chrome
+pub struct Environment {
+    pub zenith: [f32; 3],
+    pub horizon: [f32; 3],
+    pub ground: [f32; 3],
+    pub nadir: [f32; 3],
+}
+
+pub const SKY: Environment = Environment {
+    zenith: [0.1, 0.25, 0.7],
+    horizon: [0.85, 0.9, 1.0],
+    ground: [0.35, 0.27, 0.2],
+    nadir: [0.05, 0.05, 0.05],
+};
+
+impl Environment {
+    // Linear RGB seen looking along `dir`
+    pub fn sample(&self, dir: [f32; 3]) -> [f32; 3] {
+        let y = normalize(dir)[1];
+        let (from, to, f) = if y >= 0.0 { (self.horizon, self.zenith, y.sqrt()) } else { (self.ground, self.nadir, (-y).sqrt()) };
+        [0, 1, 2].map(|i| from[i] + (to[i] - from[i]) * f)
+    }
+}
+
+// The light used for shadows: the one shining down the most steeply
+pub fn shadow_light(lights: &[Light]) -> Option<usize> {
+    lights.iter()
+        .enumerate()
+        .filter(|(_, l)| l.dir[1] > 0.0)
+        .max_by(|a, b| a.1.dir[1].partial_cmp(&b.1.dir[1]).unwrap_or(std::cmp::Ordering::Equal))
+        .map(|(i, _)| i)
+}
+
+// A convex polygon with a flat color, wound counter-clockwise when seen from
+// outside the mesh
+#[derive(Clone)]
+pub struct Face {
+    pub indices: Vec<usize>,
+    pub color: Color,
+    // Texture coordinates per vertex, empty when the face has none
+    pub uv: Vec<[f32; 2]>,
+    // Characters drawn over the face instead of the shade ramp
+    pub art: Option<Arc<TextArt>>,
+}
+
+#[derive(Clone)]
+pub struct Mesh {
+    pub vertices: Vec<[f32; 3]>,
+    pub faces: Vec<Face>,
+    pub edges: Vec<(usize, usize)>,
+}
+
+// Each cube face shows the whole texture, upright when seen from outside
+// (face windings run clockwise on screen, so v comes first)
+const QUAD_UV: [[f32; 2]; 4] = [[0.0, 0.0], [0.0, 1.0], [1.0, 1.0], [1.0, 0.0]];
+
+pub fn cube_mesh() -> Mesh {
+    Mesh {
+        vertices: CUBE_VERTICES.to_vec(),
+        faces: CUBE_FACES.iter()
+            .map(|(indices, color)| Face { indices: indices.to_vec(), color: *color, uv: QUAD_UV.to_vec(), art: None })
+            .collect(),
+        edges: CUBE_EDGES.to_vec(),
+    }
+}
+
+// Every side of every face once
+pub fn face_edges(faces: &[Face]) -> Vec<(usize, usize)> {
+    let edges: BTreeSet<(usize, usize)> = faces.iter()
+        .flat_map(|f| (0..f.indices.len()).map(move |i| (f.indices[i], f.indices[(i + 1) % f.indices.len()])))
+        .map(|(a, b)| (a.min(b), a.max(b)))
+        .collect();
+    edges.into_iter().collect()
+}
+
+// Unit sphere of quads in `rings` bands from pole to pole and `slices`
+// around, with triangles around the poles. The texture wraps around it once
+pub fn sphere_mesh(rings: usize, slices: usize) -> Mesh {
+    let mut vertices = vec![[0.0, 1.0, 0.0]];
+    for ring in 1..rings {
+        let polar = PI * ring as f32 / rings as f32;
+        for slice in 0..slices {
+            let azimuth = TAU * slice as f32 / slices as f32;
+            vertices.push([polar.sin() * azimuth.cos(), polar.cos(), polar.sin() * azimuth.sin()]);
+        }
+    }
+    vertices.push([0.0, -1.0, 0.0]);
+    let bottom = vertices.len() - 1;
+    // Vertex on `ring` (0 and `rings` being the poles) at `slice`, coming
+    // back around to slice 0
+    let at = |ring: usize, slice: usize| match ring {
+        0 => 0,
+        r if r == rings => bottom,
+        _ => 1 + (ring - 1) * slices + slice % slices,
+    };
+    let uv = |ring: usize, slice: usize| [slice as f32 / slices as f32, ring as f32 / rings as f32];
+
+    let mut faces = Vec::new();
+    for ring in 0..rings {
+        for slice in 0..slices {
+            let corners = [(ring, slice), (ring, slice + 1), (ring + 1, slice + 1), (ring + 1, slice)];
+            // The pole is a single vertex, so its two corners are one
+            let corners: Vec<(usize, usize)> = match ring {
+                0 => vec![corners[0], corners[2], corners[3]],
+                r if r == rings - 1 => vec![corners[0], corners[1], corners[2]],
+                _ => corners.to_vec(),
+            };
+            faces.push(Face {
+                indices: corners.iter().map(|&(r, s)| at(r, s)).collect(),
+                color: Color::White,
+                uv: corners.iter().map(|&(r, s)| uv(r, s)).collect(),
+                art: None,
+            });
+        }
+    }
+    let edges = face_edges(&faces);
+    Mesh { vertices, faces, edges }
+}
+
+// Surface properties beyond the per-face color
+#[derive(Clone)]
+pub struct Material {
+    // Fraction of light mirrored off the surface. Only the ray tracer mirrors
+    // other objects, the rasterizer reflects just the scene environment
+    pub reflectivity: f32,
+    // 1.0 is fully opaque; lower values let what is behind show through
+    pub opacity: f32,
+    // Replaces the face colors on faces that have texture coordinates
+    pub texture: Option<Arc<Texture>>,
+    // Lowest intensity the surface shows whatever the lighting, so it glows
+    // under --bloom
+    pub emission: f32,
+}
+
+impl Default for Material {
+    fn default() -> Material {
+        Material { reflectivity: 0.0, opacity: 1.0, texture: None, emission: 0.0 }
+    }
+}
+
+// A mesh placed in the world. `spin` is the rotation speed around each axis
+// in radians per second. `turn` is a fixed yaw, pitch and roll applied on
+// top of the spin, set by hand at runtime
+#[derive(Clone)]
+pub struct Object {
+    pub mesh: Mesh,
+    pub position: [f32; 3],
+    pub scale: f32,
+    pub spin: [f32; 3],
+    pub turn: [f32; 3],
+    pub material: Material,
+}
+
+// Radians per second objects spin around x, y and z unless told otherwise
+pub const DEFAULT_SPIN: [f32; 3] = [0.7, 1.0, 0.3];
+
+impl Object {
+    pub fn new(mesh: Mesh) -> Object {
+        Object { mesh, position: [0.0; 3], scale: 1.0, spin: DEFAULT_SPIN, turn: [0.0; 3], material: Material::default() }
+    }
+
+    // World-space vertices at time `t`
+    pub fn transformed(&self, t: f32) -> Vec<[f32; 3]> {
+        self.mesh.vertices.iter()
+            .map(|v| {
+                let mut p = scale(*v, self.scale);
+                p = rotate_x(p, t * self.spin[0]);
+                p = rotate_y(p, t * self.spin[1]);
+                p = rotate_z(p, t * self.spin[2]);
+                p = rotate_y(p, self.turn[0]);
+                p = rotate_x(p, self.turn[1]);
+                p = rotate_z(p, self.turn[2]);
+                add(p, self.position)
+            })
+            .collect()
+    }
+
+    // Distance from `position` to the farthest vertex however it turns
+    pub fn extent(&self) -> f32 {
+        self.mesh.vertices.iter().map(|v| length(*v)).fold(0.0, f32::max) * self.scale
+    }
+}
+
+pub struct Scene {
+    pub objects: Vec<Object>,
+    pub lights: Vec<Light>,
+    // What reflective surfaces show where nothing else is mirrored
+    pub environment: Option<Environment>,
+    // Where a script has put the camera, if one has
+    pub camera: Option<Aim>,
+}
+
+impl Scene {
+    // Radius around the origin that holds every object however it turns
+    pub fn radius(&self) -> f32 {
+        self.objects.iter()
+            .map(|o| length(o.position) + o.extent())
+            .fold(0.0, f32::max)
+    }
+
+    // Seconds of animation one loop takes, a whole turn around the axis
+    // anything spins around fastest. `None` when nothing spins
+    pub fn loop_period(&self) -> Option<f32> {
+        let fastest = self.objects.iter().flat_map(|o| o.spin).map(f32::abs).fold(0.0, f32::max);
+        (fastest > 0.0).then(|| std::f32::consts::TAU / fastest)
+    }
+
+    // Faces split into triangles, as the renderers draw them
+    pub fn triangle_count(&self) -> usize {
+        self.objects.iter()
+            .flat_map(|o| &o.mesh.faces)
+            .map(|f| f.indices.len().saturating_sub(2))
+            .sum()
+    }
+
+    // Rotate every object in place, `yaw` around the vertical axis and
+    // `pitch` around the horizontal one
+    pub fn turn(&mut self, yaw: f32, pitch: f32) {
+        for object in &mut self.objects {
+            object.turn[0] += yaw;
+            object.turn[1] += pitch;
+        }
+    }
+}
+
+// Pips of a die in the same order as CUBE_FACES, opposite faces adding up
+// to seven. Dots read as holes against the brightly lit faces
+const DIE_PIPS: [&str; 6] = [
+    "     \n     \n  .  \n     \n     ",
+    "     \n . . \n     \n . . \n     \n . . \n     ",
+    "     \n .   \n     \n   . \n     ",
+    "     \n . . \n  .  \n . . \n     ",
+    "     \n .   \n  .  \n   . \n     ",
+    "     \n . . \n     \n . . \n     ",
+];
+
+fn die_mesh() -> Mesh {
+    let mut mesh = cube_mesh();
+    for (face, pips) in mesh.faces.iter_mut().zip(DIE_PIPS) {
+        face.color = Color::White;
+        face.art = Some(Arc::new(TextArt::new(pips)));
+    }
+    mesh
+}
+
+// A number changing how a shape is built, written NAME=VALUE after it
+pub struct ShapeParam {
+    pub name: &'static str,
+    pub about: &'static str,
+    pub default: usize,
+    pub min: usize,
+    pub max: usize,
+}
+
+// Makes the mesh of a shape for the values of its settings, in order
+type Build = Box<dyn Fn(&[usize]) -> Result<Mesh, String> + Send + Sync>;
+
+// A mesh objects can be made of by name, with --shape, `spawn` or a scene
+// file's `shape`
+pub struct Shape {
+    pub name: String,
+    pub about: String,
+    pub params: &'static [ShapeParam],
+    build: Build,
+}
+
+impl Shape {
+    pub fn new(name: &str, about: &str, params: &'static [ShapeParam],
+        build: impl Fn(&[usize]) -> Result<Mesh, String> + Send + Sync + 'static) -> Shape {
+        Shape { name: name.to_string(), about: about.to_string(), params, build: Box::new(build) }
+    }
+}
+
+const SPHERE_PARAMS: [ShapeParam; 2] = [
+    ShapeParam { name: "rings", about: "bands from pole to pole", default: 8, min: 2, max: 64 },
+    ShapeParam { name: "slices", about: "slices around", default: 16, min: 3, max: 128 },
+];
+
+// Every shape, in the order --list-shapes gives them: the built-in ones and
+// then those registered while running
+static SHAPES: LazyLock<RwLock<Vec<Shape>>> = LazyLock::new(|| RwLock::new(vec![
+    Shape::new("cube", "cube with six colored faces", &[], |_| Ok(cube_mesh())),
+    Shape::new("sphere", "sphere of quads", &SPHERE_PARAMS, |p| Ok(sphere_mesh(p[0], p[1]))),
+    Shape::new("die", "white cube with pips", &[], |_| Ok(die_mesh())),
+]));
+
+// The shapes as they are now
+pub fn shapes() -> RwLockReadGuard<'static, Vec<Shape>> {
+    SHAPES.read().unwrap_or_else(|e| e.into_inner())
+}
+
+// Make `shape` available by its name everywhere shapes are, unless a shape
+// already has that name
+pub fn register_shape(shape: Shape) -> Result<(), String> {
+    let mut shapes = SHAPES.write().unwrap_or_else(|e| e.into_inner());
+    if shapes.iter().any(|other| other.name == shape.name) {
+        return Err(format!("there is already a shape called {}", shape.name));
+    }
+    shapes.push(shape);
+    Ok(())
+}
+
+// Register every NAME.obj in `dir` as the shape NAME, read again each time
+// one is made so editing the file shows in the next. A missing directory
+// has none
+pub fn register_shape_files(dir: &Path) -> Result<(), String> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(format!("cannot read {}: {e}", dir.display())),
+    };
+    let mut paths: Vec<_> = entries.filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("obj")))
+        .collect();
+    paths.sort();
+    for path in paths {
+        let Some(name) = path.file_stem().and_then(|stem| stem.to_str()).map(str::to_string) else { continue; };
+        let about = format!("model in {}", path.display());
+        register_shape(Shape::new(&name, &about, &[], move |_| load_obj(&path)))
+            .map_err(|e| format!("{}: {e}", dir.display()))?;
+    }
+    Ok(())
+}
+
+// Whether `spec` names a shape, whatever its settings
+pub fn is_shape(spec: &str) -> bool {
+    let name = spec.split(':').next().unwrap_or(spec);
+    shapes().iter().any(|shape| shape.name == name)
+}
+
+// The mesh of the shape `spec` names, like sphere or sphere:rings=12,slices=24
+// with the settings left out at their defaults
+pub fn shape_mesh(spec: &str) -> Result<Mesh, String> {
+    let (name, settings) = spec.split_once(':').unwrap_or((spec, ""));
+    let shapes = shapes();
+    let shape = shapes.iter().find(|shape| shape.name == name)
+        .ok_or_else(|| {
+            let names: Vec<&str> = shapes.iter().map(|shape| shape.name.as_str()).collect();
+            format!("unknown shape '{name}' (expected one of: {})", names.join(", "))
+        })?;
+    let mut values: Vec<usize> = shape.params.iter().map(|param| param.default).collect();
+    for setting in settings.split(',').filter(|s| !s.is_empty()) {
+        let (key, value) = setting.split_once('=')
+            .ok_or(format!("invalid shape setting '{setting}' (expected NAME=VALUE)"))?;
+        let Some(i) = shape.params.iter().position(|param| param.name == key) else {
+            if shape.params.is_empty() { return Err(format!("{name} has no settings")); }
+            let names: Vec<&str> = shape.params.iter().map(|param| param.name).collect();
+            return Err(format!("{name} has no setting '{key}' (expected {})", names.join(" or ")));
+        };
+        let ShapeParam { min, max, .. } = shape.params[i];
+        values[i] = value.parse().ok().filter(|v| (min..=max).contains(v))
+            .ok_or(format!("invalid {key} '{value}' for {name} (expected {min} to {max})"))?;
+    }
+    (shape.build)(&values)
+}
+
+pub const SCENE_NAMES: [&str; 5] = ["cube", "pair", "mirror", "glass", "die"];
+
+// Built-in scenes selectable with --scene
+pub fn build_scene(name: &str) -> Option<Scene> {
+    let mut environment = None;
+    let objects = match name {
+        "cube" => vec![Object::new(cube_mesh())],
+        // A small cube hovering between the fill light and a larger one, so
+        // object-to-object shadows are visible
+        "pair" => {
+            let mut big = Object::new(cube_mesh());
+            big.position = [0.5, -0.7, 0.3];
+            big.scale = 0.9;
+            big.spin = [0.0, 0.4, 0.0];
+            let mut small = Object::new(cube_mesh());
+            small.position = [-0.9, 0.9, -0.2];
+            small.scale = 0.35;
+            small.spin = [0.9, 1.3, 0.5];
+            vec![big, small]
+        }
+        // Chrome cube flanked by two colored ones, best seen with --mode raytrace
+        "mirror" => {
+            let mut chrome = Object::new(cube_mesh());
+            chrome.scale = 0.8;
+            chrome.spin = [0.3, 0.5, 0.0];
+            chrome.material.reflectivity = 0.7;
+            environment = Some(SKY);
+            let mut left = Object::new(cube_mesh());
+            left.position = [-2.0, -0.6, 0.8];
+            left.scale = 0.4;
+            let mut right = Object::new(cube_mesh());
+            right.position = [2.0, 0.6, 0.8];
+            right.scale = 0.4;
+            right.spin = [-0.5, 0.8, 0.2];
+            vec![chrome, left, right]
+        }
+        // A solid cube inside a glass one
+        "glass" => {
+            let mut glass = Object::new(cube_mesh());
+            glass.scale = 1.2;
+            glass.spin = [0.3, 0.6, 0.1];
+            glass.material.opacity = 0.35;
+            let mut core = Object::new(cube_mesh());
+            core.scale = 0.5;
+            core.spin = [-0.8, 1.1, 0.4];
+            vec![glass, core]
+        }
+        "die" => vec![Object::new(die_mesh())],
+        _ => return None,
+    };
+
+    Some(Scene { objects, lights: LIGHTS.to_vec(), environment, camera: None })
+}
+
+// A scene showing just `mesh`, lit like the built-in ones
+pub fn model_scene(mesh: Mesh) -> Scene {
+    Scene { objects: vec![Object::new(mesh)], lights: LIGHTS.to_vec(), environment: None, camera: None }
+}
@@ -0,0 +1,303 @@
+// Scenes described in TOML files, for setups too big for the command line.
+// Objects and lights take the properties NAME.PROPERTY = VALUE sets on the
+// command line, with the same values: a number, a list of three, or a
+// string with an expression that follows the time `t`
+//
+//   sky = true
+//
+//   [camera]
+//   distance = 6
+//
+//   [[object]]
+//   name = "ball"
+//   shape = "sphere"
+//   position = "(sin(t) * 2, 0, 0)"
+//   emission = 0.3
+//
+//   [[object]]
+//   mesh = "teapot.obj"
+//   color = "#c08040"
+//   texture = "wood.png"
+//   spin = [0, 1, 0]
+//   scale = [
+//       { t = 0, value = 0.5 },
+//       { t = 2, value = 1, ease = "bounce" },
+//   ]
+//
+//   [[light]]
+//   dir = [0, 1, -1]
+//   color = [1, 1, 1]
+//
+// A list of keys like that sets keyframes, eased linear, cubic, elastic or
+// bounce on the way into a key. An object with a name goes by it as well
+// as objectN. Files named in it are found next to it, and saving them
+// reloads the scene like saving it does. The camera orbits at
+// `distance`, or flies from `position` looking `yaw` and `pitch` degrees
+// round, or travels along a `spline` through `points` looking at `target`
+// for `duration` seconds (see spline.rs). Without a spline, a `position`
+// that is keyframes or an expression, or a `target`, keys the camera like
+// an object's properties, looking from one at the other. Without lights
+// the scene gets the usual two
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime};
+
+use crossterm::style::Color;
+
+use crate::camera::Camera;
+use crate::color::parse_rgb;
+use crate::obj::load_obj;
+use crate::scene::{shape_mesh, Object, Scene, LIGHTS, SKY};
+use crate::script::Script;
+use crate::spline::{CameraSpline, Spline, SplineKind, Target, SPLINE_NAMES};
+use crate::texture::Texture;
+use crate::timeline::View;
+
+// A scene ready to show: its objects with the animated properties bound,
+// and where the file says to look from
+pub struct Loaded {
+    pub scene: Scene,
+    pub script: Script,
+    pub distance: Option<f32>,
+    pub fly: Option<Camera>,
+    // Camera cuts a timeline makes, in the order they happen
+    pub cuts: Vec<(f32, View)>,
+    // A move along a curve the camera makes as the animation plays
+    pub spline: Option<CameraSpline>,
+    // The scene file and the meshes and textures it names, to watch
+    pub files: Vec<PathBuf>,
+}
+
+impl Loaded {
+    // A scene that needs nothing else
+    pub fn plain(scene: Scene) -> Loaded {
+        Loaded { scene, script: Script::new(), distance: None, fly: None, cuts: Vec::new(), spline: None, files: Vec::new() }
+    }
+}
+
+// How often a watched file is looked at
+const WATCH_INTERVAL: Duration = Duration::from_millis(500);
+
+// Notices a scene file or the files it names being saved, by looking at
+// when they last changed every so often. That works the same everywhere,
+// and editors saving by writing a new file and renaming it over the old one
+pub struct Watch {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+    checked: Instant,
+}
+
+impl Watch {
+    pub fn new(paths: &[PathBuf]) -> Watch {
+        Watch { files: paths.iter().map(|path| (path.clone(), modified(path))).collect(), checked: Instant::now() }
+    }
+
+    // Whether any of the files was saved since the last call that said so.
+    // While one is missing it counts as unchanged
+    pub fn changed(&mut self) -> bool {
+        if self.checked.elapsed() < WATCH_INTERVAL { return false; }
+        self.checked = Instant::now();
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let now = modified(path);
+            if now.is_none() || now == *last { continue; }
+            *last = now;
+            changed = true;
+        }
+        changed
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Whether `name` is meant as a scene file
+pub fn is_scene_file(name: &str) -> bool {
+    name.ends_with(".toml")
+}
+
+// Read the scene in the file at `path` with its properties at time `t`,
+// and give every object what `dress` gives it
+pub fn load_scene_file(path: &Path, t: f32, dress: impl Fn(&mut Object)) -> Result<Loaded, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+    let mut loaded = parse(&text, path.parent().unwrap_or(Path::new("")), t, dress).map_err(|e| format!("{}: {e}", path.display()))?;
+    loaded.files.insert(0, path.to_path_buf());
+    Ok(loaded)
+}
+
+fn parse(text: &str, dir: &Path, t: f32, dress: impl Fn(&mut Object)) -> Result<Loaded, String> {
+    let table: toml::Table = text.parse().map_err(|e: toml::de::Error| e.message().to_string())?;
+    let mut scene = Scene { objects: Vec::new(), lights: Vec::new(), environment: None, camera: None };
+    // NAME.PROPERTY = VALUE for everything that isn't about building the object
+    let mut statements = Vec::new();
+    // What each object is called besides objectN, if anything
+    let mut names: Vec<Option<String>> = Vec::new();
+    let mut files = Vec::new();
+    let (mut distance, mut fly, mut spline) = (None, None, None);
+
+    for (key, value) in &table {
+        match key.as_str() {
+            "sky" => {
+                let sky = value.as_bool().ok_or("sky has to be true or false")?;
+                scene.environment = sky.then_some(SKY);
+            }
+            "camera" => {
+                let camera = value.as_table().ok_or("camera has to be a table")?;
+                let field = |name: &str| camera.get(name).map(|v| number(v).ok_or(format!("camera {name} has to be a number"))).transpose();
+                distance = field("distance")?;
+                // Keyframes, an expression or a target are for the script
+                let keyed = !camera.contains_key("spline")
+                    && (camera.contains_key("target") || camera.get("position").is_some_and(|p| vector(p).is_none()));
+                if keyed {
+                    for (property, value) in camera.iter().filter(|(k, _)| ["position", "target"].contains(&k.as_str())) {
+                        let value = expression(value).ok_or(format!("camera {property} has to be a list, an expression or keyframes"))?;
+                        statements.push(format!("camera.{property} = {value}"));
+                    }
+                } else if let Some(position) = camera.get("position") {
+                    let position = vector(position).ok_or("camera position has to be a list of three numbers")?;
+                    let (yaw, pitch) = (field("yaw")?.unwrap_or(0.0), field("pitch")?.unwrap_or(0.0));
+                    fly = Some(Camera { position, yaw: yaw.to_radians(), pitch: pitch.to_radians() });
+                }
+                if camera.contains_key("spline") { spline = Some(camera_spline(camera).map_err(|e| format!("camera: {e}"))?); }
+            }
+            "object" => for (i, object) in tables(value, "object")?.iter().enumerate() {
+                let at = |e: String| format!("object {}: {e}", i + 1);
+                scene.objects.push(build_object(object, dir, &mut files).map_err(at)?);
+                let name = object.get("name").map(|v| v.as_str().ok_or("name has to be a string")).transpose().map_err(|e| at(e.to_string()))?;
+                if let Some(name) = name.filter(|name| names.contains(&Some(name.to_string()))) {
+                    return Err(at(format!("another object is already called {name}")));
+                }
+                names.push(name.map(str::to_string));
+                for (property, value) in object.iter().filter(|(k, _)| !OBJECT_KEYS.contains(&k.as_str())) {
+                    let value = expression(value).ok_or(format!("{property} has to be a number, a list, an expression or keyframes")).map_err(at)?;
+                    statements.push(format!("object{}.{property} = {value}", i + 1));
+                }
+            },
+            "light" => for (i, light) in tables(value, "light")?.iter().enumerate() {
+                scene.lights.push(LIGHTS[0]);
+                for (property, value) in light.iter() {
+                    let value = expression(value).ok_or(format!("light {}: {property} has to be a number, a list, an expression or keyframes", i + 1))?;
+                    statements.push(format!("light{}.{property} = {value}", i + 1));
+                }
+            },
+            _ => return Err(format!("unknown setting '{key}' (expected sky, camera, object or light)")),
+        }
+    }
+    if scene.objects.is_empty() { return Err("there is no [[object]]".to_string()); }
+    if table.get("light").is_none() { scene.lights = LIGHTS.to_vec(); }
+
+    let mut script = Script::new();
+    for (i, name) in names.iter().enumerate() {
+        if let Some(name) = name { script.name(name, i); }
+    }
+    for statement in &statements {
+        script.run(statement, &mut scene, t, &dress)?;
+    }
+    for object in &mut scene.objects {
+        dress(object);
+    }
+    Ok(Loaded { scene, script, distance, fly, cuts: Vec::new(), spline, files })
+}
+
+// The [camera] move along a curve, from its spline, points, target and
+// duration
+fn camera_spline(camera: &toml::Table) -> Result<CameraSpline, String> {
+    let name = camera.get("spline").and_then(toml::Value::as_str).unwrap_or_default();
+    let kind = SplineKind::parse(name).ok_or(format!("unknown spline '{name}' (expected {})", SPLINE_NAMES.join(" or ")))?;
+    let points = camera.get("points").and_then(vectors).ok_or("points has to be a list of lists of three numbers")?;
+    let position = Spline::new(kind, points)?;
+    let target = match camera.get("target") {
+        None => Target::Point([0.0; 3]),
+        Some(target) => match (vector(target), vectors(target)) {
+            (Some(point), _) => Target::Point(point),
+            (None, Some(points)) => Target::Spline(Spline::new(kind, points).map_err(|e| format!("target: {e}"))?),
+            _ => return Err("target has to be a list of three numbers or a list of them".to_string()),
+        },
+    };
+    let duration = camera.get("duration").map(|v| number(v).filter(|d| *d > 0.0).ok_or("duration has to be a number of seconds above 0")).transpose()?;
+    Ok(CameraSpline { position, target, duration: duration.ok_or("a spline needs a duration")? })
+}
+
+// Keys of an object that say what it is made of rather than set a property
+const OBJECT_KEYS: [&str; 5] = ["name", "shape", "mesh", "color", "texture"];
+
+// The object an [[object]] section describes, adding the files it reads to
+// `files`
+fn build_object(object: &toml::Table, dir: &Path, files: &mut Vec<PathBuf>) -> Result<Object, String> {
+    let text = |name: &str| object.get(name).map(|v| v.as_str().ok_or(format!("{name} has to be a string"))).transpose();
+    let mesh = match (text("shape")?, text("mesh")?) {
+        (Some(shape), None) => shape_mesh(shape)?,
+        (None, Some(mesh)) => {
+            files.push(dir.join(mesh));
+            load_obj(&dir.join(mesh))?
+        }
+        _ => return Err("needs either a shape or a mesh".to_string()),
+    };
+    let mut object = Object::new(mesh);
+    if let Some(color) = text("color")? {
+        let [r, g, b] = parse_rgb(color).ok_or(format!("unknown color '{color}' (expected a color name or #rrggbb)"))?;
+        for face in &mut object.mesh.faces {
+            face.color = Color::Rgb { r, g, b };
+        }
+    }
+    if let Some(texture) = text("texture")? {
+        files.push(dir.join(texture));
+        object.material.texture = Some(Arc::new(Texture::load(&dir.join(texture))?));
+    }
+    Ok(object)
+}
+
+// An array of tables, like the [[object]] sections
+fn tables<'a>(value: &'a toml::Value, name: &str) -> Result<Vec<&'a toml::Table>, String> {
+    value.as_array()
+        .and_then(|items| items.iter().map(toml::Value::as_table).collect())
+        .ok_or(format!("{name} has to be written as [[{name}]] sections"))
+}
+
+fn number(value: &toml::Value) -> Option<f32> {
+    match value {
+        toml::Value::Integer(n) => Some(*n as f32),
+        toml::Value::Float(f) => Some(*f as f32),
+        _ => None,
+    }
+}
+
+fn vector(value: &toml::Value) -> Option<[f32; 3]> {
+    let numbers: Vec<f32> = value.as_array()?.iter().map(number).collect::<Option<_>>()?;
+    numbers.try_into().ok()
+}
+
+fn vectors(value: &toml::Value) -> Option<Vec<[f32; 3]>> {
+    value.as_array()?.iter().map(vector).collect()
+}
+
+// A value as the command line would write it
+fn expression(value: &toml::Value) -> Option<String> {
+    match value {
+        toml::Value::String(s) => Some(s.clone()),
+        toml::Value::Array(items) if !items.is_empty() && items.iter().all(toml::Value::is_table) => keys(items),
+        toml::Value::Array(items) => {
+            let items: Vec<String> = items.iter().map(expression).collect::<Option<_>>()?;
+            Some(format!("({})", items.join(", ")))
+        }
+        _ => number(value).map(|n| n.to_string()),
+    }
+}
+
+// Keyframes as keys(TIME: VALUE EASE, ...), from tables with a `t`, a
+// `value` and maybe an `ease`
+fn keys(items: &[toml::Value]) -> Option<String> {
+    let keys: Vec<String> = items.iter()
+        .map(|item| {
+            let key = item.as_table()?;
+            if key.keys().any(|name| !["t", "value", "ease"].contains(&name.as_str())) { return None; }
+            let ease = match key.get("ease") {
+                Some(ease) => format!(" {}", ease.as_str()?),
+                None => String::new(),
+            };
+            Some(format!("{}: {}{ease}", number(key.get("t")?)?, expression(key.get("value")?)?))
+        })
+        .collect::<Option<_>>()?;
+    Some(format!("keys({})", keys.join(", ")))
+}
@@ -0,0 +1,111 @@
+// Saving the frame on screen to files in the current directory, named
+// after the time (UTC) they were taken at
+
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+use clap::ValueEnum;
+use image::ExtendedColorType;
+
+use crate::color::ColorSupport;
+use crate::framebuffer::{FrameBuffer, Ramp};
+use crate::output::{write_ansi, write_svg};
+use crate::pixels::{render_bitmap, Bitmap};
+use crate::render::{render_frame, Projection, RenderSettings};
+use crate::scene::Scene;
+
+// What a screenshot is saved as
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+pub enum ShotFormat {
+    // The characters, with their colors as escape sequences
+    Text,
+    // An image from the pixel renderer
+    Png,
+    // The characters drawn in a scalable image
+    Svg,
+    // Text and PNG
+    Both,
+}
+
+impl ShotFormat {
+    pub fn text(self) -> bool {
+        matches!(self, ShotFormat::Text | ShotFormat::Both)
+    }
+
+    pub fn png(self) -> bool {
+        matches!(self, ShotFormat::Png | ShotFormat::Both)
+    }
+}
+
+// Render the view `proj` shows at time `t` again and save it in `format`,
+// the image at `cell` pixels per cell. Returns what was saved. The face
+// under the pointer isn't lit up in it
+pub fn screenshot(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings, format: ShotFormat,
+    cell: (usize, usize), colors: ColorSupport) -> Result<String, String> {
+    let settings = RenderSettings { hover: None, ..settings.clone() };
+    let name = shot_name();
+    let mut saved = Vec::new();
+    if format.text() {
+        save_text(&format!("{name}.txt"), &render_frame(scene, proj, t, &settings), &settings.ramp, colors)?;
+        saved.push(".txt");
+    }
+    if format.png() {
+        save_png(&format!("{name}.png"), &render_bitmap(scene, proj, t, &settings, cell))?;
+        saved.push(".png");
+    }
+    if format == ShotFormat::Svg {
+        save_svg(&format!("{name}.svg"), &render_frame(scene, proj, t, &settings), &settings.ramp, colors)?;
+        saved.push(".svg");
+    }
+    Ok(format!("saved {name}{}", saved.join(" and ")))
+}
+
+// teruminator-YYYYMMDD-HHMMSS-mmm, in UTC down to the millisecond so quick
+// presses don't overwrite each other
+fn shot_name() -> String {
+    let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+    let (days, seconds) = (now.as_secs() / 86400, now.as_secs() % 86400);
+    let (year, month, day) = civil_date(days as i64);
+    format!("teruminator-{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{:03}", seconds / 3600, seconds / 60 % 60,
+        seconds % 60, now.subsec_millis())
+}
+
+// The year, month and day `days` after 1970-01-01, going by the Gregorian
+// calendar's 400-year cycles (https://howardhinnant.github.io/date_algorithms.html)
+fn civil_date(days: i64) -> (i64, u32, u32) {
+    let days = days + 719468;
+    let era = days.div_euclid(146097);
+    let day_of_era = days.rem_euclid(146097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Months counted from March, so February's leap day comes last
+    let month = (5 * day_of_year + 2) / 153;
+    let day = (day_of_year - (153 * month + 2) / 5 + 1) as u32;
+    let month = if month < 10 { month + 3 } else { month - 9 } as u32;
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+fn save_text(path: &str, fb: &FrameBuffer, ramp: &Ramp, colors: ColorSupport) -> Result<(), String> {
+    let written = File::create(path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write_ansi(&mut out, fb, ramp, colors)?;
+        out.flush()
+    });
+    written.map_err(|e| format!("cannot save {path}: {e}"))
+}
+
+pub fn save_svg(path: &str, fb: &FrameBuffer, ramp: &Ramp, colors: ColorSupport) -> Result<(), String> {
+    let written = File::create(path).and_then(|file| {
+        let mut out = BufWriter::new(file);
+        write_svg(&mut out, fb, ramp, colors)?;
+        out.flush()
+    });
+    written.map_err(|e| format!("cannot save {path}: {e}"))
+}
+
+pub fn save_png(path: &str, image: &Bitmap) -> Result<(), String> {
+    let raw: Vec<u8> = image.pixels.iter().flatten().copied().collect();
+    image::save_buffer(path, &raw, image.width as u32, image.height as u32, ExtendedColorType::Rgb8)
+        .map_err(|e| format!("cannot save {path}: {e}"))
+}
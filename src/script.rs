@@ -0,0 +1,607 @@
+// Statements typed after `:` besides the commands: spawning objects and
+// setting properties of objects and lights. A value using `t`, the
+// animation time, stays bound to the property and is worked out again
+// every frame
+//
+//   spawn sphere; sphere.scale = 0.5; light.dir = (1, 1, 0)
+//   sphere.position = (2 * sin(t), 0, 2 * cos(t))
+//   sphere.scale = keys(0: 0.5, 1: 1 elastic)
+//   camera.position = keys(0: (0, 1, -6), 4: (6, 2, 0) cubic)
+//   sphere.scale
+//
+// Objects are called objectN, counted from 1, or by the name they were
+// spawned with. Lights are called lightN, or light for the first one. The
+// camera has a position and a target it looks at, and once either is set
+// it stays there rather than orbiting or flying
+
+use crate::keyframes::{segment, Ease, EASE_NAMES};
+use crate::camera::{Aim, Camera};
+use crate::render::DISTANCE;
+use crate::scene::{shape_mesh, Object, Scene};
+
+#[derive(Clone, Copy)]
+enum Value {
+    Number(f32),
+    Vector([f32; 3]),
+}
+
+impl std::fmt::Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Value::Number(n) => write!(f, "{n:.2}"),
+            Value::Vector([x, y, z]) => write!(f, "({x:.2}, {y:.2}, {z:.2})"),
+        }
+    }
+}
+
+#[derive(Clone)]
+enum Expr {
+    Number(f32),
+    // The animation time
+    Time,
+    Vector(Box<[Expr; 3]>),
+    Negate(Box<Expr>),
+    // `+`, `-`, `*` or `/`
+    Binary(char, Box<Expr>, Box<Expr>),
+    Call(Function, Box<Expr>),
+    // Key times, never going down, the values at them and the easing into each
+    Keys(Vec<f32>, Vec<Expr>, Vec<Ease>),
+}
+
+type Function = fn(f32) -> f32;
+
+const FUNCTIONS: [(&str, Function); 6] = [
+    ("sin", f32::sin),
+    ("cos", f32::cos),
+    ("abs", f32::abs),
+    ("sqrt", f32::sqrt),
+    ("floor", f32::floor),
+    ("fract", f32::fract),
+];
+
+impl Expr {
+    fn parse(text: &str) -> Result<Expr, String> {
+        let mut parser = Parser { tokens: tokenize(text)?, next: 0 };
+        let expr = parser.sum()?;
+        match parser.peek() {
+            None => Ok(expr),
+            Some(token) => Err(format!("unexpected {token} in '{text}'")),
+        }
+    }
+
+    fn uses_time(&self) -> bool {
+        match self {
+            Expr::Number(_) => false,
+            Expr::Time => true,
+            Expr::Vector(parts) => parts.iter().any(Expr::uses_time),
+            Expr::Negate(e) | Expr::Call(_, e) => e.uses_time(),
+            Expr::Binary(_, a, b) => a.uses_time() || b.uses_time(),
+            Expr::Keys(..) => true,
+        }
+    }
+
+    fn eval(&self, t: f32) -> Result<Value, String> {
+        let number = |e: &Expr| match e.eval(t)? {
+            Value::Number(n) => Ok(n),
+            Value::Vector(_) => Err("expected a number, not (x, y, z)".to_string()),
+        };
+        Ok(match self {
+            Expr::Number(n) => Value::Number(*n),
+            Expr::Time => Value::Number(t),
+            Expr::Vector(parts) => Value::Vector([number(&parts[0])?, number(&parts[1])?, number(&parts[2])?]),
+            Expr::Negate(e) => match e.eval(t)? {
+                Value::Number(n) => Value::Number(-n),
+                Value::Vector(v) => Value::Vector(v.map(|c| -c)),
+            },
+            Expr::Call(f, e) => Value::Number(f(number(e)?)),
+            Expr::Binary(op, a, b) => {
+                let apply = |x: f32, y: f32| match op {
+                    '+' => x + y,
+                    '-' => x - y,
+                    '*' => x * y,
+                    _ => x / y,
+                };
+                match (a.eval(t)?, b.eval(t)?, op) {
+                    (Value::Number(x), Value::Number(y), _) => Value::Number(apply(x, y)),
+                    (Value::Vector(u), Value::Vector(v), '+' | '-') => Value::Vector(std::array::from_fn(|i| apply(u[i], v[i]))),
+                    (Value::Vector(v), Value::Number(n), '*' | '/') => Value::Vector(v.map(|c| apply(c, n))),
+                    (Value::Number(n), Value::Vector(v), '*') => Value::Vector(v.map(|c| n * c)),
+                    _ => return Err(format!("cannot {op} these")),
+                }
+            }
+            Expr::Keys(times, values, eases) => {
+                let (from, to, share) = segment(times, eases, t);
+                match (values[from].eval(t)?, values[to].eval(t)?) {
+                    (Value::Number(a), Value::Number(b)) => Value::Number(a + (b - a) * share),
+                    (Value::Vector(a), Value::Vector(b)) => Value::Vector(std::array::from_fn(|i| a[i] + (b[i] - a[i]) * share)),
+                    _ => return Err(KEYS_MIXED.to_string()),
+                }
+            }
+        })
+    }
+}
+
+#[derive(Clone, PartialEq)]
+enum Token {
+    Number(f32),
+    Name(String),
+    Symbol(char),
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Token::Number(n) => write!(f, "{n}"),
+            Token::Name(name) => write!(f, "'{name}'"),
+            Token::Symbol(c) => write!(f, "'{c}'"),
+        }
+    }
+}
+
+fn tokenize(text: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = text.chars().peekable();
+    while let Some(&c) = chars.peek() {
+        if c.is_whitespace() {
+            chars.next();
+        } else if c.is_ascii_digit() || c == '.' {
+            let mut number = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_ascii_digit() || **c == '.') {
+                number.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Number(number.parse().map_err(|_| format!("invalid number '{number}'"))?));
+        } else if c.is_alphabetic() {
+            let mut name = String::new();
+            while let Some(&c) = chars.peek().filter(|c| c.is_alphanumeric()) {
+                name.push(c);
+                chars.next();
+            }
+            tokens.push(Token::Name(name));
+        } else if "+-*/(),:".contains(c) {
+            tokens.push(Token::Symbol(c));
+            chars.next();
+        } else {
+            return Err(format!("unexpected '{c}'"));
+        }
+    }
+    Ok(tokens)
+}
+
+// Recursive descent, lowest precedence first
+struct Parser {
+    tokens: Vec<Token>,
+    next: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.next)
+    }
+
+    fn take(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.next).cloned();
+        self.next += 1;
+        token
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), String> {
+        match self.take() {
+            Some(Token::Symbol(s)) if s == c => Ok(()),
+            Some(token) => Err(format!("expected '{c}', not {token}")),
+            None => Err(format!("expected '{c}'")),
+        }
+    }
+
+    fn sum(&mut self) -> Result<Expr, String> {
+        let mut expr = self.product()?;
+        while let Some(Token::Symbol(op @ ('+' | '-'))) = self.peek().cloned() {
+            self.next += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.product()?));
+        }
+        Ok(expr)
+    }
+
+    fn product(&mut self) -> Result<Expr, String> {
+        let mut expr = self.unary()?;
+        while let Some(Token::Symbol(op @ ('*' | '/'))) = self.peek().cloned() {
+            self.next += 1;
+            expr = Expr::Binary(op, Box::new(expr), Box::new(self.unary()?));
+        }
+        Ok(expr)
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+        if self.peek() == Some(&Token::Symbol('-')) {
+            self.next += 1;
+            return Ok(Expr::Negate(Box::new(self.unary()?)));
+        }
+        self.atom()
+    }
+
+    fn atom(&mut self) -> Result<Expr, String> {
+        match self.take() {
+            Some(Token::Number(n)) => Ok(Expr::Number(n)),
+            Some(Token::Name(name)) if name == "t" => Ok(Expr::Time),
+            Some(Token::Name(name)) if name == "keys" => self.keys(),
+            Some(Token::Name(name)) => {
+                let f = FUNCTIONS.iter().find(|(n, _)| *n == name)
+                    .ok_or(format!("unknown function '{name}' (expected t or one of: {})",
+                        FUNCTIONS.map(|(n, _)| n).join(", ")))?.1;
+                self.expect('(')?;
+                let arg = self.sum()?;
+                self.expect(')')?;
+                Ok(Expr::Call(f, Box::new(arg)))
+            }
+            // Parentheses around one value, or a vector of three
+            Some(Token::Symbol('(')) => {
+                let first = self.sum()?;
+                if self.peek() != Some(&Token::Symbol(',')) {
+                    self.expect(')')?;
+                    return Ok(first);
+                }
+                self.expect(',')?;
+                let second = self.sum()?;
+                self.expect(',')?;
+                let third = self.sum()?;
+                self.expect(')')?;
+                Ok(Expr::Vector(Box::new([first, second, third])))
+            }
+            Some(token) => Err(format!("unexpected {token}")),
+            None => Err("missing a value".to_string()),
+        }
+    }
+
+    // What follows `keys`: (TIME: VALUE EASE, ...)
+    fn keys(&mut self) -> Result<Expr, String> {
+        self.expect('(')?;
+        let (mut times, mut values, mut eases) = (Vec::new(), Vec::new(), Vec::new());
+        loop {
+            let time = match self.sum()? {
+                time if time.uses_time() => return Err("key times have to be numbers".to_string()),
+                time => match time.eval(0.0)? {
+                    Value::Number(n) => n,
+                    Value::Vector(_) => return Err("key times have to be numbers".to_string()),
+                },
+            };
+            if times.last().is_some_and(|last| time < *last) { return Err("key times can't go down".to_string()); }
+            self.expect(':')?;
+            values.push(self.sum()?);
+            let ease = match self.peek().cloned() {
+                Some(Token::Name(name)) => {
+                    self.next += 1;
+                    Ease::parse(&name).ok_or(format!("unknown easing '{name}' (expected one of: {})", EASE_NAMES.join(", ")))?
+                }
+                _ => Ease::Linear,
+            };
+            times.push(time);
+            eases.push(ease);
+            match self.take() {
+                Some(Token::Symbol(',')) => {}
+                Some(Token::Symbol(')')) => break,
+                Some(token) => return Err(format!("expected ',' or ')', not {token}")),
+                None => return Err("expected ')'".to_string()),
+            }
+        }
+        let vectors: Vec<bool> = values.iter().map(|value| matches!(value.eval(0.0), Ok(Value::Vector(_)))).collect();
+        if vectors.iter().any(|vector| *vector != vectors[0]) { return Err(KEYS_MIXED.to_string()); }
+        Ok(Expr::Keys(times, values, eases))
+    }
+}
+
+const KEYS_MIXED: &str = "keys mix numbers and (x, y, z)";
+
+#[derive(Clone, Copy, PartialEq)]
+enum Owner {
+    Object(usize),
+    Light(usize),
+    Camera,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Property {
+    Position,
+    Scale,
+    Spin,
+    Turn,
+    Reflectivity,
+    Opacity,
+    Emission,
+    Direction,
+    Color,
+    LookAt,
+}
+
+const OBJECT_PROPERTIES: [(&str, Property); 7] = [
+    ("position", Property::Position),
+    ("scale", Property::Scale),
+    ("spin", Property::Spin),
+    ("turn", Property::Turn),
+    ("reflectivity", Property::Reflectivity),
+    ("opacity", Property::Opacity),
+    ("emission", Property::Emission),
+];
+const LIGHT_PROPERTIES: [(&str, Property); 2] = [
+    ("dir", Property::Direction),
+    ("color", Property::Color),
+];
+const CAMERA_PROPERTIES: [(&str, Property); 2] = [
+    ("position", Property::Position),
+    ("target", Property::LookAt),
+];
+
+// The camera before a script moves it: where it starts, looking at the
+// origin
+fn aim(scene: &Scene) -> Aim {
+    scene.camera.unwrap_or(Aim { position: Camera::orbit(DISTANCE).position, target: [0.0; 3] })
+}
+
+#[derive(Clone, Copy, PartialEq)]
+struct Target {
+    owner: Owner,
+    property: Property,
+}
+
+impl Target {
+    fn get(self, scene: &Scene) -> Value {
+        match self.owner {
+            Owner::Object(i) => {
+                let object = &scene.objects[i];
+                match self.property {
+                    Property::Position => Value::Vector(object.position),
+                    Property::Spin => Value::Vector(object.spin),
+                    Property::Turn => Value::Vector(object.turn),
+                    Property::Scale => Value::Number(object.scale),
+                    Property::Reflectivity => Value::Number(object.material.reflectivity),
+                    Property::Opacity => Value::Number(object.material.opacity),
+                    _ => Value::Number(object.material.emission),
+                }
+            }
+            Owner::Light(i) => match self.property {
+                Property::Direction => Value::Vector(scene.lights[i].dir),
+                _ => Value::Vector(scene.lights[i].color),
+            },
+            Owner::Camera => match self.property {
+                Property::Position => Value::Vector(aim(scene).position),
+                _ => Value::Vector(aim(scene).target),
+            },
+        }
+    }
+
+    // Fails when `value` is a number where a vector goes or the other way
+    fn set(self, scene: &mut Scene, value: Value) -> Result<(), String> {
+        let wanted = match (self.get(scene), value) {
+            (Value::Number(_), Value::Number(n)) => {
+                let object = match self.owner {
+                    Owner::Object(i) => &mut scene.objects[i],
+                    Owner::Light(_) | Owner::Camera => unreachable!("lights and the camera have no number properties"),
+                };
+                match self.property {
+                    Property::Scale => object.scale = n,
+                    Property::Reflectivity => object.material.reflectivity = n.clamp(0.0, 1.0),
+                    Property::Opacity => object.material.opacity = n.clamp(0.0, 1.0),
+                    _ => object.material.emission = n.clamp(0.0, 1.0),
+                }
+                return Ok(());
+            }
+            (Value::Vector(_), Value::Vector(v)) => {
+                match (self.owner, self.property) {
+                    (Owner::Object(i), Property::Position) => scene.objects[i].position = v,
+                    (Owner::Object(i), Property::Spin) => scene.objects[i].spin = v,
+                    (Owner::Object(i), _) => scene.objects[i].turn = v,
+                    (Owner::Light(i), Property::Direction) => scene.lights[i].dir = v,
+                    (Owner::Light(i), _) => scene.lights[i].color = v.map(|c| c.max(0.0)),
+                    (Owner::Camera, Property::Position) => scene.camera = Some(Aim { position: v, ..aim(scene) }),
+                    (Owner::Camera, _) => scene.camera = Some(Aim { target: v, ..aim(scene) }),
+                }
+                return Ok(());
+            }
+            (Value::Number(_), _) => "a number",
+            (Value::Vector(_), _) => "(x, y, z)",
+        };
+        Err(format!("expected {wanted}"))
+    }
+}
+
+#[derive(Clone)]
+pub struct Script {
+    // Spawned objects by name, as indices into the scene's objects
+    names: Vec<(String, usize)>,
+    // Properties bound to values using the time
+    bindings: Vec<(Target, Expr)>,
+}
+
+impl Script {
+    pub fn new() -> Script {
+        Script { names: Vec::new(), bindings: Vec::new() }
+    }
+
+    // Whether `statement` is for the script rather than a command: spawning,
+    // or naming a property
+    pub fn handles(statement: &str) -> bool {
+        let first = statement.split(|c: char| c.is_whitespace() || c == '=').next().unwrap_or("");
+        first == "spawn" || first.contains('.')
+    }
+
+    // Run a statement at time `t`, with `dress` giving a spawned object what
+    // every object in the scene gets. Returns what it did
+    pub fn run(&mut self, statement: &str, scene: &mut Scene, t: f32, dress: impl Fn(&mut Object)) -> Result<String, String> {
+        let statement = statement.trim();
+        if let Some(("spawn", shape)) = statement.split_once(char::is_whitespace).or(Some((statement, ""))) {
+            let shape = shape.trim();
+            let mesh = shape_mesh(shape).map_err(|e| format!("cannot spawn: {e}"))?;
+            let mut object = Object::new(mesh);
+            dress(&mut object);
+            scene.objects.push(object);
+            // The first one gets the shape's name, the next ones a number too
+            let shape = shape.split(':').next().unwrap_or(shape);
+            let name = (1..).map(|n| if n == 1 { shape.to_string() } else { format!("{shape}{n}") })
+                .find(|name| self.names.iter().all(|(taken, _)| taken != name))
+                .expect("some number is free");
+            self.names.push((name.clone(), scene.objects.len() - 1));
+            return Ok(format!("spawned {name}"));
+        }
+
+        let (path, value) = match statement.split_once('=') {
+            Some((path, value)) => (path.trim(), Some(value.trim())),
+            None => (statement.trim(), None),
+        };
+        let target = self.target(path, scene)?;
+        let Some(value) = value else { return Ok(format!("{path} = {}", target.get(scene))); };
+        let at = |e: String| format!("{path}: {e}");
+        let expr = Expr::parse(value).map_err(at)?;
+        let shown = expr.eval(t).map_err(at)?;
+        target.set(scene, shown).map_err(at)?;
+        self.bindings.retain(|(bound, _)| *bound != target);
+        if expr.uses_time() {
+            self.bindings.push((target, expr));
+            return Ok(format!("{path} follows {value}"));
+        }
+        // What it was set to, after any clamping
+        Ok(format!("{path} = {}", target.get(scene)))
+    }
+
+    // The property at `path` as a statement would write it, unrounded
+    pub fn value(&self, path: &str, scene: &Scene) -> Result<String, String> {
+        Ok(match self.target(path, scene)?.get(scene) {
+            Value::Number(n) => n.to_string(),
+            Value::Vector([x, y, z]) => format!("({x}, {y}, {z})"),
+        })
+    }
+
+    // Call the object at `index` `name` too, if nothing else is
+    pub fn name(&mut self, name: &str, index: usize) {
+        if self.names.iter().all(|(taken, _)| taken != name) {
+            self.names.push((name.to_string(), index));
+        }
+    }
+
+    // Set the bound properties for time `t`
+    pub fn update(&self, scene: &mut Scene, t: f32) {
+        for (target, expr) in &self.bindings {
+            // They had the right type when they were bound
+            if let Ok(value) = expr.eval(t) {
+                let _ = target.set(scene, value);
+            }
+        }
+    }
+
+    fn target(&self, path: &str, scene: &Scene) -> Result<Target, String> {
+        let (owner_name, property) = path.split_once('.').ok_or(format!("'{path}' names no property"))?;
+        let numbered = |prefix: &str| owner_name.strip_prefix(prefix)
+            .and_then(|n| if n.is_empty() { Some(1) } else { n.parse::<usize>().ok() });
+        let owner = if let Some((_, i)) = self.names.iter().find(|(name, _)| name == owner_name) {
+            Owner::Object(*i)
+        } else if let Some(n) = numbered("object").filter(|n| (1..=scene.objects.len()).contains(n)) {
+            Owner::Object(n - 1)
+        } else if let Some(n) = numbered("light").filter(|n| (1..=scene.lights.len()).contains(n)) {
+            Owner::Light(n - 1)
+        } else if owner_name == "camera" {
+            Owner::Camera
+        } else {
+            return Err(format!("no object, light or camera called '{owner_name}'"));
+        };
+        let properties: &[(&str, Property)] = match owner {
+            Owner::Object(_) => &OBJECT_PROPERTIES,
+            Owner::Light(_) => &LIGHT_PROPERTIES,
+            Owner::Camera => &CAMERA_PROPERTIES,
+        };
+        let property = properties.iter().find(|(name, _)| *name == property)
+            .ok_or(format!("{owner_name} has no {property} (expected one of: {})",
+                properties.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")))?.1;
+        Ok(Target { owner, property })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scene::model_scene;
+
+    fn number(text: &str, t: f32) -> f32 {
+        match Expr::parse(text).and_then(|expr| expr.eval(t)) {
+            Ok(Value::Number(n)) => n,
+            other => panic!("{text} is {:?}", other.map(|value| value.to_string())),
+        }
+    }
+
+    fn vector(text: &str, t: f32) -> [f32; 3] {
+        match Expr::parse(text).and_then(|expr| expr.eval(t)) {
+            Ok(Value::Vector(v)) => v,
+            other => panic!("{text} is {:?}", other.map(|value| value.to_string())),
+        }
+    }
+
+    fn error(text: &str) -> String {
+        Expr::parse(text).and_then(|expr| expr.eval(0.0)).err().unwrap_or_else(|| panic!("{text} has a value"))
+    }
+
+    #[test]
+    fn precedence() {
+        assert_eq!(number("1 + 2 * 3", 0.0), 7.0);
+        assert_eq!(number("(1 + 2) * 3", 0.0), 9.0);
+        assert_eq!(number("-2 * 3 + 1", 0.0), -5.0);
+        assert_eq!(number("1 - 2 - 3", 0.0), -4.0);
+        assert_eq!(number("8 / 2 / 2", 0.0), 2.0);
+        assert_eq!(number("2 * t - 1", 3.0), 5.0);
+        assert_eq!(vector("2 * (1, 2, 3) + (1, 0, 0)", 0.0), [3.0, 4.0, 6.0]);
+        assert_eq!(vector("-(1, t, 0) / 2", 4.0), [-0.5, -2.0, 0.0]);
+    }
+
+    #[test]
+    fn function_calls() {
+        assert_eq!(number("sin(0) + cos(0)", 0.0), 1.0);
+        assert_eq!(number("abs(-3) + sqrt(16)", 0.0), 7.0);
+        assert_eq!(number("floor(t) * 10 + fract(t)", 2.5), 20.5);
+        assert_eq!(vector("(sin(0), 2 * abs(t), 0)", -1.0), [0.0, 2.0, 0.0]);
+    }
+
+    #[test]
+    fn keys_ease_between_values() {
+        let keys = "keys(0: 0, 2: 10, 4: 0 cubic)";
+        assert_eq!(number(keys, -1.0), 0.0);
+        assert_eq!(number(keys, 1.0), 5.0);
+        assert_eq!(number(keys, 2.0), 10.0);
+        assert_eq!(number(keys, 9.0), 0.0);
+        let eased = number(keys, 2.5);
+        assert!(eased > 7.5 && eased < 10.0, "cubic starts slow, got {eased}");
+        assert_eq!(vector("keys(0: (0, 0, 0), 1: (2, 4, 6))", 0.5), [1.0, 2.0, 3.0]);
+        assert_eq!(number("keys(0: 1, 2: 3 bounce)", 2.0), 3.0);
+    }
+
+    #[test]
+    fn bad_expressions() {
+        assert_eq!(error("1 +"), "missing a value");
+        assert_eq!(error("1 $ 2"), "unexpected '$'");
+        assert_eq!(error("1 2"), "unexpected 2 in '1 2'");
+        assert_eq!(error("(1, 2)"), "expected ',', not ')'");
+        assert_eq!(error("wave(t)"), "unknown function 'wave' (expected t or one of: sin, cos, abs, sqrt, floor, fract)");
+        assert_eq!(error("sin((1, 2, 3))"), "expected a number, not (x, y, z)");
+        assert_eq!(error("(1, 2, 3) * (1, 2, 3)"), "cannot * these");
+        assert_eq!(error("keys(1: 0, 0: 1)"), "key times can't go down");
+        assert_eq!(error("keys(t: 0)"), "key times have to be numbers");
+        assert_eq!(error("keys(0: 1, 1: (1, 2, 3))"), KEYS_MIXED);
+        assert!(error("keys(0: 1 wobble)").starts_with("unknown easing 'wobble'"));
+    }
+
+    #[test]
+    fn assigning_properties() {
+        let mut scene = model_scene(shape_mesh("cube").expect("the cube is built in"));
+        let mut script = Script::new();
+        let mut run = |statement: &str, scene: &mut Scene| script.run(statement, scene, 0.0, |_| {});
+        assert_eq!(run("object1.scale = 2", &mut scene).as_deref(), Ok("object1.scale = 2.00"));
+        assert_eq!(scene.objects[0].scale, 2.0);
+        assert_eq!(run("object1.opacity = 5", &mut scene).as_deref(), Ok("object1.opacity = 1.00"));
+        assert_eq!(run("object1.scale = (1, 2, 3)", &mut scene), Err("object1.scale: expected a number".to_string()));
+        assert_eq!(run("object1.position = 1", &mut scene), Err("object1.position: expected (x, y, z)".to_string()));
+        assert_eq!(run("object2.scale = 1", &mut scene), Err("no object, light or camera called 'object2'".to_string()));
+        assert!(run("object1.size = 1", &mut scene).is_err_and(|e| e.starts_with("object1 has no size (expected one of: position, scale")));
+        assert!(run("object1.scale = 1 +", &mut scene).is_err_and(|e| e == "object1.scale: missing a value"));
+        assert_eq!(run("camera.target = (1, 0, 0)", &mut scene).as_deref(), Ok("camera.target = (1.00, 0.00, 0.00)"));
+        assert!(scene.camera.is_some_and(|aim| aim.target == [1.0, 0.0, 0.0]));
+        assert_eq!(run("object1.position = (t, 0, 0)", &mut scene).as_deref(), Ok("object1.position follows (t, 0, 0)"));
+        script.update(&mut scene, 3.0);
+        assert_eq!(scene.objects[0].position, [3.0, 0.0, 0.0]);
+        assert_eq!(script.value("object1.position", &scene).as_deref(), Ok("(3, 0, 0)"));
+    }
+}
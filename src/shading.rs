@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use clap::ValueEnum;
+
+use crate::math::{dot, get_face_normal, length, sub};
+use crate::scene::Face;
+
+// How light intensity is turned into characters
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Shading {
+    // Continuous intensity mapped onto the shade ramp
+    Smooth,
+    // Cel shading: a few flat bands plus silhouette outlines
+    Toon,
+    // Pen-and-ink strokes following the surface orientation
+    Hatch,
+}
+
+impl Shading {
+    pub fn parse(s: &str) -> Option<Shading> {
+        Shading::from_str(s, false).ok()
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shading::Smooth => "smooth",
+            Shading::Toon => "toon",
+            Shading::Hatch => "hatch",
+        }
+    }
+
+    // The model after this one when cycling at runtime
+    pub fn next(self) -> Shading {
+        match self {
+            Shading::Smooth => Shading::Toon,
+            Shading::Toon => Shading::Hatch,
+            Shading::Hatch => Shading::Smooth,
+        }
+    }
+
+    pub fn intensity(&self, intensity: f32) -> f32 {
+        match self {
+            Shading::Smooth | Shading::Hatch => intensity,
+            Shading::Toon => TOON_BANDS.iter()
+                .find(|(limit, _)| intensity < *limit)
+                .map_or(1.0, |(_, level)| *level),
+        }
+    }
+}
+
+// Brings light intensity into 0.0..=1.0 before it is turned into characters
+// and colors. Where several lights add up intensity can pass 1.0
+#[derive(Clone, Copy, PartialEq)]
+pub struct ToneMap {
+    // Intensities are raised to 1 / gamma, above 1.0 brightens midtones
+    pub gamma: f32,
+    // Compress highlights with extended Reinhard instead of clipping them
+    pub reinhard: bool,
+}
+
+impl Default for ToneMap {
+    fn default() -> ToneMap {
+        ToneMap { gamma: 1.0, reinhard: false }
+    }
+}
+
+impl ToneMap {
+    // `white` is the brightest intensity the lights can reach, which the
+    // Reinhard curve maps to exactly 1.0
+    pub fn apply(&self, intensity: f32, white: f32) -> f32 {
+        let mapped = if self.reinhard && white > 1.0 {
+            intensity * (1.0 + intensity / (white * white)) / (1.0 + intensity)
+        } else {
+            intensity
+        };
+        mapped.clamp(0.0, 1.0).powf(1.0 / self.gamma)
+    }
+}
+
+// (upper bound, flat intensity) per toon band
+const TOON_BANDS: [(f32, f32); 3] = [(0.2, 0.15), (0.6, 0.5), (f32::MAX, 0.95)];
+
+pub const OUTLINE_CHAR: char = '█';
+
+// Edges where a front-facing face meets a back-facing one (or the mesh ends),
+// i.e. the outline of the object as seen from `eye`
+pub fn silhouette_edges(faces: &[Face], verts: &[[f32; 3]], eye: [f32; 3]) -> Vec<(usize, usize)> {
+    // (front-facing count, total count) per undirected edge
+    let mut edges: HashMap<(usize, usize), (u32, u32)> = HashMap::new();
+    for face in faces {
+        let n = face.indices.len();
+        if n < 3 { continue; }
+        let (v0, v1, v2) = (verts[face.indices[0]], verts[face.indices[1]], verts[face.indices[2]]);
+        let front = dot(get_face_normal(v0, v1, v2), sub(eye, v0)) > 0.0;
+
+        for k in 0..n {
+            let (a, b) = (face.indices[k], face.indices[(k + 1) % n]);
+            let entry = edges.entry((a.min(b), a.max(b))).or_insert((0, 0));
+            entry.0 += front as u32;
+            entry.1 += 1;
+        }
+    }
+
+    let mut outline: Vec<(usize, usize)> = edges.into_iter()
+        .filter(|(_, (front, total))| *front == 1 && *total <= 2)
+        .map(|(edge, _)| edge)
+        .collect();
+    outline.sort_unstable();
+    outline
+}
+
+// Screen direction of a hatching stroke
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Stroke {
+    Horizontal,
+    Rising,
+    Vertical,
+    Falling,
+}
+
+impl Stroke {
+    fn char(self) -> char {
+        match self {
+            Stroke::Horizontal => '-',
+            Stroke::Rising => '/',
+            Stroke::Vertical => '|',
+            Stroke::Falling => '\\',
+        }
+    }
+
+    fn crossing(self) -> Stroke {
+        match self {
+            Stroke::Horizontal => Stroke::Vertical,
+            Stroke::Rising => Stroke::Falling,
+            Stroke::Vertical => Stroke::Horizontal,
+            Stroke::Falling => Stroke::Rising,
+        }
+    }
+}
+
+// Strokes run across the surface, perpendicular to its normal as projected
+// on screen. Faces looking straight at the camera get diagonal strokes.
+pub fn hatch_stroke(normal: [f32; 3]) -> Stroke {
+    let (nx, ny) = (normal[0], normal[1]);
+    if nx.hypot(ny) < 1e-3 * length(normal) { return Stroke::Rising; }
+
+    // Angle of the stroke direction (-ny, nx), folded into 0..180 degrees
+    let angle = (nx.atan2(-ny).to_degrees() + 180.0) % 180.0;
+    match ((angle + 22.5) / 45.0) as u32 % 4 {
+        0 => Stroke::Horizontal,
+        1 => Stroke::Rising,
+        2 => Stroke::Vertical,
+        _ => Stroke::Falling,
+    }
+}
+
+// Bright surfaces get sparse strokes, mid tones solid strokes, darker ones
+// alternate with the crossing direction and the darkest are cross-hatched
+pub fn hatch_char(stroke: Stroke, intensity: f32, x: usize, y: usize) -> char {
+    if intensity >= 0.8 {
+        if (x + 2 * y).is_multiple_of(4) { stroke.char() } else { ' ' }
+    } else if intensity >= 0.5 {
+        stroke.char()
+    } else if intensity >= 0.25 {
+        if (x + y).is_multiple_of(2) { stroke.char() } else { stroke.crossing().char() }
+    } else {
+        'X'
+    }
+}
@@ -0,0 +1,116 @@
+use clap::ValueEnum;
+
+use crate::math::{cross, dot, normalize};
+
+// How shadows are computed, from cheapest to most accurate
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ShadowQuality {
+    Off,
+    // Object silhouettes projected onto the ground plane only
+    Ground,
+    // Light-space depth map, so objects also shadow each other
+    Map,
+}
+
+const SHADOW_MAP_SIZE: usize = 128;
+
+// Orthographic depth map rendered along a directional light
+pub struct ShadowMap {
+    u: [f32; 3],
+    v: [f32; 3],
+    dir: [f32; 3],
+    origin: [f32; 2],
+    texel: f32,
+    bias: f32,
+    depth: Vec<f32>,
+}
+
+impl ShadowMap {
+    // `light_dir` points towards the light; `polygons` are world-space
+    // occluders (any face, whichever way it points)
+    pub fn build(light_dir: [f32; 3], polygons: &[Vec<[f32; 3]>]) -> ShadowMap {
+        let dir = normalize(light_dir);
+        let helper = if dir[1].abs() < 0.9 { [0.0, 1.0, 0.0] } else { [1.0, 0.0, 0.0] };
+        let u = normalize(cross(helper, dir));
+        let v = cross(dir, u);
+
+        let mut min = [f32::MAX; 2];
+        let mut max = [f32::MIN; 2];
+        for p in polygons.iter().flatten() {
+            let (a, b) = (dot(*p, u), dot(*p, v));
+            min = [min[0].min(a), min[1].min(b)];
+            max = [max[0].max(a), max[1].max(b)];
+        }
+        let extent = (max[0] - min[0]).max(max[1] - min[1]).max(1e-3);
+        let texel = extent / (SHADOW_MAP_SIZE - 1) as f32;
+
+        let mut map = ShadowMap {
+            u,
+            v,
+            dir,
+            origin: min,
+            texel,
+            bias: texel * 1.5,
+            depth: vec![f32::MAX; SHADOW_MAP_SIZE * SHADOW_MAP_SIZE],
+        };
+
+        for polygon in polygons {
+            let projected: Vec<[f32; 3]> = polygon.iter().map(|p| map.to_light(*p)).collect();
+            // Fan triangulation of the convex polygon
+            for i in 1..projected.len().saturating_sub(1) {
+                map.raster_triangle(projected[0], projected[i], projected[i + 1]);
+            }
+        }
+
+        map
+    }
+
+    // Texel coordinates plus distance away from the light
+    fn to_light(&self, p: [f32; 3]) -> [f32; 3] {
+        [
+            (dot(p, self.u) - self.origin[0]) / self.texel,
+            (dot(p, self.v) - self.origin[1]) / self.texel,
+            -dot(p, self.dir),
+        ]
+    }
+
+    fn raster_triangle(&mut self, a: [f32; 3], b: [f32; 3], c: [f32; 3]) {
+        let area = (b[0] - a[0]) * (c[1] - a[1]) - (b[1] - a[1]) * (c[0] - a[0]);
+        if area.abs() < 1e-6 { return; }
+
+        let max_i = (SHADOW_MAP_SIZE - 1) as f32;
+        let x0 = a[0].min(b[0]).min(c[0]).floor().max(0.0) as usize;
+        let x1 = a[0].max(b[0]).max(c[0]).ceil().min(max_i) as usize;
+        let y0 = a[1].min(b[1]).min(c[1]).floor().max(0.0) as usize;
+        let y1 = a[1].max(b[1]).max(c[1]).ceil().min(max_i) as usize;
+
+        for y in y0..=y1 {
+            for x in x0..=x1 {
+                let (px, py) = (x as f32, y as f32);
+                let w0 = ((b[0] - px) * (c[1] - py) - (b[1] - py) * (c[0] - px)) / area;
+                let w1 = ((c[0] - px) * (a[1] - py) - (c[1] - py) * (a[0] - px)) / area;
+                let w2 = 1.0 - w0 - w1;
+                if w0 < 0.0 || w1 < 0.0 || w2 < 0.0 { continue; }
+
+                let z = w0 * a[2] + w1 * b[2] + w2 * c[2];
+                let idx = y * SHADOW_MAP_SIZE + x;
+                if z < self.depth[idx] {
+                    self.depth[idx] = z;
+                }
+            }
+        }
+    }
+
+    // Whether something sits between `p` and the light. The depth bias grows
+    // as the surface at `p` (with unit `normal`) turns away from the light.
+    pub fn occluded(&self, p: [f32; 3], normal: [f32; 3]) -> bool {
+        let [x, y, z] = self.to_light(p);
+        let (xi, yi) = (x.round(), y.round());
+        if xi < 0.0 || yi < 0.0 || xi >= SHADOW_MAP_SIZE as f32 || yi >= SHADOW_MAP_SIZE as f32 {
+            return false;
+        }
+
+        let bias = self.bias / dot(normal, self.dir).max(0.25);
+        z > self.depth[yi as usize * SHADOW_MAP_SIZE + xi as usize] + bias
+    }
+}
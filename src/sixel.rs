@@ -0,0 +1,68 @@
+// Sixel graphics: the image is sent as bands six pixels tall, each column
+// of a band one character encoding which of its six pixels are set
+
+use std::io::Write;
+
+use crate::color::{ansi256_rgb, nearest_ansi256};
+use crate::pixels::Bitmap;
+
+// Whether the terminal is known to display sixel images. There is no
+// reliable way to ask without reading the reply from the terminal, so this
+// goes by name
+pub fn sixel_supported() -> bool {
+    let term = std::env::var("TERM").unwrap_or_default();
+    let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+    term.starts_with("foot") || term.starts_with("mlterm") || term.contains("sixel") || program == "WezTerm"
+}
+
+// Write `image` as a sixel sequence at the cursor. Colors go through the
+// xterm 256-color palette, so the image needs at most 256 color registers.
+// Only whole bands are sent, so the image never reaches past its cells
+pub fn write_sixel(out: &mut impl Write, image: &Bitmap) -> std::io::Result<()> {
+    let height = image.height - image.height % 6;
+    let indices: Vec<u8> = image.pixels.iter().map(|&rgb| nearest_ansi256(rgb)).collect();
+
+    write!(out, "\x1bP0;1;0q\"1;1;{};{}", image.width, height)?;
+    let mut used = [false; 256];
+    for &i in &indices[..image.width * height] { used[i as usize] = true; }
+    for (i, _) in used.iter().enumerate().filter(|(_, u)| **u) {
+        let [r, g, b] = ansi256_rgb(i as u8).map(|c| c as u32 * 100 / 255);
+        write!(out, "#{i};2;{r};{g};{b}")?;
+    }
+
+    let mut row = vec![0u8; image.width];
+    for band in (0..height).step_by(6) {
+        let mut in_band = [false; 256];
+        for y in band..band + 6 {
+            for &i in &indices[y * image.width..(y + 1) * image.width] { in_band[i as usize] = true; }
+        }
+        for (color, _) in in_band.iter().enumerate().filter(|(_, u)| **u) {
+            for (x, bits) in row.iter_mut().enumerate() {
+                *bits = (0..6).filter(|dy| indices[(band + dy) * image.width + x] as usize == color)
+                    .fold(0, |acc, dy| acc | 1 << dy);
+            }
+            write!(out, "#{color}")?;
+            write_runs(out, &row)?;
+            // Back to the start of the band for the next color
+            out.write_all(b"$")?;
+        }
+        out.write_all(b"-")?;
+    }
+    out.write_all(b"\x1b\\")
+}
+
+// One band's worth of a single color, with repeats run-length encoded
+fn write_runs(out: &mut impl Write, row: &[u8]) -> std::io::Result<()> {
+    let mut x = 0;
+    while x < row.len() {
+        let run = row[x..].iter().take_while(|&&b| b == row[x]).count();
+        let c = (b'?' + row[x]) as char;
+        if run > 3 {
+            write!(out, "!{run}{c}")?;
+        } else {
+            for _ in 0..run { write!(out, "{c}")?; }
+        }
+        x += run;
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+// Smooth camera moves for scene files: the camera travels along a curve
+// through or shaped by a list of points, at the same speed all the way, and
+// looks at a point or along a curve of its own
+//
+//   [camera]
+//   spline = "catmull-rom"
+//   points = [[0, 1, -6], [6, 2, 0], [0, 3, 6]]
+//   target = [0, 0, 0]
+//   duration = 8
+//
+// Catmull-Rom curves go through every point. Bezier curves are cubic
+// pieces of four points each, sharing their ends, so 4, 7, 10... points:
+// each piece goes through its first and last and is pulled towards the two
+// between them. Over `duration` seconds the camera goes from one end to the
+// other, staying there after
+
+use crate::camera::{Aim, Camera};
+use crate::math::{add, length, scale, sub};
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum SplineKind {
+    CatmullRom,
+    Bezier,
+}
+
+pub const SPLINE_NAMES: [&str; 2] = ["catmull-rom", "bezier"];
+
+impl SplineKind {
+    pub fn parse(s: &str) -> Option<SplineKind> {
+        match s {
+            "catmull-rom" => Some(SplineKind::CatmullRom),
+            "bezier" => Some(SplineKind::Bezier),
+            _ => None,
+        }
+    }
+}
+
+// Points measured along each piece of a curve to tell how long it is
+const SAMPLES: usize = 32;
+
+pub struct Spline {
+    kind: SplineKind,
+    points: Vec<[f32; 3]>,
+    // How far along the curve each sample is, from 0 at the start, with
+    // the curve's parameter there
+    lengths: Vec<(f32, f32)>,
+}
+
+impl Spline {
+    pub fn new(kind: SplineKind, points: Vec<[f32; 3]>) -> Result<Spline, String> {
+        match kind {
+            SplineKind::CatmullRom if points.len() < 2 => return Err("a catmull-rom spline needs 2 points or more".to_string()),
+            SplineKind::Bezier if points.len() < 4 || points.len() % 3 != 1 => {
+                return Err(format!("a bezier spline needs 4, 7, 10... points, not {}", points.len()));
+            }
+            _ => {}
+        }
+        let mut spline = Spline { kind, points, lengths: Vec::new() };
+        let steps = spline.pieces() * SAMPLES;
+        let mut last = spline.point(0.0);
+        let mut travelled = 0.0;
+        spline.lengths.push((0.0, 0.0));
+        for i in 1..=steps {
+            let u = i as f32 / SAMPLES as f32;
+            let point = spline.point(u);
+            travelled += length(sub(point, last));
+            spline.lengths.push((travelled, u));
+            last = point;
+        }
+        Ok(spline)
+    }
+
+    fn pieces(&self) -> usize {
+        match self.kind {
+            SplineKind::CatmullRom => self.points.len() - 1,
+            SplineKind::Bezier => (self.points.len() - 1) / 3,
+        }
+    }
+
+    // The point at `u`, whole numbers being where pieces meet
+    fn point(&self, u: f32) -> [f32; 3] {
+        let piece = (u.floor() as usize).min(self.pieces() - 1);
+        let s = u - piece as f32;
+        let weighted = |points: [[f32; 3]; 4], weights: [f32; 4]| {
+            (0..4).fold([0.0; 3], |sum, i| add(sum, scale(points[i], weights[i])))
+        };
+        match self.kind {
+            SplineKind::CatmullRom => {
+                // The ends are repeated for the pieces at either end
+                let at = |i: isize| self.points[i.clamp(0, self.points.len() as isize - 1) as usize];
+                let i = piece as isize;
+                let (s2, s3) = (s * s, s * s * s);
+                weighted([at(i - 1), at(i), at(i + 1), at(i + 2)], [
+                    (-s3 + 2.0 * s2 - s) / 2.0,
+                    (3.0 * s3 - 5.0 * s2 + 2.0) / 2.0,
+                    (-3.0 * s3 + 4.0 * s2 + s) / 2.0,
+                    (s3 - s2) / 2.0,
+                ])
+            }
+            SplineKind::Bezier => {
+                let r = 1.0 - s;
+                let p = &self.points[piece * 3..piece * 3 + 4];
+                weighted([p[0], p[1], p[2], p[3]], [r * r * r, 3.0 * r * r * s, 3.0 * r * s * s, s * s * s])
+            }
+        }
+    }
+
+    // The point `share` of the way along the curve by its length, from 0 to
+    // 1, so even steps of `share` move it the same distance
+    pub fn along(&self, share: f32) -> [f32; 3] {
+        let total = self.lengths.last().map_or(0.0, |(length, _)| *length);
+        let goal = share.clamp(0.0, 1.0) * total;
+        let next = self.lengths.partition_point(|(length, _)| *length < goal).clamp(1, self.lengths.len() - 1);
+        let ((l0, u0), (l1, u1)) = (self.lengths[next - 1], self.lengths[next]);
+        let part = if l1 > l0 { (goal - l0) / (l1 - l0) } else { 0.0 };
+        self.point(u0 + (u1 - u0) * part)
+    }
+}
+
+// Where the camera looks while it travels
+pub enum Target {
+    Point([f32; 3]),
+    Spline(Spline),
+}
+
+pub struct CameraSpline {
+    pub position: Spline,
+    pub target: Target,
+    pub duration: f32,
+}
+
+impl CameraSpline {
+    // The camera `t` seconds in
+    pub fn at(&self, t: f32) -> Camera {
+        let share = t / self.duration;
+        let position = self.position.along(share);
+        let target = match &self.target {
+            Target::Point(point) => *point,
+            Target::Spline(spline) => spline.along(share),
+        };
+        Aim { position, target }.camera()
+    }
+}
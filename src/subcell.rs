@@ -0,0 +1,160 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+use crate::color::{color_to_rgb, rgb_color, shade_rgb};
+use crate::framebuffer::{bayer_threshold, Cell, FrameBuffer, Glyph, QUADRANT_CHARS};
+
+// Characters that split a terminal cell into a grid of dots, for a finer
+// effective resolution. The frame is rendered at the finer resolution and
+// each grid of samples packed into one character
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum SubCell {
+    Off,
+    // 2x4 braille dots, U+2800 to U+28FF
+    Braille,
+    // 2x2 quadrant blocks
+    Quadrant,
+    // 2x3 sextant blocks, U+1FB00 onwards
+    Sextant,
+    // 2x4 octant blocks, added in Unicode 16
+    Octant,
+}
+
+impl SubCell {
+    // The finest blocks the terminal can be trusted to draw. Terminals that
+    // draw block elements themselves don't depend on the font covering the
+    // newer ranges; everywhere else quadrants are the safe choice
+    pub fn detect() -> SubCell {
+        let term = std::env::var("TERM").unwrap_or_default();
+        let program = std::env::var("TERM_PROGRAM").unwrap_or_default();
+        if term == "xterm-kitty" || term.starts_with("foot") || term == "xterm-ghostty" || program == "WezTerm" {
+            return SubCell::Octant;
+        }
+        SubCell::Quadrant
+    }
+
+    // Samples per cell across and down
+    pub fn grid(self) -> Option<(usize, usize)> {
+        match self {
+            SubCell::Off => None,
+            SubCell::Braille | SubCell::Octant => Some((2, 4)),
+            SubCell::Quadrant => Some((2, 2)),
+            SubCell::Sextant => Some((2, 3)),
+        }
+    }
+
+    // Character for a mask of lit samples, bit `row * columns + column`
+    fn glyph(self, mask: u8) -> char {
+        match self {
+            SubCell::Off => ' ',
+            SubCell::Braille => braille(mask),
+            SubCell::Quadrant => QUADRANT_CHARS[mask as usize],
+            SubCell::Sextant => sextant(mask),
+            SubCell::Octant => OCTANT_CHARS[mask as usize],
+        }
+    }
+}
+
+// Braille numbers its dots down the left column first, with the bottom row
+// added last: 1 2 3 7 on the left, 4 5 6 8 on the right
+const BRAILLE_DOTS: [u8; 8] = [0x01, 0x08, 0x02, 0x10, 0x04, 0x20, 0x40, 0x80];
+
+fn braille(mask: u8) -> char {
+    let dots = (0..8).filter(|i| mask & (1 << i) != 0).fold(0u32, |acc, i| acc | BRAILLE_DOTS[i] as u32);
+    char::from_u32(0x2800 + dots).unwrap_or(' ')
+}
+
+// Sextants follow the mask directly, except for the four patterns that
+// already existed as block elements, which the range skips
+fn sextant(mask: u8) -> char {
+    match mask {
+        0 => ' ',
+        0b010101 => '▌',
+        0b101010 => '▐',
+        0b111111 => '█',
+        _ => {
+            let skipped = (mask > 0b010101) as u32 + (mask > 0b101010) as u32;
+            char::from_u32(0x1FB00 + mask as u32 - 1 - skipped).unwrap_or(' ')
+        }
+    }
+}
+
+// Octants by mask. The range is filled out with older block elements for
+// the patterns that already had one, so it doesn't follow the mask
+const OCTANT_CHARS: [char; 256] = [
+    ' ', '𜺨', '𜺫', '🮂', '𜴀', '▘', '𜴁', '𜴂', '𜴃', '𜴄', '▝', '𜴅', '𜴆', '𜴇', '𜴈', '▀',
+    '𜴉', '𜴊', '𜴋', '𜴌', '🯦', '𜴍', '𜴎', '𜴏', '𜴐', '𜴑', '𜴒', '𜴓', '𜴔', '𜴕', '𜴖', '𜴗',
+    '𜴘', '𜴙', '𜴚', '𜴛', '𜴜', '𜴝', '𜴞', '𜴟', '🯧', '𜴠', '𜴡', '𜴢', '𜴣', '𜴤', '𜴥', '𜴦',
+    '𜴧', '𜴨', '𜴩', '𜴪', '𜴫', '𜴬', '𜴭', '𜴮', '𜴯', '𜴰', '𜴱', '𜴲', '𜴳', '𜴴', '𜴵', '🮅',
+    '𜺣', '𜴶', '𜴷', '𜴸', '𜴹', '𜴺', '𜴻', '𜴼', '𜴽', '𜴾', '𜴿', '𜵀', '𜵁', '𜵂', '𜵃', '𜵄',
+    '▖', '𜵅', '𜵆', '𜵇', '𜵈', '▌', '𜵉', '𜵊', '𜵋', '𜵌', '▞', '𜵍', '𜵎', '𜵏', '𜵐', '▛',
+    '𜵑', '𜵒', '𜵓', '𜵔', '𜵕', '𜵖', '𜵗', '𜵘', '𜵙', '𜵚', '𜵛', '𜵜', '𜵝', '𜵞', '𜵟', '𜵠',
+    '𜵡', '𜵢', '𜵣', '𜵤', '𜵥', '𜵦', '𜵧', '𜵨', '𜵩', '𜵪', '𜵫', '𜵬', '𜵭', '𜵮', '𜵯', '𜵰',
+    '𜺠', '𜵱', '𜵲', '𜵳', '𜵴', '𜵵', '𜵶', '𜵷', '𜵸', '𜵹', '𜵺', '𜵻', '𜵼', '𜵽', '𜵾', '𜵿',
+    '𜶀', '𜶁', '𜶂', '𜶃', '𜶄', '𜶅', '𜶆', '𜶇', '𜶈', '𜶉', '𜶊', '𜶋', '𜶌', '𜶍', '𜶎', '𜶏',
+    '▗', '𜶐', '𜶑', '𜶒', '𜶓', '▚', '𜶔', '𜶕', '𜶖', '𜶗', '▐', '𜶘', '𜶙', '𜶚', '𜶛', '▜',
+    '𜶜', '𜶝', '𜶞', '𜶟', '𜶠', '𜶡', '𜶢', '𜶣', '𜶤', '𜶥', '𜶦', '𜶧', '𜶨', '𜶩', '𜶪', '𜶫',
+    '▂', '𜶬', '𜶭', '𜶮', '𜶯', '𜶰', '𜶱', '𜶲', '𜶳', '𜶴', '𜶵', '𜶶', '𜶷', '𜶸', '𜶹', '𜶺',
+    '𜶻', '𜶼', '𜶽', '𜶾', '𜶿', '𜷀', '𜷁', '𜷂', '𜷃', '𜷄', '𜷅', '𜷆', '𜷇', '𜷈', '𜷉', '𜷊',
+    '𜷋', '𜷌', '𜷍', '𜷎', '𜷏', '𜷐', '𜷑', '𜷒', '𜷓', '𜷔', '𜷕', '𜷖', '𜷗', '𜷘', '𜷙', '𜷚',
+    '▄', '𜷛', '𜷜', '𜷝', '𜷞', '▙', '𜷟', '𜷠', '𜷡', '𜷢', '▟', '𜷣', '▆', '𜷤', '𜷥', '█',
+];
+
+// Pack a frame rendered at `kind`'s finer resolution into one character per
+// cell. Edges and other fixed glyphs light their samples; shaded samples are
+// lit through an ordered dither, so intensity turns into dot density. The
+// cell takes the average color of its lit samples
+pub fn pack_subcells(fine: &FrameBuffer, kind: SubCell, truecolor: bool) -> FrameBuffer {
+    let (cols, rows) = kind.grid().expect("packing needs a sub-cell grid");
+    let (width, height) = (fine.width / cols, fine.height / rows);
+    let mut out = FrameBuffer::new(width, height);
+
+    for cy in 0..height {
+        for cx in 0..width {
+            let mut mask = 0u8;
+            let mut depth = f32::MAX;
+            let mut rgb = [0.0f32; 3];
+            let mut colors: Vec<(Color, usize)> = Vec::new();
+
+            for sy in 0..rows {
+                for sx in 0..cols {
+                    let (fx, fy) = (cx * cols + sx, cy * rows + sy);
+                    let idx = fy * fine.width + fx;
+                    let cell = fine.cells[idx];
+                    if cell.is_empty() { continue; }
+                    let lit = match cell.glyph {
+                        Glyph::Fixed(_) => true,
+                        _ => cell.intensity > bayer_threshold(fx as i32, fy as i32),
+                    };
+                    if !lit { continue; }
+
+                    mask |= 1 << (sy * cols + sx);
+                    depth = depth.min(fine.depth[idx]);
+                    let color = match cell.glyph {
+                        Glyph::Fixed(_) => cell.color,
+                        _ if truecolor => shade_rgb(cell.color, cell.intensity),
+                        _ => cell.color,
+                    };
+                    for (acc, c) in rgb.iter_mut().zip(color_to_rgb(color)) {
+                        *acc += c as f32 / 255.0;
+                    }
+                    match colors.iter_mut().find(|(c, _)| *c == color) {
+                        Some(entry) => entry.1 += 1,
+                        None => colors.push((color, 1)),
+                    }
+                }
+            }
+            if mask == 0 { continue; }
+
+            let lit = mask.count_ones() as f32;
+            let color = if truecolor {
+                rgb_color(rgb.map(|c| c / lit), true)
+            } else {
+                colors.iter().max_by_key(|(_, count)| *count).map_or(Color::White, |(c, _)| *c)
+            };
+            out.plot(cx as i32, cy as i32, depth, Cell::glyph(kind.glyph(mask), color));
+        }
+    }
+
+    out
+}
@@ -0,0 +1,135 @@
+use std::io::{stdout, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crossterm::{
+    cursor::{Hide, Show},
+    event::{DisableMouseCapture, EnableMouseCapture, KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags},
+    execute,
+    style::ResetColor,
+    terminal::{disable_raw_mode, enable_raw_mode, supports_keyboard_enhancement, window_size, EnterAlternateScreen, LeaveAlternateScreen},
+};
+
+// Set from the signal handler, the frame loop exits when it sees it
+static QUIT_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Whether the terminal currently needs restoring
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+// Whether keyboard protocol flags were pushed and need popping
+static ENHANCED: AtomicBool = AtomicBool::new(false);
+
+// Puts the terminal in the state the renderer wants (alternate screen, raw
+// mode, hidden cursor, mouse reporting) and puts it back when dropped, however the program
+// leaves the frame loop
+pub struct TerminalGuard;
+
+impl TerminalGuard {
+    pub fn new() -> std::io::Result<TerminalGuard> {
+        install_panic_hook();
+        enable_raw_mode()?;
+        ACTIVE.store(true, Ordering::SeqCst);
+        // From here on the guard exists, so a failure below still restores
+        let guard = TerminalGuard;
+        execute!(stdout(), EnterAlternateScreen, Hide, EnableMouseCapture)?;
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+// Undo everything the guard set up, once: a panic restores from the hook and
+// then again from the guard while unwinding. Nothing sensible can be done if
+// restoring fails, so errors are ignored
+fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) { return; }
+    if ENHANCED.swap(false, Ordering::SeqCst) {
+        let _ = execute!(stdout(), PopKeyboardEnhancementFlags);
+    }
+    let _ = execute!(stdout(), DisableMouseCapture, ResetColor, Show, LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+}
+
+// Restore the terminal before the panic message is printed, otherwise it
+// goes to the alternate screen and disappears with it
+fn install_panic_hook() {
+    let default_hook = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        restore();
+        default_hook(info);
+    }));
+}
+
+// SIGINT, SIGTERM and SIGHUP ask the frame loop to stop instead of killing
+// the process mid-frame, so the guard gets to run. Raw mode turns Ctrl+C
+// into a key press, this covers signals sent from elsewhere
+pub fn install_signal_handler() -> Result<(), String> {
+    ctrlc::set_handler(|| QUIT_REQUESTED.store(true, Ordering::SeqCst))
+        .map_err(|e| format!("cannot install signal handler: {e}"))
+}
+
+pub fn quit_requested() -> bool {
+    QUIT_REQUESTED.load(Ordering::SeqCst)
+}
+
+// Size of one cell in pixels, if the terminal tells. The kernel's window
+// size has pixel fields that many terminals leave at zero, those are asked
+// with `CSI 16 t` instead. Needs raw mode and has to run before anything
+// else reads input, or the reply would arrive as key presses
+pub fn cell_pixel_size() -> Option<(usize, usize)> {
+    match window_size() {
+        Ok(size) if size.columns > 0 && size.rows > 0 && size.width > 0 && size.height > 0 => {
+            Some(((size.width / size.columns) as usize, (size.height / size.rows) as usize))
+        }
+        _ => query_cell_size(),
+    }
+}
+
+// Have key releases reported through the kitty keyboard protocol, where the
+// terminal has it. Returns whether it does. Waits for the terminal to
+// answer, so like `cell_pixel_size` it has to run before anything else
+// reads input
+pub fn enable_key_releases() -> bool {
+    if !supports_keyboard_enhancement().unwrap_or(false) { return false; }
+    let flags = KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES | KeyboardEnhancementFlags::REPORT_EVENT_TYPES;
+    let pushed = execute!(stdout(), PushKeyboardEnhancementFlags(flags)).is_ok();
+    ENHANCED.store(pushed, Ordering::SeqCst);
+    pushed
+}
+
+// How long to wait for the terminal to answer a query; terminals that don't
+// know it never answer
+#[cfg(unix)]
+const QUERY_TIMEOUT_MS: i32 = 100;
+
+// Send `CSI 16 t` and parse the `CSI 6 ; height ; width t` reply
+#[cfg(unix)]
+fn query_cell_size() -> Option<(usize, usize)> {
+    let mut out = stdout();
+    out.write_all(b"\x1b[16t").ok()?;
+    out.flush().ok()?;
+
+    let mut reply = Vec::new();
+    while !reply.ends_with(b"t") && reply.len() < 32 {
+        let mut fd = libc::pollfd { fd: libc::STDIN_FILENO, events: libc::POLLIN, revents: 0 };
+        // SAFETY: one valid pollfd, and a buffer of the length passed to read
+        let mut byte = 0u8;
+        let ready = unsafe { libc::poll(&mut fd, 1, QUERY_TIMEOUT_MS) };
+        if ready <= 0 || unsafe { libc::read(libc::STDIN_FILENO, (&mut byte as *mut u8).cast(), 1) } != 1 {
+            return None;
+        }
+        reply.push(byte);
+    }
+
+    let reply = std::str::from_utf8(&reply).ok()?;
+    let fields = reply.strip_prefix("\x1b[6;")?.strip_suffix('t')?;
+    let (height, width) = fields.split_once(';')?;
+    let (width, height) = (width.parse().ok()?, height.parse().ok()?);
+    (width > 0 && height > 0).then_some((width, height))
+}
+
+#[cfg(not(unix))]
+fn query_cell_size() -> Option<(usize, usize)> {
+    None
+}
@@ -0,0 +1,71 @@
+use std::path::Path;
+
+// RGB image sampled with wrapping nearest-neighbour lookups
+pub struct Texture {
+    width: usize,
+    height: usize,
+    pixels: Vec<[u8; 3]>,
+}
+
+impl Texture {
+    // Any PNG or JPEG the image crate can decode
+    pub fn load(path: &Path) -> Result<Texture, String> {
+        let img = image::open(path)
+            .map_err(|e| format!("cannot load texture {}: {e}", path.display()))?
+            .into_rgb8();
+        let (width, height) = (img.width() as usize, img.height() as usize);
+        if width == 0 || height == 0 {
+            return Err(format!("texture {} is empty", path.display()));
+        }
+
+        let pixels = img.pixels().map(|p| p.0).collect();
+        Ok(Texture { width, height, pixels })
+    }
+
+    // `v` grows upwards, like in most modelling tools
+    pub fn sample(&self, uv: [f32; 2]) -> [u8; 3] {
+        let u = uv[0].rem_euclid(1.0);
+        let v = 1.0 - uv[1].rem_euclid(1.0);
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        self.pixels[y * self.width + x]
+    }
+}
+
+// Rec. 709 luma of an 8-bit color, in 0.0..=1.0
+pub fn luminance(rgb: [u8; 3]) -> f32 {
+    (0.2126 * rgb[0] as f32 + 0.7152 * rgb[1] as f32 + 0.0722 * rgb[2] as f32) / 255.0
+}
+
+// A grid of characters stretched over a face, e.g. the pips of a die.
+// Rows are given top to bottom; spaces are see-through and leave the regular
+// shading visible
+pub struct TextArt {
+    width: usize,
+    height: usize,
+    chars: Vec<char>,
+}
+
+impl TextArt {
+    pub fn new(text: &str) -> TextArt {
+        let lines: Vec<&str> = text.lines().collect();
+        let width = lines.iter().map(|l| l.chars().count()).max().unwrap_or(0).max(1);
+        let height = lines.len().max(1);
+        let mut chars = vec![' '; width * height];
+        for (y, line) in lines.iter().enumerate() {
+            for (x, c) in line.chars().enumerate() {
+                chars[y * width + x] = c;
+            }
+        }
+        TextArt { width, height, chars }
+    }
+
+    // Same orientation as `Texture::sample`, `None` on blank cells
+    pub fn sample(&self, uv: [f32; 2]) -> Option<char> {
+        let u = uv[0].rem_euclid(1.0);
+        let v = 1.0 - uv[1].rem_euclid(1.0);
+        let x = ((u * self.width as f32) as usize).min(self.width - 1);
+        let y = ((v * self.height as f32) as usize).min(self.height - 1);
+        Some(self.chars[y * self.width + x]).filter(|c| *c != ' ')
+    }
+}
@@ -0,0 +1,224 @@
+// Timelines: animations written as what happens when, one step a line,
+// for --timeline. They are turned into keyframes on the objects they name
+// and camera cuts
+//
+//   # the cube turns round, drops into place, and the camera cuts to it
+//   at 0s..2s rotate cube 360° about y cubic
+//   at 2s..3s move cube to (0, -1, 0) bounce
+//   at 3s scale cube to 0.5
+//   at 3s cut camera to right
+//
+// A step with one time happens at once, with two it takes from the first
+// to the second, eased as keyframes are. Steps can also be separated by ;.
+// Times are in seconds, with s or ms after them or without, angles in
+// degrees. An object that is rotated stops spinning
+
+use std::f32::consts::PI;
+use std::path::Path;
+
+use crate::camera::{Camera, MAX_PITCH};
+use crate::keyframes::{Ease, EASE_NAMES};
+use crate::math::scale;
+use crate::scene::Scene;
+use crate::script::Script;
+
+// What a camera cut looks at the scene from, always towards its middle
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    Front,
+    Back,
+    Left,
+    Right,
+    Top,
+    Bottom,
+}
+
+const VIEW_NAMES: [&str; 6] = ["front", "back", "left", "right", "top", "bottom"];
+
+impl View {
+    fn parse(s: &str) -> Option<View> {
+        match s {
+            "front" => Some(View::Front),
+            "back" => Some(View::Back),
+            "left" => Some(View::Left),
+            "right" => Some(View::Right),
+            "top" => Some(View::Top),
+            "bottom" => Some(View::Bottom),
+            _ => None,
+        }
+    }
+
+    // The camera `distance` away on this side
+    pub fn camera(self, distance: f32) -> Camera {
+        let (yaw, pitch) = match self {
+            View::Front => (0.0, 0.0),
+            View::Back => (PI, 0.0),
+            View::Left => (PI / 2.0, 0.0),
+            View::Right => (-PI / 2.0, 0.0),
+            View::Top => (0.0, MAX_PITCH),
+            View::Bottom => (0.0, -MAX_PITCH),
+        };
+        let camera = Camera { position: [0.0; 3], yaw, pitch };
+        Camera { position: scale(camera.world_dir([0.0, 0.0, 1.0]), -distance), ..camera }
+    }
+}
+
+enum Change {
+    // Degrees around x, y or z
+    Rotate(f32, usize),
+    Move(String),
+    Scale(String),
+}
+
+enum Action {
+    Change { object: String, change: Change, ease: Ease },
+    Cut(View),
+}
+
+struct Step {
+    line: usize,
+    start: f32,
+    end: f32,
+    action: Action,
+}
+
+pub struct Timeline {
+    // What errors about it start with, the file it came from
+    name: String,
+    steps: Vec<Step>,
+}
+
+// What a timeline makes of a scene: statements binding the keyframes, and
+// the camera cuts in the order they happen
+pub struct Compiled {
+    pub statements: Vec<String>,
+    pub cuts: Vec<(f32, View)>,
+}
+
+impl Timeline {
+    pub fn load(path: &Path) -> Result<Timeline, String> {
+        let text = std::fs::read_to_string(path).map_err(|e| format!("cannot read {}: {e}", path.display()))?;
+        let name = path.display().to_string();
+        Timeline::parse(&text, name.clone()).map_err(|e| format!("{name}: {e}"))
+    }
+
+    fn parse(text: &str, name: String) -> Result<Timeline, String> {
+        let mut steps = Vec::new();
+        for (i, line) in text.lines().enumerate() {
+            let line_text = line.split('#').next().unwrap_or("");
+            for step in line_text.split(';').map(str::trim).filter(|s| !s.is_empty()) {
+                steps.push(parse_step(step, i + 1).map_err(|e| format!("line {}: {e}", i + 1))?);
+            }
+        }
+        // In the order they start, steps starting together in the order written
+        steps.sort_by(|a: &Step, b: &Step| a.start.total_cmp(&b.start));
+        Ok(Timeline { name, steps })
+    }
+
+    // The keyframes for the objects of `scene`, starting from what they are
+    // now. `script` knows their names
+    pub fn compile(&self, script: &Script, scene: &Scene) -> Result<Compiled, String> {
+        // For each property changed: its keys, its value after the last
+        // one and when that was
+        let mut tracks: Vec<(String, Vec<String>, String, f32)> = Vec::new();
+        let mut statements = Vec::new();
+        let mut cuts = Vec::new();
+        for step in &self.steps {
+            let at = |e: String| format!("{}: line {}: {e}", self.name, step.line);
+            let (object, change, ease) = match &step.action {
+                Action::Cut(view) => {
+                    cuts.push((step.start, *view));
+                    continue;
+                }
+                Action::Change { object, change, ease } => (object, change, ease),
+            };
+            let property = match change {
+                Change::Rotate(..) => "turn",
+                Change::Move(_) => "position",
+                Change::Scale(_) => "scale",
+            };
+            let path = format!("{object}.{property}");
+            let i = match tracks.iter().position(|(tracked, ..)| *tracked == path) {
+                Some(i) => i,
+                None => {
+                    tracks.push((path.clone(), Vec::new(), script.value(&path, scene).map_err(at)?, f32::NEG_INFINITY));
+                    if property == "turn" { statements.push(format!("{object}.spin = (0, 0, 0)")); }
+                    tracks.len() - 1
+                }
+            };
+            let (_, keys, value, end) = &mut tracks[i];
+            if step.start < *end { return Err(at(format!("starts before the last change to {path} is over"))); }
+            let next = match change {
+                Change::Rotate(degrees, axis) => {
+                    // Turns are yaw, pitch and roll: around y, x and then z
+                    let mut turn = ["0".to_string(), "0".to_string(), "0".to_string()];
+                    turn[[1, 0, 2][*axis]] = degrees.to_radians().to_string();
+                    format!("{value} + ({})", turn.join(", "))
+                }
+                Change::Move(to) | Change::Scale(to) => to.clone(),
+            };
+            // Held until the step starts, unless a step before ends there
+            if step.start > *end { keys.push(format!("{}: {value}", step.start)); }
+            keys.push(format!("{}: {next} {}", step.end, ease.name()));
+            (*value, *end) = (next, step.end);
+        }
+        statements.extend(tracks.into_iter().map(|(path, keys, ..)| format!("{path} = keys({})", keys.join(", "))));
+        Ok(Compiled { statements, cuts })
+    }
+}
+
+// at TIME[..TIME] ACTION
+fn parse_step(step: &str, line: usize) -> Result<Step, String> {
+    let rest = step.strip_prefix("at ").ok_or("a step starts with at, like at 2s")?.trim_start();
+    let (times, rest) = rest.split_once(char::is_whitespace).ok_or("a step needs something to do")?;
+    let (start, end) = match times.split_once("..") {
+        Some((start, end)) => (seconds(start)?, seconds(end)?),
+        None => (seconds(times)?, seconds(times)?),
+    };
+    if end < start { return Err(format!("{times} ends before it starts")); }
+    let words: Vec<&str> = rest.split_whitespace().collect();
+    let action = match words.as_slice() {
+        ["cut", "camera", "to", view] => Action::Cut(View::parse(view)
+            .ok_or(format!("unknown view '{view}' (expected one of: {})", VIEW_NAMES.join(", ")))?),
+        ["rotate", object, angle, "about", axis, ease @ ..] => {
+            let degrees = angle.trim_end_matches('°').trim_end_matches("deg").parse::<f32>()
+                .map_err(|_| format!("invalid angle '{angle}' (expected degrees like 90°)"))?;
+            let axis = ["x", "y", "z"].iter().position(|a| a.eq_ignore_ascii_case(axis))
+                .ok_or(format!("invalid axis '{axis}' (expected x, y or z)"))?;
+            Action::Change { object: object.to_string(), change: Change::Rotate(degrees, axis), ease: easing(ease)? }
+        }
+        [verb @ ("move" | "scale"), object, "to", ..] => {
+            // The value is everything after `to` but an easing at the end
+            let value = rest.split_once(" to ").map_or("", |(_, value)| value.trim());
+            let (value, ease) = match value.rsplit_once(char::is_whitespace) {
+                Some((value, ease)) if Ease::parse(ease).is_some() => (value.trim(), easing(&[ease])?),
+                _ => (value, Ease::Linear),
+            };
+            if value.is_empty() { return Err(format!("{verb} needs a value after to")); }
+            let change = if *verb == "move" { Change::Move(value.to_string()) } else { Change::Scale(value.to_string()) };
+            Action::Change { object: object.to_string(), change, ease }
+        }
+        _ => return Err(format!("cannot tell what '{rest}' does (expected rotate OBJECT ANGLE about AXIS, \
+            move OBJECT to (X, Y, Z), scale OBJECT to SIZE or cut camera to VIEW)")),
+    };
+    Ok(Step { line, start, end, action })
+}
+
+// An easing given after a step, linear if there is none
+fn easing(words: &[&str]) -> Result<Ease, String> {
+    match words {
+        [] => Ok(Ease::Linear),
+        [name] => Ease::parse(name).ok_or(format!("unknown easing '{name}' (expected one of: {})", EASE_NAMES.join(", "))),
+        _ => Err(format!("unexpected '{}' at the end", words.join(" "))),
+    }
+}
+
+// Seconds in 2s, 500ms or 2
+fn seconds(s: &str) -> Result<f32, String> {
+    let (number, scale) = match s.strip_suffix("ms") {
+        Some(number) => (number, 0.001),
+        None => (s.strip_suffix('s').unwrap_or(s), 1.0),
+    };
+    number.parse::<f32>().ok().map(|n| n * scale).filter(|n| n.is_finite() && *n >= 0.0)
+        .ok_or(format!("invalid time '{s}' (expected seconds like 2s or 500ms)"))
+}
@@ -0,0 +1,68 @@
+// Undoing and redoing edits, so trying things out on the scene loses
+// nothing. Each edit keeps the objects, lights and command-line bindings
+// as they were before it, whole, which is cheap next to a frame's work
+
+use crate::scene::{Light, Object, Scene};
+use crate::script::Script;
+
+// Edits remembered at most, the oldest are dropped first
+const MAX_EDITS: usize = 100;
+
+// What an edit can change
+pub struct Snapshot {
+    objects: Vec<Object>,
+    lights: Vec<Light>,
+    script: Script,
+}
+
+impl Snapshot {
+    pub fn take(scene: &Scene, script: &Script) -> Snapshot {
+        Snapshot { objects: scene.objects.clone(), lights: scene.lights.clone(), script: script.clone() }
+    }
+
+    // Put `scene` and `script` back as they were, returning how they are now
+    fn restore(self, scene: &mut Scene, script: &mut Script) -> Snapshot {
+        let now = Snapshot::take(scene, script);
+        (scene.objects, scene.lights, *script) = (self.objects, self.lights, self.script);
+        now
+    }
+}
+
+pub struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    pub fn new() -> History {
+        History { undo: Vec::new(), redo: Vec::new() }
+    }
+
+    // An edit was made to the scene `before` showed. What was undone can't
+    // be redone after it
+    pub fn record(&mut self, before: Snapshot) {
+        if self.undo.len() == MAX_EDITS { self.undo.remove(0); }
+        self.undo.push(before);
+        self.redo.clear();
+    }
+
+    // Go back before the last edit, or `false` when there is none
+    pub fn undo(&mut self, scene: &mut Scene, script: &mut Script) -> bool {
+        let Some(before) = self.undo.pop() else { return false; };
+        self.redo.push(before.restore(scene, script));
+        true
+    }
+
+    // Make the last undone edit again, or `false` when there is none
+    pub fn redo(&mut self, scene: &mut Scene, script: &mut Script) -> bool {
+        let Some(after) = self.redo.pop() else { return false; };
+        self.undo.push(after.restore(scene, script));
+        true
+    }
+
+    // Forget everything, for a scene the edits weren't made to
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
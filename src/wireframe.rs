@@ -0,0 +1,88 @@
+use clap::ValueEnum;
+use crossterm::style::Color;
+
+use crate::framebuffer::{Cell, FrameBuffer};
+use crate::math::{dot, get_face_normal, sub};
+use crate::render::{fill_face, for_each_line_cell, slope_char, Projection, RenderSettings};
+use crate::scene::Scene;
+
+// Character and color for edge cells hidden behind a face
+const FAINT_CHAR: char = '.';
+const FAINT_COLOR: Color = Color::DarkGrey;
+// How far behind the nearest face an edge cell has to be to count as hidden,
+// so edges are not hidden by the faces they bound
+const HIDDEN_BIAS: f32 = 0.1;
+// Character for vertices in points mode
+const POINT_CHAR: char = 'o';
+
+// What happens to edges that opaque faces cover
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum HiddenLines {
+    Show,
+    Faint,
+    Hide,
+}
+
+// Edges of every object. Opaque faces are first rasterized into the depth
+// buffer only, so edge cells behind them can be told apart.
+pub fn render_wireframe(scene: &Scene, proj: &Projection, t: f32, settings: &RenderSettings) -> FrameBuffer {
+    let mut fb = FrameBuffer::new(proj.width, proj.height);
+    let transformed: Vec<Vec<[f32; 3]>> = scene.objects.iter().map(|o| o.transformed(t)).collect();
+
+    if settings.hidden_lines != HiddenLines::Show {
+        for (object, verts) in scene.objects.iter().zip(&transformed) {
+            if object.material.opacity < 1.0 { continue; }
+            for face in &object.mesh.faces {
+                let face_verts: Vec<[f32; 3]> = face.indices.iter().map(|i| verts[*i]).collect();
+                let normal = get_face_normal(face_verts[0], face_verts[1], face_verts[2]);
+                if dot(normal, sub(proj.eye(), face_verts[0])) <= 0.0 { continue; }
+                fill_face(&mut fb, proj, &face_verts, |_| Cell::EMPTY);
+            }
+        }
+    }
+    let surface = fb.depth.clone();
+    // Farthest surface depth around a cell. Faces seen at grazing angles
+    // change depth a lot within one cell, so an edge only counts as hidden
+    // when the faces all around it are in front
+    let (width, height) = (fb.width as i32, fb.height as i32);
+    let farthest = |x: i32, y: i32| {
+        let mut far = f32::MIN;
+        for ny in y - 1..=y + 1 {
+            for nx in x - 1..=x + 1 {
+                let inside = nx >= 0 && ny >= 0 && nx < width && ny < height;
+                far = far.max(if inside { surface[(ny * width + nx) as usize] } else { f32::MAX });
+            }
+        }
+        far
+    };
+
+    for (object, verts) in scene.objects.iter().zip(&transformed) {
+        for (i, j) in &object.mesh.edges {
+            let (Some(p1), Some(p2)) = (proj.project_f(verts[*i]), proj.project_f(verts[*j])) else { continue; };
+            let (a, b) = ((p1.0 as i32, p1.1 as i32, p1.2), (p2.0 as i32, p2.1 as i32, p2.2));
+            for_each_line_cell(a, b, |x, y, z| {
+                let Some(idx) = fb.index(x, y) else { return; };
+                if z - HIDDEN_BIAS <= farthest(x, y) {
+                    fb.plot(x, y, z - HIDDEN_BIAS, Cell::glyph(slope_char(p1, p2, x, y, proj.aspect), Color::White));
+                } else if settings.hidden_lines == HiddenLines::Faint && fb.cells[idx].is_empty() {
+                    fb.cells[idx] = Cell::glyph(FAINT_CHAR, FAINT_COLOR);
+                }
+            });
+        }
+    }
+
+    fb
+}
+
+// Every vertex as a single character, nearer ones drawn over farther ones
+pub fn render_points(scene: &Scene, proj: &Projection, t: f32) -> FrameBuffer {
+    let mut fb = FrameBuffer::new(proj.width, proj.height);
+    for object in &scene.objects {
+        for v in object.transformed(t) {
+            if let Some((x, y, z)) = proj.project(v) {
+                fb.plot(x, y, z, Cell::glyph(POINT_CHAR, Color::White));
+            }
+        }
+    }
+    fb
+}